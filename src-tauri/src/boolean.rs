@@ -0,0 +1,61 @@
+//! アウトラインの結合（ブーリアン演算の和）
+//! 隣り合う文字の重なりや、バリアブルフォントの重なった輪郭を取り除く
+
+use i_overlay::core::fill_rule::FillRule;
+use i_overlay::float::simplify::SimplifyShape;
+
+use crate::outline::{flatten, GlyphOutline, Segment, TextLayout};
+
+/// 曲線を許容誤差tolerance以内の折れ線にし、ノンゼロ規則で塗られる範囲に正規化した輪郭
+fn normalized_contours(segments: &[Segment], tolerance: f64) -> Vec<Vec<[f64; 2]>> {
+    let contours: Vec<Vec<[f64; 2]>> = flatten(segments, tolerance)
+        .into_iter()
+        .map(|polyline| polyline.points.into_iter().map(|(x, y)| [x, y]).collect())
+        .collect();
+    contours
+        .simplify_shape(FillRule::NonZero)
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// 複数のアウトラインを1つの複合パスに結合する（曲線は折れ線になる）
+/// 輪郭の向きがフォントごとに異なっても打ち消し合わないよう、アウトラインごとに正規化してから結合する
+pub fn union<'a>(outlines: impl IntoIterator<Item = &'a [Segment]>, tolerance: f64) -> Vec<Segment> {
+    let contours: Vec<Vec<[f64; 2]>> = outlines
+        .into_iter()
+        .flat_map(|segments| normalized_contours(segments, tolerance))
+        .collect();
+
+    let mut segments = Vec::new();
+    for contour in contours.simplify_shape(FillRule::NonZero).into_iter().flatten() {
+        let mut points = contour.into_iter();
+        let Some([x, y]) = points.next() else {
+            continue;
+        };
+        segments.push(Segment::MoveTo(x, y));
+        segments.extend(points.map(|[x, y]| Segment::LineTo(x, y)));
+        segments.push(Segment::Close);
+    }
+    segments
+}
+
+/// 全文字のアウトラインを結合した1つのアウトラインだけを持つレイアウト
+pub fn union_layout(layout: &TextLayout, tolerance: f64) -> TextLayout {
+    let segments = union(layout.glyphs.iter().map(|g| g.segments.as_slice()), tolerance);
+    let glyphs = if segments.is_empty() {
+        Vec::new()
+    } else {
+        vec![GlyphOutline {
+            char_index: 0,
+            text: layout.glyphs.iter().map(|g| g.text.as_str()).collect(),
+            font_name: layout.glyphs.first().map(|g| g.font_name.clone()).unwrap_or_default(),
+            segments,
+        }]
+    };
+    TextLayout {
+        width: layout.width,
+        height: layout.height,
+        glyphs,
+    }
+}
@@ -0,0 +1,409 @@
+//! システムフォントと追加登録したファイル・フォルダのカタログ（フェイスごとの名前・プロパティ・収録文字）を
+//! 走査し、アプリのキャッシュフォルダのインデックスファイルにキャッシュする
+//! 起動時に裏で、または更新を指示されたときに走査し直し、
+//! フォントファイルの追加・削除と、更新日時・サイズが変わったものだけを反映する
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+use font_kit::font::Font;
+use font_kit::source::SystemSource;
+use ttf_parser::Face;
+
+use crate::coverage::mapped_codepoints;
+use crate::{face_name, handle_location, parse_face, read_font_data, style_name, FontLocation};
+
+pub const CATALOG_FILENAME: &str = "font-scope-catalog.json";
+/// 追加登録したフォルダから読み込むフォントファイルの拡張子
+const FONT_EXTENSIONS: [&str; 6] = ["ttf", "otf", "ttc", "otc", "woff", "woff2"];
+/// 保存形式を変えたら上げる（古いインデックスは読み捨てて作り直す）
+const CATALOG_VERSION: u32 = 1;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct CatalogFace {
+    pub location: FontLocation,
+    pub family: String,
+    pub full_name: String,
+    pub postscript_name: Option<String>,
+    pub weight: f32,
+    pub stretch: f32,
+    /// "normal" | "italic" | "oblique"
+    pub style: String,
+    pub has_vertical_metrics: bool,
+    pub monospaced: bool,
+    pub variable: bool,
+    /// 追加登録したファイル・フォルダから読み込んだフェイス
+    #[serde(default)]
+    pub user_font: bool,
+    /// 収録文字のビットマップ（256文字ごとのブロック番号 → 256ビット、空のブロックは持たない）
+    pub coverage: BTreeMap<u32, [u64; 4]>,
+}
+
+impl CatalogFace {
+    pub fn has_char(&self, c: char) -> bool {
+        let cp = c as u32;
+        self.coverage
+            .get(&(cp >> 8))
+            .is_some_and(|bits| bits[(cp as usize >> 6) & 3] & (1 << (cp & 63)) != 0)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+struct CatalogFile {
+    /// 更新日時（UNIX時間の秒）
+    modified: u64,
+    size: u64,
+    /// 走査したフェイス番号（読めなかったフェイスも含む）
+    font_indices: BTreeSet<u32>,
+    faces: Vec<CatalogFace>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default, Clone)]
+pub struct FontCatalog {
+    version: u32,
+    /// 追加登録したフォントファイル・フォルダ（インストールされていないフォント）
+    #[serde(default)]
+    sources: Vec<String>,
+    /// フォントファイルのパス → ファイル内のフェイス
+    files: BTreeMap<String, CatalogFile>,
+}
+
+#[derive(serde::Serialize)]
+pub struct CatalogRefreshSummary {
+    pub files: usize,
+    pub faces: usize,
+    /// 新規・更新により読み直したファイル数
+    pub updated_files: usize,
+    pub removed_files: usize,
+}
+
+/// ファイルの更新日時とサイズ
+fn file_stamp(path: &str) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some((modified, metadata.len()))
+}
+
+fn coverage_bitmap(face: &Face) -> BTreeMap<u32, [u64; 4]> {
+    let mut coverage: BTreeMap<u32, [u64; 4]> = BTreeMap::new();
+    for cp in mapped_codepoints(face) {
+        let bits = coverage.entry(cp >> 8).or_default();
+        bits[(cp as usize >> 6) & 3] |= 1 << (cp & 63);
+    }
+    coverage
+}
+
+/// 追加登録したパス以下のフォントファイルを列挙（フォルダはサブフォルダも含む）
+fn collect_source_files(path: &Path, files: &mut Vec<String>) {
+    if path.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            collect_source_files(&entry.path(), files);
+        }
+    } else if path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| FONT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+    {
+        files.push(path.to_string_lossy().to_string());
+    }
+}
+
+/// 1つのフォントファイル内のフェイスを読む
+/// font_indicesが無ければファイル内の全フェイス（コレクションの全メンバー）を読む
+fn scan_file(
+    path: &str,
+    font_indices: Option<&BTreeSet<u32>>,
+    user_font: bool,
+) -> (BTreeSet<u32>, Vec<CatalogFace>) {
+    let location = FontLocation {
+        path: path.to_string(),
+        font_index: 0,
+    };
+    let Ok(font_data) = read_font_data(&location) else {
+        return (font_indices.cloned().unwrap_or_default(), Vec::new());
+    };
+    let font_data = Arc::new(font_data);
+
+    let font_indices = font_indices.cloned().unwrap_or_else(|| {
+        (0..ttf_parser::fonts_in_collection(&font_data).unwrap_or(1)).collect()
+    });
+
+    let faces = font_indices
+        .iter()
+        .filter_map(|&font_index| {
+            let face = parse_face(&font_data, font_index).ok()?;
+            let font = Font::from_bytes(font_data.clone(), font_index).ok()?;
+            let properties = font.properties();
+            let tables = face.tables();
+
+            // 名前の無いフォントはファイル名で一覧に出す
+            let file_stem = || {
+                Path::new(path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            };
+            let family = Some(font.family_name())
+                .filter(|name| !name.is_empty())
+                .or_else(|| face_name(&face, ttf_parser::name_id::FAMILY))
+                .unwrap_or_else(file_stem);
+            let full_name = Some(font.full_name())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| family.clone());
+
+            Some(CatalogFace {
+                location: FontLocation {
+                    path: path.to_string(),
+                    font_index,
+                },
+                family,
+                full_name,
+                postscript_name: font.postscript_name(),
+                weight: properties.weight.0,
+                stretch: properties.stretch.0,
+                style: style_name(properties.style).to_string(),
+                has_vertical_metrics: tables.vhea.is_some() && tables.vmtx.is_some(),
+                monospaced: face.is_monospaced(),
+                variable: face.is_variable(),
+                user_font,
+                coverage: coverage_bitmap(&face),
+            })
+        })
+        .collect();
+
+    (font_indices, faces)
+}
+
+impl FontCatalog {
+    /// インデックスファイルを読む（無い・壊れている・形式が古い場合は空）
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<FontCatalog>(&json).ok())
+            .filter(|catalog| catalog.version == CATALOG_VERSION)
+            .unwrap_or_default()
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create catalog directory: {}", e))?;
+        }
+        let json = serde_json::to_string(self).map_err(|e| format!("Failed to serialize font catalog: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write font catalog: {}", e))
+    }
+
+    /// システムのフォントと追加登録したパスを列挙し、追加・更新されたファイルだけを読み直す
+    fn refresh(&mut self) -> CatalogRefreshSummary {
+        self.version = CATALOG_VERSION;
+
+        // パス → フェイス番号（追加登録したファイルはNone = ファイル内の全フェイス）
+        let mut system_files: BTreeMap<String, BTreeSet<u32>> = BTreeMap::new();
+        if let Ok(handles) = SystemSource::new().all_fonts() {
+            for handle in handles {
+                if let Ok(location) = handle_location(&handle) {
+                    system_files.entry(location.path).or_default().insert(location.font_index);
+                }
+            }
+        }
+        let mut font_files: BTreeMap<String, Option<BTreeSet<u32>>> = system_files
+            .into_iter()
+            .map(|(path, font_indices)| (path, Some(font_indices)))
+            .collect();
+        let mut source_files = Vec::new();
+        for source in &self.sources {
+            collect_source_files(Path::new(source), &mut source_files);
+        }
+        for path in source_files {
+            // インストール済みのフォントと同じファイルはシステム側の情報を使う
+            font_files.entry(path).or_insert(None);
+        }
+
+        let before = self.files.len();
+        self.files.retain(|path, _| font_files.contains_key(path));
+        let removed_files = before - self.files.len();
+
+        let mut updated_files = 0;
+        for (path, font_indices) in &font_files {
+            let Some((modified, size)) = file_stamp(path) else {
+                self.files.remove(path);
+                continue;
+            };
+
+            let user_font = font_indices.is_none();
+            let unchanged = self.files.get(path).is_some_and(|file| {
+                file.modified == modified
+                    && file.size == size
+                    && font_indices.as_ref().is_none_or(|indices| file.font_indices == *indices)
+                    && file.faces.iter().all(|face| face.user_font == user_font)
+            });
+            if unchanged {
+                continue;
+            }
+
+            let (font_indices, faces) = scan_file(path, font_indices.as_ref(), user_font);
+            self.files.insert(
+                path.clone(),
+                CatalogFile {
+                    modified,
+                    size,
+                    font_indices,
+                    faces,
+                },
+            );
+            updated_files += 1;
+        }
+
+        CatalogRefreshSummary {
+            files: self.files.len(),
+            faces: self.faces().count(),
+            updated_files,
+            removed_files,
+        }
+    }
+
+    pub fn faces(&self) -> impl Iterator<Item = &CatalogFace> {
+        self.files.values().flat_map(|file| file.faces.iter())
+    }
+
+    /// ファミリー名（大文字小文字を区別しない）から標準的なフェイスを選ぶ
+    /// normalスタイル → ウェイト400に近いもの → 幅1.0に近いものの順に優先
+    pub fn find_family(&self, family: &str) -> Option<&CatalogFace> {
+        self.faces()
+            .filter(|face| face.family.eq_ignore_ascii_case(family))
+            .min_by(|a, b| {
+                let key = |f: &CatalogFace| {
+                    (
+                        f.style != "normal",
+                        (f.weight - 400.0).abs(),
+                        (f.stretch - 1.0).abs(),
+                    )
+                };
+                let (a, b) = (key(a), key(b));
+                a.0.cmp(&b.0)
+                    .then_with(|| a.1.total_cmp(&b.1))
+                    .then_with(|| a.2.total_cmp(&b.2))
+            })
+    }
+
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    pub fn find_postscript_name(&self, postscript_name: &str) -> Option<&CatalogFace> {
+        self.faces()
+            .find(|face| face.postscript_name.as_deref() == Some(postscript_name))
+    }
+}
+
+/// Tauriで管理するカタログ
+/// 走査は1つずつ行い、走査中も前回のカタログを読めるようにする
+#[derive(Default, Clone)]
+pub struct CatalogState(Arc<CatalogInner>);
+
+#[derive(Default)]
+struct CatalogInner {
+    /// インデックスファイルのパス（Noneなら保存しない）
+    index_path: Option<PathBuf>,
+    catalog: Mutex<Option<Arc<FontCatalog>>>,
+    /// 走査中は保持する
+    scanning: Mutex<()>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl CatalogState {
+    /// 前回のインデックスがあれば読み込み、走査が終わるまではそれを使う
+    pub fn new(index_path: Option<PathBuf>) -> Self {
+        let catalog = index_path
+            .as_deref()
+            .map(FontCatalog::load)
+            .filter(|catalog| !catalog.files.is_empty())
+            .map(Arc::new);
+        Self(Arc::new(CatalogInner {
+            index_path,
+            catalog: Mutex::new(catalog),
+            ..CatalogInner::default()
+        }))
+    }
+
+    pub fn with<R>(&self, f: impl FnOnce(&FontCatalog) -> R) -> R {
+        f(&self.current())
+    }
+
+    fn current(&self) -> Arc<FontCatalog> {
+        if let Some(catalog) = lock(&self.0.catalog).clone() {
+            return catalog;
+        }
+
+        // まだ一度も走査していなければ、終わるまで待つ（起動時に始めた走査ならその結果を使う）
+        let _scanning = lock(&self.0.scanning);
+        if let Some(catalog) = lock(&self.0.catalog).clone() {
+            return catalog;
+        }
+        match self.scan(|_| Ok(())) {
+            Ok((catalog, _)) => catalog,
+            Err(_) => Arc::default(),
+        }
+    }
+
+    /// 裏でフォントの追加・削除・更新を反映する
+    pub fn refresh_in_background(&self) {
+        let state = self.clone();
+        thread::spawn(move || {
+            let _ = state.refresh();
+        });
+    }
+
+    /// フォントの追加・削除・更新を反映してインデックスを保存
+    pub fn refresh(&self) -> Result<CatalogRefreshSummary, String> {
+        self.update_sources(|_| Ok(()))
+    }
+
+    /// 追加登録したファイル・フォルダの一覧を変更してから更新する
+    pub fn update_sources(
+        &self,
+        f: impl FnOnce(&mut Vec<String>) -> Result<(), String>,
+    ) -> Result<CatalogRefreshSummary, String> {
+        let _scanning = lock(&self.0.scanning);
+        self.scan(f).map(|(_, summary)| summary)
+    }
+
+    /// scanningを保持した状態で呼ぶ
+    fn scan(
+        &self,
+        f: impl FnOnce(&mut Vec<String>) -> Result<(), String>,
+    ) -> Result<(Arc<FontCatalog>, CatalogRefreshSummary), String> {
+        let mut catalog = match lock(&self.0.catalog).as_deref() {
+            Some(catalog) => catalog.clone(),
+            None => self.0.index_path.as_deref().map(FontCatalog::load).unwrap_or_default(),
+        };
+        let sources = catalog.sources.clone();
+        f(&mut catalog.sources)?;
+        let summary = catalog.refresh();
+
+        if summary.updated_files > 0 || summary.removed_files > 0 || catalog.sources != sources {
+            if let Some(path) = &self.0.index_path {
+                // 保存できなくても次回起動時に走査し直すだけなので、カタログの更新は続ける
+                let _ = catalog.save(path);
+            }
+        }
+
+        let catalog = Arc::new(catalog);
+        *lock(&self.0.catalog) = Some(catalog.clone());
+        Ok((catalog, summary))
+    }
+}
@@ -0,0 +1,404 @@
+//! CFFのINDEX・DICTを読み書きするための補助関数
+
+/// DICTの1項目（演算子とそのオペランド）
+pub struct DictEntry<'a> {
+    /// エスケープ付きの演算子は0x0C00 | 演算子
+    pub operator: u16,
+    /// 整数オペランド（実数は0として扱う）
+    pub operands: Vec<i32>,
+    /// オペランドの元のバイト列（書き換えない項目をそのまま書き戻すため）
+    pub raw_operands: &'a [u8],
+}
+
+/// INDEXを読み、各要素と次の構造の開始位置を返す
+pub fn index(data: &[u8], offset: usize) -> Option<(Vec<&[u8]>, usize)> {
+    read_index(data, offset, 2)
+}
+
+/// CFF2のINDEX（要素数が4バイト）を読む
+pub fn index2(data: &[u8], offset: usize) -> Option<(Vec<&[u8]>, usize)> {
+    read_index(data, offset, 4)
+}
+
+fn read_index(data: &[u8], offset: usize, count_size: usize) -> Option<(Vec<&[u8]>, usize)> {
+    let count_bytes = data.get(offset..offset + count_size)?;
+    let count = count_bytes.iter().fold(0usize, |acc, &b| acc << 8 | b as usize);
+    if count == 0 {
+        return Some((Vec::new(), offset + count_size));
+    }
+
+    let off_size = *data.get(offset + count_size)? as usize;
+    let offsets_start = offset + count_size + 1;
+    let read_offset = |i: usize| {
+        let start = offsets_start + i * off_size;
+        let bytes = data.get(start..start + off_size)?;
+        Some(bytes.iter().fold(0usize, |acc, &b| acc << 8 | b as usize))
+    };
+
+    // オフセットは1始まり
+    let data_start = offsets_start + (count + 1) * off_size - 1;
+    let mut items = Vec::with_capacity(count);
+    for i in 0..count {
+        let start = data_start + read_offset(i)?;
+        let end = data_start + read_offset(i + 1)?;
+        items.push(data.get(start..end)?);
+    }
+
+    Some((items, data_start + read_offset(count)?))
+}
+
+/// 要素を並べたINDEXを作る
+pub fn write_index(items: &[&[u8]]) -> Vec<u8> {
+    encode_index(items, 2)
+}
+
+/// 要素を並べたCFF2のINDEXを作る
+pub fn write_index2(items: &[&[u8]]) -> Vec<u8> {
+    encode_index(items, 4)
+}
+
+fn encode_index(items: &[&[u8]], count_size: usize) -> Vec<u8> {
+    let count = (items.len() as u32).to_be_bytes();
+    if items.is_empty() {
+        return count[4 - count_size..].to_vec();
+    }
+
+    let data_size: usize = items.iter().map(|item| item.len()).sum();
+    let off_size = match data_size + 1 {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFF_FFFF => 3,
+        _ => 4,
+    };
+
+    let mut out = Vec::with_capacity(count_size + 1 + (items.len() + 1) * off_size + data_size);
+    out.extend_from_slice(&count[4 - count_size..]);
+    out.push(off_size as u8);
+    // オフセットは1始まり
+    let offsets = std::iter::once(1).chain(items.iter().scan(1usize, |offset, item| {
+        *offset += item.len();
+        Some(*offset)
+    }));
+    for offset in offsets {
+        let bytes = (offset as u32).to_be_bytes();
+        out.extend_from_slice(&bytes[4 - off_size..]);
+    }
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out
+}
+
+/// DICTを項目ごとに分解する
+pub fn dict_entries(dict: &[u8]) -> Option<Vec<DictEntry<'_>>> {
+    let mut entries = Vec::new();
+    let mut operands = Vec::new();
+    let mut operand_start = 0;
+    let mut i = 0;
+
+    while i < dict.len() {
+        let b0 = dict[i];
+        match b0 {
+            // 22〜24はCFF2の演算子（vsindex・blend・vstore）
+            0..=24 => {
+                let raw_operands = &dict[operand_start..i];
+                let operator = if b0 == 12 {
+                    i += 1;
+                    0x0C00 | *dict.get(i)? as u16
+                } else {
+                    b0 as u16
+                };
+                entries.push(DictEntry {
+                    operator,
+                    operands: std::mem::take(&mut operands),
+                    raw_operands,
+                });
+                i += 1;
+                operand_start = i;
+            }
+            28 => {
+                operands.push(i16::from_be_bytes([*dict.get(i + 1)?, *dict.get(i + 2)?]) as i32);
+                i += 3;
+            }
+            29 => {
+                let bytes = dict.get(i + 1..i + 5)?;
+                operands.push(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+                i += 5;
+            }
+            30 => {
+                // 実数は整数として扱わない（元のバイト列はraw_operandsに残る）
+                i += 1;
+                while i < dict.len() && dict[i] & 0x0F != 0x0F && dict[i] >> 4 != 0x0F {
+                    i += 1;
+                }
+                operands.push(0);
+                i += 1;
+            }
+            32..=246 => {
+                operands.push(b0 as i32 - 139);
+                i += 1;
+            }
+            247..=250 => {
+                operands.push((b0 as i32 - 247) * 256 + *dict.get(i + 1)? as i32 + 108);
+                i += 2;
+            }
+            251..=254 => {
+                operands.push(-(b0 as i32 - 251) * 256 - *dict.get(i + 1)? as i32 - 108);
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+
+    Some(entries)
+}
+
+/// DICTから指定した演算子の整数オペランドを取り出す
+pub fn dict_operands(dict: &[u8], operator: u16) -> Option<Vec<i32>> {
+    dict_entries(dict)?
+        .into_iter()
+        .find(|entry| entry.operator == operator)
+        .map(|entry| entry.operands)
+}
+
+/// DICTに項目を書き込む
+/// 整数オペランドは常に5バイト形式にする（値が変わっても長さが変わらないよう、オフセットの書き換えに使う）
+pub fn write_dict_entry(out: &mut Vec<u8>, operator: u16, operands: &[i32]) {
+    for operand in operands {
+        out.push(29);
+        out.extend_from_slice(&operand.to_be_bytes());
+    }
+    write_operator(out, operator);
+}
+
+/// 元のオペランドのまま項目を書き戻す
+pub fn write_raw_dict_entry(out: &mut Vec<u8>, entry: &DictEntry) {
+    out.extend_from_slice(entry.raw_operands);
+    write_operator(out, entry.operator);
+}
+
+fn write_operator(out: &mut Vec<u8>, operator: u16) {
+    if operator & 0xFF00 == 0x0C00 {
+        out.push(12);
+    }
+    out.push(operator as u8);
+}
+
+/// サブルーチンの番号に足す値（要素数で決まる）
+fn subr_bias(count: usize) -> i32 {
+    match count {
+        0..1240 => 107,
+        1240..33900 => 1131,
+        _ => 32768,
+    }
+}
+
+/// CharString（Type 2・CFF2）を読んで、呼び出されるサブルーチンを調べる
+pub struct SubrCollector<'a> {
+    global_subrs: &'a [&'a [u8]],
+    /// CFF2のItemVariationDataごとのリージョン数（blendの引数の数を求めるため）
+    region_counts: &'a [usize],
+    cff2: bool,
+    /// 呼び出されたグローバルサブルーチンの番号
+    pub global_used: std::collections::BTreeSet<usize>,
+}
+
+/// CharStringを読んでいる途中の状態（サブルーチンの呼び出しをまたいで引き継ぐ）
+struct CharStringState {
+    stack: Vec<i32>,
+    /// 宣言されたステムヒントの数（hintmaskの長さが決まる）
+    stems: usize,
+    vsindex: usize,
+}
+
+impl<'a> SubrCollector<'a> {
+    pub fn new(global_subrs: &'a [&'a [u8]], region_counts: &'a [usize], cff2: bool) -> Self {
+        Self {
+            global_subrs,
+            region_counts,
+            cff2,
+            global_used: Default::default(),
+        }
+    }
+
+    /// グリフのCharStringを読み、呼び出されたローカルサブルーチンの番号をlocal_usedに加える
+    /// 解釈できない命令があればNone
+    pub fn collect(
+        &mut self,
+        char_string: &[u8],
+        local_subrs: &[&[u8]],
+        vsindex: usize,
+        local_used: &mut std::collections::BTreeSet<usize>,
+    ) -> Option<()> {
+        let mut state = CharStringState {
+            stack: Vec::new(),
+            stems: 0,
+            vsindex,
+        };
+        self.run(char_string, local_subrs, local_used, &mut state, 0).map(|_| ())
+    }
+
+    /// endcharで終わればtrue
+    fn run(
+        &mut self,
+        code: &[u8],
+        local_subrs: &[&[u8]],
+        local_used: &mut std::collections::BTreeSet<usize>,
+        state: &mut CharStringState,
+        depth: usize,
+    ) -> Option<bool> {
+        // サブルーチンの入れ子は10段まで
+        if depth > 10 {
+            return None;
+        }
+
+        let mut i = 0;
+        while i < code.len() {
+            let b0 = code[i];
+            match b0 {
+                28 => {
+                    state.stack.push(i16::from_be_bytes([*code.get(i + 1)?, *code.get(i + 2)?]) as i32);
+                    i += 3;
+                }
+                32..=246 => {
+                    state.stack.push(b0 as i32 - 139);
+                    i += 1;
+                }
+                247..=250 => {
+                    state.stack.push((b0 as i32 - 247) * 256 + *code.get(i + 1)? as i32 + 108);
+                    i += 2;
+                }
+                251..=254 => {
+                    state.stack.push(-(b0 as i32 - 251) * 256 - *code.get(i + 1)? as i32 - 108);
+                    i += 2;
+                }
+                // 16.16固定小数点（整数部だけを使う）
+                255 => {
+                    let bytes = code.get(i + 1..i + 5)?;
+                    state.stack.push(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) >> 16);
+                    i += 5;
+                }
+                // hstem・vstem・hstemhm・vstemhm
+                1 | 3 | 18 | 23 => {
+                    state.stems += state.stack.len() / 2;
+                    state.stack.clear();
+                    i += 1;
+                }
+                // hintmask・cntrmask（直前の引数は暗黙のvstem）の後ろにステム数分のビット列が続く
+                19 | 20 => {
+                    state.stems += state.stack.len() / 2;
+                    state.stack.clear();
+                    i += 1 + state.stems.div_ceil(8);
+                }
+                // callsubr・callgsubr
+                10 | 29 => {
+                    let subrs = if b0 == 10 { local_subrs } else { self.global_subrs };
+                    let index = usize::try_from(state.stack.pop()? + subr_bias(subrs.len())).ok()?;
+                    let subr = subrs.get(index)?;
+                    if b0 == 10 {
+                        local_used.insert(index);
+                    } else {
+                        self.global_used.insert(index);
+                    }
+                    if self.run(subr, local_subrs, local_used, state, depth + 1)? {
+                        return Some(true);
+                    }
+                    i += 1;
+                }
+                // return
+                11 if !self.cff2 => return Some(false),
+                // endchar
+                14 if !self.cff2 => return Some(true),
+                // vsindex
+                15 if self.cff2 => {
+                    state.vsindex = usize::try_from(state.stack.pop()?).ok()?;
+                    i += 1;
+                }
+                // blend: n個の値とそれぞれのリージョンごとの差分からn個の値を作る
+                16 if self.cff2 => {
+                    let count = usize::try_from(state.stack.pop()?).ok()?;
+                    let regions = *self.region_counts.get(state.vsindex)?;
+                    let length = state.stack.len().checked_sub(count * regions)?;
+                    state.stack.truncate(length);
+                    i += 1;
+                }
+                // 2バイトの演算子（flexなど）
+                12 => {
+                    state.stack.clear();
+                    i += 2;
+                }
+                _ => {
+                    state.stack.clear();
+                    i += 1;
+                }
+            }
+        }
+        Some(false)
+    }
+}
+
+/// CFF2のVariationStore（ItemVariationStore）のItemVariationDataごとのリージョン数
+pub fn region_counts(store: &[u8]) -> Option<Vec<usize>> {
+    let count = crate::sfnt::read_u16(store, 6)? as usize;
+    (0..count)
+        .map(|i| {
+            let offset = crate::sfnt::read_u32(store, 8 + i * 4)? as usize;
+            crate::sfnt::read_u16(store, offset + 4).map(usize::from)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    type WriteFn = fn(&[&[u8]]) -> Vec<u8>;
+    type ReadFn = fn(&[u8], usize) -> Option<(Vec<&[u8]>, usize)>;
+
+    #[test]
+    fn index_round_trips_for_each_offset_size() {
+        let large = vec![7u8; 0x1_0000];
+        let cases: [Vec<&[u8]>; 4] = [
+            vec![],
+            vec![b"a", b"", b"bc"],
+            vec![&large[..300], b"x"],
+            vec![&large, b"", b"tail"],
+        ];
+        for items in cases {
+            for (write, read) in [(write_index as WriteFn, index as ReadFn), (write_index2, index2)] {
+                let mut data = vec![0xAA];
+                data.extend(write(&items));
+                let (decoded, end) = read(&data, 1).unwrap();
+                assert_eq!(decoded, items);
+                assert_eq!(end, data.len());
+            }
+        }
+    }
+
+    #[test]
+    fn dict_round_trips_integer_operands() {
+        let mut dict = Vec::new();
+        write_dict_entry(&mut dict, 17, &[123_456]);
+        write_dict_entry(&mut dict, 18, &[-5, 70_000]);
+        write_dict_entry(&mut dict, 0x0C24, &[0]);
+
+        assert_eq!(dict_operands(&dict, 17), Some(vec![123_456]));
+        assert_eq!(dict_operands(&dict, 18), Some(vec![-5, 70_000]));
+        assert_eq!(dict_operands(&dict, 0x0C24), Some(vec![0]));
+        assert_eq!(dict_operands(&dict, 15), None);
+    }
+
+    #[test]
+    fn collects_subrs_after_blended_hints() {
+        // CFF2: 2つの値をリージョン2つ分の差分でblendしてhstemにし、1バイトのhintmaskの後でローカルサブルーチン1を呼ぶ
+        // ステム数を数え損ねてhintmaskのビット列（10 = callsubr）を演算子として読むと、番号が無く失敗する
+        let char_string = [149, 159, 140, 140, 140, 140, 141, 16, 1, 19, 10, 32, 10];
+        let local_subrs: [&[u8]; 3] = [&[], &[], &[]];
+        let region_counts = [2];
+        let mut collector = SubrCollector::new(&[], &region_counts, true);
+        let mut local_used = BTreeSet::new();
+        collector.collect(&char_string, &local_subrs, 0, &mut local_used).unwrap();
+        assert_eq!(local_used, BTreeSet::from([0]));
+    }
+}
@@ -0,0 +1,44 @@
+//! 書き出しで使う色の変換
+
+/// "#RGB" | "#RRGGBB" | "#RRGGBBAA" をRGBAに変換する
+pub fn parse_color(color: &str) -> Result<[u8; 4], String> {
+    let invalid = || format!("Invalid color: {:?}", color);
+    let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+    let channel = |i: usize, len: usize| {
+        let digits = hex.get(i * len..(i + 1) * len).ok_or_else(invalid)?;
+        let value = u8::from_str_radix(digits, 16).map_err(|_| invalid())?;
+        Ok::<u8, String>(if len == 1 { value * 17 } else { value })
+    };
+
+    match hex.len() {
+        3 => Ok([channel(0, 1)?, channel(1, 1)?, channel(2, 1)?, 255]),
+        6 => Ok([channel(0, 2)?, channel(1, 2)?, channel(2, 2)?, 255]),
+        8 => Ok([channel(0, 2)?, channel(1, 2)?, channel(2, 2)?, channel(3, 2)?]),
+        _ => Err(invalid()),
+    }
+}
+
+/// RGBを0〜1のCMYKに単純変換する（カラープロファイルは使わない、黒はK100になる）
+pub fn rgb_to_cmyk([r, g, b, _]: [u8; 4]) -> [f64; 4] {
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let k = 1.0 - r.max(g).max(b);
+    if k >= 1.0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+    [
+        (1.0 - r - k) / (1.0 - k),
+        (1.0 - g - k) / (1.0 - k),
+        (1.0 - b - k) / (1.0 - k),
+        k,
+    ]
+}
+
+/// 0〜1の色成分（cmykならC・M・Y・K、そうでなければR・G・B）、不透明度は含めない
+pub fn color_components(color: &str, cmyk: bool) -> Result<Vec<f64>, String> {
+    let rgba = parse_color(color)?;
+    Ok(if cmyk {
+        rgb_to_cmyk(rgba).to_vec()
+    } else {
+        rgba[..3].iter().map(|v| *v as f64 / 255.0).collect()
+    })
+}
@@ -0,0 +1,248 @@
+//! cmapを走査し、名前付きの文字集合（JIS X 0208/0213・常用漢字・Latin-1など）に対する収録状況を取得
+
+use std::collections::HashSet;
+use ttf_parser::{Face, Tag};
+
+use crate::cff;
+
+/// 文字集合ごとの収録状況
+#[derive(serde::Serialize)]
+pub struct CharsetCoverage {
+    /// 例: "jisx0208_level1", "joyo", "latin1", "required"
+    pub id: String,
+    pub name: String,
+    pub total: usize,
+    pub covered: usize,
+    /// 収録されていない文字（集合内の順序のまま連結）
+    pub missing: String,
+}
+
+/// CID-keyed CFFのROS（Registry-Ordering-Supplement）
+#[derive(serde::Serialize)]
+pub struct CidInfo {
+    pub registry: Option<String>,
+    pub ordering: Option<String>,
+    /// CFFに記録されているSupplement
+    pub supplement: i32,
+    pub number_of_glyphs: u16,
+    /// Adobe-Japan1の場合、グリフ数が満たす最大のSupplement（例: 23058以上なら6）
+    pub adobe_japan1_supplement: Option<u8>,
+}
+
+#[derive(serde::Serialize)]
+pub struct FontCoverage {
+    /// cmapに収録されている文字数
+    pub total_codepoints: usize,
+    pub charsets: Vec<CharsetCoverage>,
+    /// CID-keyed CFFでない場合はNone
+    pub cid: Option<CidInfo>,
+}
+
+const JIS_X_0208_LEVEL1: &str = include_str!("charsets/jisx0208_level1.txt");
+const JIS_X_0208_LEVEL2: &str = include_str!("charsets/jisx0208_level2.txt");
+/// 第3・第4水準と非漢字を含む全体（結合文字列で表される25字は除く）
+const JIS_X_0213: &str = include_str!("charsets/jisx0213.txt");
+/// 2010年告示の常用漢字表（2136字、音訓順）
+const JOYO_KANJI: &str = include_str!("charsets/joyo.txt");
+
+type CodepointRanges = &'static [(u32, u32)];
+
+/// ISO/IEC 8859-1の図形文字
+const LATIN_1: &[(u32, u32)] = &[(0x0020, 0x007E), (0x00A0, 0x00FF)];
+const LATIN_EXTENDED_A: &[(u32, u32)] = &[(0x0100, 0x017F)];
+/// Greek and Coptic（未割り当てを除く）
+const GREEK: &[(u32, u32)] = &[
+    (0x0370, 0x0377), (0x037A, 0x037F), (0x0384, 0x038A), (0x038C, 0x038C),
+    (0x038E, 0x03A1), (0x03A3, 0x03FF),
+];
+const CYRILLIC: &[(u32, u32)] = &[(0x0400, 0x04FF)];
+/// 絵文字の主要ブロック（Unicode 17.0で割り当て済みの範囲）と、
+/// ブロック外でemoji-data.txtが絵文字とする矢印・星、国旗・キーキャップの組み立てに使う文字
+const EMOJI: &[(u32, u32)] = &[
+    // Combining Enclosing Keycap（#・*・0〜9の後に付けてキーキャップにする）
+    (0x20E3, 0x20E3),
+    // Miscellaneous Symbols and Arrowsのうち絵文字（⬅⬆⬇⬛⬜⭐⭕）
+    (0x2B05, 0x2B07), (0x2B1B, 0x2B1C), (0x2B50, 0x2B50), (0x2B55, 0x2B55),
+    // Regional Indicator Symbols（2文字の組で国旗になる）
+    (0x1F1E6, 0x1F1FF),
+    // Miscellaneous Symbols and Pictographs
+    (0x1F300, 0x1F5FF),
+    // Emoticons
+    (0x1F600, 0x1F64F),
+    // Transport and Map Symbols
+    (0x1F680, 0x1F6D8), (0x1F6DC, 0x1F6EC), (0x1F6F0, 0x1F6FC),
+    // Supplemental Symbols and Pictographs
+    (0x1F900, 0x1F9FF),
+    // Symbols and Pictographs Extended-A
+    (0x1FA70, 0x1FA7C), (0x1FA80, 0x1FA8A), (0x1FA8E, 0x1FAC6), (0x1FAC8, 0x1FAC8),
+    (0x1FACD, 0x1FADC), (0x1FADF, 0x1FAEA), (0x1FAEF, 0x1FAF8),
+];
+
+/// Adobe-Japan1の各Supplementまでに定義されているCIDの数
+const ADOBE_JAPAN1_CID_COUNTS: [(u8, u16); 8] = [
+    (0, 8284), (1, 8359), (2, 8720), (3, 9354), (4, 15444), (5, 20317), (6, 23058), (7, 23060),
+];
+
+/// 文字を列挙する文字集合（ID, 表示名, 改行区切りのデータ）
+const LISTED_CHARSETS: [(&str, &str, &str); 4] = [
+    ("jisx0208_level1", "JIS X 0208 Level 1", JIS_X_0208_LEVEL1),
+    ("jisx0208_level2", "JIS X 0208 Level 2", JIS_X_0208_LEVEL2),
+    ("jisx0213", "JIS X 0213:2004", JIS_X_0213),
+    ("joyo", "Jōyō Kanji", JOYO_KANJI),
+];
+
+/// コードポイントの範囲で表す文字集合（ID, 表示名, 範囲）
+const BLOCK_CHARSETS: [(&str, &str, CodepointRanges); 5] = [
+    ("latin1", "Latin-1", LATIN_1),
+    ("latin_extended_a", "Latin Extended-A", LATIN_EXTENDED_A),
+    ("greek", "Greek", GREEK),
+    ("cyrillic", "Cyrillic", CYRILLIC),
+    ("emoji", "Emoji", EMOJI),
+];
+
+/// required_textが指定された場合は、その文字（改行・空白を除く）の収録状況を先頭に加える
+pub fn collect_font_coverage(face: &Face, required_text: Option<&str>) -> FontCoverage {
+    let codepoints = mapped_codepoints(face);
+
+    let mut charsets = Vec::new();
+    if let Some(text) = required_text {
+        let mut seen = HashSet::new();
+        let chars: Vec<char> = text
+            .chars()
+            .filter(|c| !c.is_whitespace() && seen.insert(*c))
+            .collect();
+        charsets.push(charset_coverage("required", "Required text", chars, &codepoints));
+    }
+
+    let ids = LISTED_CHARSETS
+        .iter()
+        .map(|(id, name, _)| (*id, *name))
+        .chain(BLOCK_CHARSETS.iter().map(|(id, name, _)| (*id, *name)));
+    for (id, name) in ids {
+        let chars = charset_chars(id).unwrap_or_default();
+        charsets.push(charset_coverage(id, name, chars, &codepoints));
+    }
+
+    FontCoverage {
+        total_codepoints: codepoints.len(),
+        charsets,
+        cid: cid_info(face),
+    }
+}
+
+/// 文字集合のIDからその文字を集合内の順序で返す（例: "joyo", "latin1"）
+pub fn charset_chars(id: &str) -> Option<Vec<char>> {
+    if let Some((_, _, data)) = LISTED_CHARSETS.iter().find(|(listed, _, _)| *listed == id) {
+        // 全角スペースも集合に含まれるため、改行のみ除く
+        return Some(data.chars().filter(|c| *c != '\n').collect());
+    }
+
+    let (_, _, ranges) = BLOCK_CHARSETS.iter().find(|(block, _, _)| *block == id)?;
+    Some(
+        ranges
+            .iter()
+            .flat_map(|&(start, end)| (start..=end).filter_map(char::from_u32))
+            .collect(),
+    )
+}
+
+/// 用字系（ISO 15924のコード、大文字小文字は区別しない）に対応しているか判定するための文字
+/// Haniは常用漢字をすべて収録していれば対応とみなす
+pub fn script_chars(script: &str) -> Option<Vec<char>> {
+    let ranges: &[(u32, u32)] = match script.to_ascii_lowercase().as_str() {
+        "hira" => &[(0x3041, 0x3096)],
+        "kana" => &[(0x30A1, 0x30FA)],
+        "hani" => return Some(JOYO_KANJI.chars().filter(|c| *c != '\n').collect()),
+        "latn" => &[(0x0041, 0x005A), (0x0061, 0x007A)],
+        "grek" => &[(0x0391, 0x03A1), (0x03A3, 0x03A9), (0x03B1, 0x03C9)],
+        "cyrl" => &[(0x0410, 0x044F)],
+        _ => return None,
+    };
+
+    Some(
+        ranges
+            .iter()
+            .flat_map(|&(start, end)| (start..=end).filter_map(char::from_u32))
+            .collect(),
+    )
+}
+
+/// Unicodeのcmapサブテーブルで.notdef以外のグリフに割り当てられているコードポイント
+pub fn mapped_codepoints(face: &Face) -> HashSet<u32> {
+    let mut codepoints = HashSet::new();
+
+    if let Some(cmap) = face.tables().cmap {
+        for subtable in cmap.subtables.into_iter().filter(|s| s.is_unicode()) {
+            subtable.codepoints(|cp| {
+                if subtable.glyph_index(cp).is_some_and(|g| g.0 != 0) {
+                    codepoints.insert(cp);
+                }
+            });
+        }
+    }
+
+    codepoints
+}
+
+fn charset_coverage(id: &str, name: &str, chars: Vec<char>, codepoints: &HashSet<u32>) -> CharsetCoverage {
+    let missing: String = chars
+        .iter()
+        .filter(|c| !codepoints.contains(&(**c as u32)))
+        .collect();
+    let missing_count = missing.chars().count();
+
+    CharsetCoverage {
+        id: id.to_string(),
+        name: name.to_string(),
+        total: chars.len(),
+        covered: chars.len() - missing_count,
+        missing,
+    }
+}
+
+/// CFFのTop DICTからROSを読む（CID-keyedでなければNone）
+fn cid_info(face: &Face) -> Option<CidInfo> {
+    let data = face.raw_face().table(Tag::from_bytes(b"CFF "))?;
+
+    let header_size = *data.get(2)? as usize;
+    let (_, top_dict_index) = cff::index(data, header_size)?;
+    let (top_dicts, string_index) = cff::index(data, top_dict_index)?;
+    let (strings, _) = cff::index(data, string_index)?;
+
+    let top_dict = top_dicts.first()?;
+    let ros = cff::dict_operands(top_dict, 0x0C1E)?;
+    let [registry, ordering, supplement] = ros[..] else {
+        return None;
+    };
+
+    // 標準文字列（SID 0〜390）以外はString INDEXを参照する
+    let sid_string = |sid: i32| {
+        let index = usize::try_from(sid).ok()?.checked_sub(391)?;
+        strings
+            .get(index)
+            .map(|s| String::from_utf8_lossy(s).to_string())
+    };
+    let registry = sid_string(registry);
+    let ordering = sid_string(ordering);
+
+    let number_of_glyphs = face.number_of_glyphs();
+    let is_adobe_japan1 =
+        registry.as_deref() == Some("Adobe") && ordering.as_deref() == Some("Japan1");
+    let adobe_japan1_supplement = if is_adobe_japan1 {
+        ADOBE_JAPAN1_CID_COUNTS
+            .iter()
+            .rev()
+            .find(|(_, count)| number_of_glyphs >= *count)
+            .map(|(supplement, _)| *supplement)
+    } else {
+        None
+    };
+
+    Some(CidInfo {
+        registry,
+        ordering,
+        supplement,
+        number_of_glyphs,
+        adobe_japan1_supplement,
+    })
+}
@@ -0,0 +1,493 @@
+//! 配置したアウトラインをDXF（AutoCAD R2000形式、単位はmm）にする
+//! R2000で必須のテーブル・ブロック・オブジェクトと、全オブジェクトのハンドルを持たせる
+
+use crate::offset::LineJoin;
+use crate::outline::{flatten, format_number, to_cubics, Segment, TextLayout};
+
+/// アウトラインを置くレイヤー
+const LAYER: &str = "TEXT";
+
+/// 曲線の書き出し方
+pub enum CurveMode {
+    /// 許容誤差（mm）以内の折れ線にしてLWPOLYLINEにする
+    Polyline(f64),
+    /// 輪郭ごとに3次のSPLINEにする（直線も3次ベジェとして含める）
+    Spline,
+}
+
+/// グループコードと値の組を書く
+fn push_pair(out: &mut String, code: u16, value: &str) {
+    out.push_str(&format!("{:>3}\n{}\n", code, value));
+}
+
+/// オブジェクトのハンドル（16進数）を順に振る
+struct Handles(u32);
+
+impl Handles {
+    fn next(&mut self) -> String {
+        self.0 += 1;
+        format!("{:X}", self.0)
+    }
+}
+
+/// 記号テーブルの始まりを書き、テーブルのハンドルを返す
+fn begin_table(out: &mut String, handles: &mut Handles, name: &str, count: usize) -> String {
+    let handle = handles.next();
+    push_pair(out, 0, "TABLE");
+    push_pair(out, 2, name);
+    push_pair(out, 5, &handle);
+    push_pair(out, 330, "0");
+    push_pair(out, 100, "AcDbSymbolTable");
+    push_pair(out, 70, &count.to_string());
+    handle
+}
+
+/// 記号テーブルのレコードの始まりを書き、レコードのハンドルを返す
+fn begin_record(out: &mut String, handles: &mut Handles, kind: &str, table: &str, subclass: &str) -> String {
+    let handle = handles.next();
+    push_pair(out, 0, kind);
+    // DIMSTYLEだけはハンドルのグループコードが105
+    push_pair(out, if kind == "DIMSTYLE" { 105 } else { 5 }, &handle);
+    push_pair(out, 330, table);
+    push_pair(out, 100, "AcDbSymbolTableRecord");
+    push_pair(out, 100, subclass);
+    handle
+}
+
+/// 線種（ByBlock・ByLayerとアウトラインに使う実線）
+fn push_linetype(out: &mut String, handles: &mut Handles, table: &str, name: &str, description: &str) {
+    begin_record(out, handles, "LTYPE", table, "AcDbLinetypeTableRecord");
+    push_pair(out, 2, name);
+    push_pair(out, 70, "0");
+    push_pair(out, 3, description);
+    push_pair(out, 72, "65");
+    push_pair(out, 73, "0");
+    push_pair(out, 40, "0");
+}
+
+fn push_layer(out: &mut String, handles: &mut Handles, table: &str, name: &str) {
+    begin_record(out, handles, "LAYER", table, "AcDbLayerTableRecord");
+    push_pair(out, 2, name);
+    push_pair(out, 70, "0");
+    push_pair(out, 62, "7");
+    push_pair(out, 6, "Continuous");
+}
+
+/// モデル空間・ペーパー空間のブロック定義（中身は持たない）
+fn push_block(out: &mut String, handles: &mut Handles, name: &str, block_record: &str, paper_space: bool) {
+    let entity_header = |out: &mut String, handles: &mut Handles, kind: &str| {
+        push_pair(out, 0, kind);
+        push_pair(out, 5, &handles.next());
+        push_pair(out, 330, block_record);
+        push_pair(out, 100, "AcDbEntity");
+        if paper_space {
+            push_pair(out, 67, "1");
+        }
+        push_pair(out, 8, "0");
+    };
+    entity_header(out, handles, "BLOCK");
+    push_pair(out, 100, "AcDbBlockBegin");
+    push_pair(out, 2, name);
+    push_pair(out, 70, "0");
+    push_pair(out, 10, "0");
+    push_pair(out, 20, "0");
+    push_pair(out, 30, "0");
+    push_pair(out, 3, name);
+    push_pair(out, 1, "");
+    entity_header(out, handles, "ENDBLK");
+    push_pair(out, 100, "AcDbBlockEnd");
+}
+
+/// 直線を3次ベジェの制御点2つと終点にする
+fn line_as_cubic((x0, y0): (f64, f64), (x, y): (f64, f64)) -> [(f64, f64); 3] {
+    [
+        (x0 + (x - x0) / 3.0, y0 + (y - y0) / 3.0),
+        (x0 + (x - x0) * 2.0 / 3.0, y0 + (y - y0) * 2.0 / 3.0),
+        (x, y),
+    ]
+}
+
+/// 3次ベジェの連なりの輪郭（始点と、各区間の制御点2つと終点）
+type CubicContour = ((f64, f64), Vec<[(f64, f64); 3]>);
+
+/// アウトラインを輪郭ごとの3次ベジェの連なりにする
+fn cubic_contours(segments: &[Segment]) -> Vec<CubicContour> {
+    let mut contours = Vec::new();
+    let mut start = (0.0, 0.0);
+    let mut current = (0.0, 0.0);
+    let mut curves: Vec<[(f64, f64); 3]> = Vec::new();
+
+    for segment in to_cubics(segments) {
+        match segment {
+            Segment::MoveTo(x, y) => {
+                if !curves.is_empty() {
+                    contours.push((start, std::mem::take(&mut curves)));
+                }
+                start = (x, y);
+                current = start;
+            }
+            Segment::LineTo(x, y) => {
+                curves.push(line_as_cubic(current, (x, y)));
+                current = (x, y);
+            }
+            Segment::CurveTo(x1, y1, x2, y2, x, y) => {
+                curves.push([(x1, y1), (x2, y2), (x, y)]);
+                current = (x, y);
+            }
+            Segment::QuadTo(..) => unreachable!(),
+            Segment::Close => {
+                // 終点が始点に戻っていなければ直線で閉じる
+                if current != start {
+                    curves.push(line_as_cubic(current, start));
+                    current = start;
+                }
+            }
+        }
+    }
+    if !curves.is_empty() {
+        contours.push((start, curves));
+    }
+    contours
+}
+
+/// テキストのアウトラインをDXFにする
+/// scaleはpxからmmへの倍率で、左下を原点にY軸を上向きにする
+pub fn render_dxf(layout: &TextLayout, scale: f64, curve_mode: &CurveMode) -> String {
+    let bounds = layout.painted_bounds(&[], LineJoin::Round);
+    let (width, height) = (bounds.width() * scale, bounds.height() * scale);
+    let point = |(x, y): (f64, f64)| {
+        (
+            format_number((x - bounds.min_x) * scale),
+            format_number((bounds.max_y - y) * scale),
+        )
+    };
+    let mut handles = Handles(0);
+
+    // テーブル（VPORT・LTYPE・LAYER・STYLE・VIEW・UCS・APPID・DIMSTYLE・BLOCK_RECORD）
+    let mut tables = String::new();
+    push_pair(&mut tables, 0, "SECTION");
+    push_pair(&mut tables, 2, "TABLES");
+
+    let table = begin_table(&mut tables, &mut handles, "VPORT", 1);
+    begin_record(&mut tables, &mut handles, "VPORT", &table, "AcDbViewportTableRecord");
+    push_pair(&mut tables, 2, "*ACTIVE");
+    push_pair(&mut tables, 70, "0");
+    push_pair(&mut tables, 10, "0");
+    push_pair(&mut tables, 20, "0");
+    push_pair(&mut tables, 11, "1");
+    push_pair(&mut tables, 21, "1");
+    // アウトライン全体が収まるように表示する
+    push_pair(&mut tables, 12, &format_number(width / 2.0));
+    push_pair(&mut tables, 22, &format_number(height / 2.0));
+    push_pair(&mut tables, 40, &format_number(height.max(width / 2.0).max(1.0) * 1.1));
+    push_pair(&mut tables, 41, "2");
+    push_pair(&mut tables, 0, "ENDTAB");
+
+    let table = begin_table(&mut tables, &mut handles, "LTYPE", 3);
+    push_linetype(&mut tables, &mut handles, &table, "ByBlock", "");
+    push_linetype(&mut tables, &mut handles, &table, "ByLayer", "");
+    push_linetype(&mut tables, &mut handles, &table, "Continuous", "Solid line");
+    push_pair(&mut tables, 0, "ENDTAB");
+
+    let table = begin_table(&mut tables, &mut handles, "LAYER", 2);
+    push_layer(&mut tables, &mut handles, &table, "0");
+    push_layer(&mut tables, &mut handles, &table, LAYER);
+    push_pair(&mut tables, 0, "ENDTAB");
+
+    let table = begin_table(&mut tables, &mut handles, "STYLE", 1);
+    begin_record(&mut tables, &mut handles, "STYLE", &table, "AcDbTextStyleTableRecord");
+    push_pair(&mut tables, 2, "Standard");
+    push_pair(&mut tables, 70, "0");
+    push_pair(&mut tables, 40, "0");
+    push_pair(&mut tables, 41, "1");
+    push_pair(&mut tables, 50, "0");
+    push_pair(&mut tables, 71, "0");
+    push_pair(&mut tables, 42, "2.5");
+    push_pair(&mut tables, 3, "txt");
+    push_pair(&mut tables, 4, "");
+    push_pair(&mut tables, 0, "ENDTAB");
+
+    for name in ["VIEW", "UCS"] {
+        begin_table(&mut tables, &mut handles, name, 0);
+        push_pair(&mut tables, 0, "ENDTAB");
+    }
+
+    let table = begin_table(&mut tables, &mut handles, "APPID", 1);
+    begin_record(&mut tables, &mut handles, "APPID", &table, "AcDbRegAppTableRecord");
+    push_pair(&mut tables, 2, "ACAD");
+    push_pair(&mut tables, 70, "0");
+    push_pair(&mut tables, 0, "ENDTAB");
+
+    let table = begin_table(&mut tables, &mut handles, "DIMSTYLE", 1);
+    push_pair(&mut tables, 100, "AcDbDimStyleTable");
+    push_pair(&mut tables, 71, "0");
+    begin_record(&mut tables, &mut handles, "DIMSTYLE", &table, "AcDbDimStyleTableRecord");
+    push_pair(&mut tables, 2, "Standard");
+    push_pair(&mut tables, 70, "0");
+    push_pair(&mut tables, 0, "ENDTAB");
+
+    let table = begin_table(&mut tables, &mut handles, "BLOCK_RECORD", 2);
+    let model_space = begin_record(&mut tables, &mut handles, "BLOCK_RECORD", &table, "AcDbBlockTableRecord");
+    push_pair(&mut tables, 2, "*Model_Space");
+    let paper_space = begin_record(&mut tables, &mut handles, "BLOCK_RECORD", &table, "AcDbBlockTableRecord");
+    push_pair(&mut tables, 2, "*Paper_Space");
+    push_pair(&mut tables, 0, "ENDTAB");
+    push_pair(&mut tables, 0, "ENDSEC");
+
+    let mut blocks = String::new();
+    push_pair(&mut blocks, 0, "SECTION");
+    push_pair(&mut blocks, 2, "BLOCKS");
+    push_block(&mut blocks, &mut handles, "*Model_Space", &model_space, false);
+    push_block(&mut blocks, &mut handles, "*Paper_Space", &paper_space, true);
+    push_pair(&mut blocks, 0, "ENDSEC");
+
+    let mut entities = String::new();
+    push_pair(&mut entities, 0, "SECTION");
+    push_pair(&mut entities, 2, "ENTITIES");
+    let mut begin_entity = |out: &mut String, kind: &str, subclass: &str| {
+        push_pair(out, 0, kind);
+        push_pair(out, 5, &handles.next());
+        push_pair(out, 330, &model_space);
+        push_pair(out, 100, "AcDbEntity");
+        push_pair(out, 8, LAYER);
+        push_pair(out, 100, subclass);
+    };
+    for glyph in &layout.glyphs {
+        match curve_mode {
+            CurveMode::Polyline(tolerance) => {
+                for polyline in flatten(&glyph.segments, tolerance / scale) {
+                    begin_entity(&mut entities, "LWPOLYLINE", "AcDbPolyline");
+                    push_pair(&mut entities, 90, &polyline.points.len().to_string());
+                    push_pair(&mut entities, 70, if polyline.closed { "1" } else { "0" });
+                    push_pair(&mut entities, 43, "0");
+                    for p in &polyline.points {
+                        let (x, y) = point(*p);
+                        push_pair(&mut entities, 10, &x);
+                        push_pair(&mut entities, 20, &y);
+                    }
+                }
+            }
+            CurveMode::Spline => {
+                for (start, curves) in cubic_contours(&glyph.segments) {
+                    // ベジェの連なりを、内部ノットの多重度3のB-スプラインとして書く
+                    let control_points: Vec<(f64, f64)> = std::iter::once(start)
+                        .chain(curves.iter().flatten().copied())
+                        .collect();
+                    let mut knots = vec![0.0; 4];
+                    for i in 1..curves.len() {
+                        knots.extend([i as f64; 3]);
+                    }
+                    knots.extend([curves.len() as f64; 4]);
+
+                    begin_entity(&mut entities, "SPLINE", "AcDbSpline");
+                    push_pair(&mut entities, 210, "0");
+                    push_pair(&mut entities, 220, "0");
+                    push_pair(&mut entities, 230, "1");
+                    push_pair(&mut entities, 70, "8"); // 平面
+                    push_pair(&mut entities, 71, "3");
+                    push_pair(&mut entities, 72, &knots.len().to_string());
+                    push_pair(&mut entities, 73, &control_points.len().to_string());
+                    push_pair(&mut entities, 74, "0");
+                    push_pair(&mut entities, 42, "0.0000001");
+                    push_pair(&mut entities, 43, "0.0000001");
+                    for knot in &knots {
+                        push_pair(&mut entities, 40, &format_number(*knot));
+                    }
+                    for p in &control_points {
+                        let (x, y) = point(*p);
+                        push_pair(&mut entities, 10, &x);
+                        push_pair(&mut entities, 20, &y);
+                        push_pair(&mut entities, 30, "0");
+                    }
+                }
+            }
+        }
+    }
+    push_pair(&mut entities, 0, "ENDSEC");
+
+    // ルートの辞書とグループの辞書
+    let mut objects = String::new();
+    let root = handles.next();
+    let groups = handles.next();
+    push_pair(&mut objects, 0, "SECTION");
+    push_pair(&mut objects, 2, "OBJECTS");
+    push_pair(&mut objects, 0, "DICTIONARY");
+    push_pair(&mut objects, 5, &root);
+    push_pair(&mut objects, 330, "0");
+    push_pair(&mut objects, 100, "AcDbDictionary");
+    push_pair(&mut objects, 281, "1");
+    push_pair(&mut objects, 3, "ACAD_GROUP");
+    push_pair(&mut objects, 350, &groups);
+    push_pair(&mut objects, 0, "DICTIONARY");
+    push_pair(&mut objects, 5, &groups);
+    push_pair(&mut objects, 330, &root);
+    push_pair(&mut objects, 100, "AcDbDictionary");
+    push_pair(&mut objects, 281, "1");
+    push_pair(&mut objects, 0, "ENDSEC");
+
+    // $HANDSEEDは振ったハンドルより大きくする
+    let mut out = String::new();
+    push_pair(&mut out, 0, "SECTION");
+    push_pair(&mut out, 2, "HEADER");
+    push_pair(&mut out, 9, "$ACADVER");
+    push_pair(&mut out, 1, "AC1015");
+    push_pair(&mut out, 9, "$HANDSEED");
+    push_pair(&mut out, 5, &handles.next());
+    push_pair(&mut out, 9, "$INSUNITS");
+    push_pair(&mut out, 70, "4"); // mm
+    push_pair(&mut out, 9, "$MEASUREMENT");
+    push_pair(&mut out, 70, "1"); // メートル法
+    push_pair(&mut out, 9, "$EXTMIN");
+    push_pair(&mut out, 10, "0");
+    push_pair(&mut out, 20, "0");
+    push_pair(&mut out, 30, "0");
+    push_pair(&mut out, 9, "$EXTMAX");
+    push_pair(&mut out, 10, &format_number(width));
+    push_pair(&mut out, 20, &format_number(height));
+    push_pair(&mut out, 30, "0");
+    push_pair(&mut out, 0, "ENDSEC");
+    push_pair(&mut out, 0, "SECTION");
+    push_pair(&mut out, 2, "CLASSES");
+    push_pair(&mut out, 0, "ENDSEC");
+
+    out.push_str(&tables);
+    out.push_str(&blocks);
+    out.push_str(&entities);
+    out.push_str(&objects);
+    push_pair(&mut out, 0, "EOF");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outline::GlyphOutline;
+
+    /// 10px角の正方形1つ（左上が原点）
+    fn square_layout() -> TextLayout {
+        TextLayout {
+            width: 10.0,
+            height: 10.0,
+            glyphs: vec![GlyphOutline {
+                char_index: 0,
+                text: "A".to_string(),
+                font_name: "Test".to_string(),
+                segments: vec![
+                    Segment::MoveTo(0.0, 0.0),
+                    Segment::LineTo(10.0, 0.0),
+                    Segment::LineTo(10.0, 10.0),
+                    Segment::LineTo(0.0, 10.0),
+                    Segment::Close,
+                ],
+            }],
+        }
+    }
+
+    /// （グループコード, 値）の組に分ける
+    fn pairs(dxf: &str) -> Vec<(u16, &str)> {
+        let lines: Vec<&str> = dxf.lines().collect();
+        assert_eq!(lines.len() % 2, 0);
+        lines
+            .chunks(2)
+            .map(|pair| (pair[0].trim().parse().unwrap(), pair[1]))
+            .collect()
+    }
+
+    /// 種類がkindのエンティティ・オブジェクトごとの組（次のグループコード0の手前まで）
+    fn records<'a>(pairs: &[(u16, &'a str)], kind: &str) -> Vec<Vec<(u16, &'a str)>> {
+        pairs
+            .iter()
+            .enumerate()
+            .filter(|(_, &pair)| pair == (0, kind))
+            .map(|(i, _)| pairs[i + 1..].iter().take_while(|(code, _)| *code != 0).copied().collect())
+            .collect()
+    }
+
+    fn values<'a>(record: &[(u16, &'a str)], code: u16) -> Vec<&'a str> {
+        record.iter().filter(|(c, _)| *c == code).map(|(_, value)| *value).collect()
+    }
+
+    #[test]
+    fn sections_and_handles_are_consistent() {
+        let dxf = render_dxf(&square_layout(), 0.5, &CurveMode::Spline);
+        let pairs = pairs(&dxf);
+
+        let sections: Vec<&str> = pairs
+            .windows(2)
+            .filter(|w| w[0] == (0, "SECTION"))
+            .map(|w| {
+                assert_eq!(w[1].0, 2);
+                w[1].1
+            })
+            .collect();
+        assert_eq!(sections, ["HEADER", "CLASSES", "TABLES", "BLOCKS", "ENTITIES", "OBJECTS"]);
+        assert_eq!(pairs.iter().filter(|&&pair| pair == (0, "ENDSEC")).count(), sections.len());
+        assert_eq!(pairs.last(), Some(&(0, "EOF")));
+
+        // ハンドルは重複せず、$HANDSEEDより小さい
+        let handles: Vec<u32> = pairs
+            .windows(2)
+            .filter(|w| w[1].0 == 5 || (w[1].0 == 105 && w[0] == (0, "DIMSTYLE")))
+            .filter(|w| w[0] != (9, "$HANDSEED"))
+            .map(|w| u32::from_str_radix(w[1].1, 16).unwrap())
+            .collect();
+        let unique: std::collections::BTreeSet<u32> = handles.iter().copied().collect();
+        assert_eq!(unique.len(), handles.len());
+        let seed_index = pairs.iter().position(|&pair| pair == (9, "$HANDSEED")).unwrap();
+        let seed = u32::from_str_radix(pairs[seed_index + 1].1, 16).unwrap();
+        assert!(handles.iter().all(|&handle| handle < seed));
+
+        // 所有者（330）と辞書の参照（350）は振ったハンドルを指す
+        for &(code, value) in &pairs {
+            if (code == 330 && value != "0") || code == 350 {
+                assert!(unique.contains(&u32::from_str_radix(value, 16).unwrap()), "{} {}", code, value);
+            }
+        }
+    }
+
+    #[test]
+    fn polyline_mode_writes_closed_lwpolylines() {
+        let dxf = render_dxf(&square_layout(), 0.5, &CurveMode::Polyline(0.01));
+        let pairs = pairs(&dxf);
+        assert!(records(&pairs, "SPLINE").is_empty());
+        let polylines = records(&pairs, "LWPOLYLINE");
+        assert_eq!(polylines.len(), 1);
+
+        let polyline = &polylines[0];
+        assert_eq!(values(polyline, 8), [LAYER]);
+        assert_eq!(values(polyline, 90), ["4"]);
+        assert_eq!(values(polyline, 70), ["1"]);
+        // 左下を原点にY軸を上向きにし、mmにする
+        assert_eq!(values(polyline, 10), ["0", "5", "5", "0"]);
+        assert_eq!(values(polyline, 20), ["5", "5", "0", "0"]);
+    }
+
+    #[test]
+    fn spline_mode_writes_cubic_bezier_splines() {
+        let dxf = render_dxf(&square_layout(), 0.5, &CurveMode::Spline);
+        let pairs = pairs(&dxf);
+        assert!(records(&pairs, "LWPOLYLINE").is_empty());
+        let splines = records(&pairs, "SPLINE");
+        assert_eq!(splines.len(), 1);
+
+        // 4辺の3次ベジェ：制御点は始点と各辺の3点、内部ノットは多重度3
+        let spline = &splines[0];
+        assert_eq!(values(spline, 71), ["3"]);
+        assert_eq!(values(spline, 72), ["17"]);
+        assert_eq!(values(spline, 73), ["13"]);
+        assert_eq!(
+            values(spline, 40),
+            ["0", "0", "0", "0", "1", "1", "1", "2", "2", "2", "3", "3", "3", "4", "4", "4", "4"]
+        );
+        let x = values(spline, 10);
+        let y = values(spline, 20);
+        assert_eq!(x.len(), 13);
+        // 角の点が3つおきに並び、その間は辺の3等分点になる
+        assert_eq!((x[0], y[0]), ("0", "5"));
+        assert_eq!((x[3], y[3]), ("5", "5"));
+        assert_eq!((x[6], y[6]), ("5", "0"));
+        assert_eq!((x[9], y[9]), ("0", "0"));
+        assert_eq!((x[12], y[12]), ("0", "5"));
+        assert_eq!((x[1], x[2]), ("1.667", "3.333"));
+    }
+}
@@ -0,0 +1,114 @@
+//! 配置したアウトラインをEPS（Encapsulated PostScript）にする
+
+use crate::color::color_components;
+use crate::offset::{LineJoin, MITER_LIMIT};
+use crate::outline::{format_number, to_cubics, Bounds, Segment, TextLayout, PX_TO_PT};
+use crate::StrokeLayer;
+
+/// 出力を短くするための省略名
+const PROLOG: &str = "/m { moveto } bind def
+/l { lineto } bind def
+/c { curveto } bind def
+/h { closepath } bind def
+/f { fill } bind def
+/B { gsave fill grestore stroke } bind def
+";
+
+/// 色を設定する命令（setrgbcolorまたはsetcmykcolor）
+fn color_command(color: &str, cmyk: bool) -> Result<String, String> {
+    let components: Vec<String> = color_components(color, cmyk)?
+        .into_iter()
+        .map(format_number)
+        .collect();
+    let operator = if cmyk { "setcmykcolor" } else { "setrgbcolor" };
+    Ok(format!("{} {}", components.join(" "), operator))
+}
+
+/// ページ座標（左下が原点、単位はpt）のパス
+fn path_commands(segments: &[Segment], page: &Bounds) -> String {
+    let x = |x: f64| format_number((x - page.min_x) * PX_TO_PT);
+    let y = |y: f64| format_number((page.max_y - y) * PX_TO_PT);
+
+    // PostScriptには2次ベジェが無いため3次に変換する
+    let mut commands = String::new();
+    for segment in to_cubics(segments) {
+        match segment {
+            Segment::MoveTo(px, py) => commands.push_str(&format!("{} {} m\n", x(px), y(py))),
+            Segment::LineTo(px, py) => commands.push_str(&format!("{} {} l\n", x(px), y(py))),
+            Segment::CurveTo(x1, y1, x2, y2, px, py) => commands.push_str(&format!(
+                "{} {} {} {} {} {} c\n",
+                x(x1), y(y1), x(x2), y(y2), x(px), y(py)
+            )),
+            Segment::QuadTo(..) => unreachable!(),
+            Segment::Close => commands.push_str("h\n"),
+        }
+    }
+    commands
+}
+
+/// アウトラインとストロークが収まるバウンディングボックスのEPSを作る
+/// ストロークは外側のレイヤーから全文字分を描き、最後に塗りを描く
+pub fn render_eps(
+    layout: &TextLayout,
+    text_color: &str,
+    stroke_layers: &[&StrokeLayer],
+    stroke_join: LineJoin,
+    cmyk: bool,
+) -> Result<String, String> {
+    let page = layout.painted_bounds(stroke_layers, stroke_join);
+    let width = page.width() * PX_TO_PT;
+    let height = page.height() * PX_TO_PT;
+
+    let paths: Vec<String> = layout
+        .glyphs
+        .iter()
+        .map(|glyph| path_commands(&glyph.segments, &page))
+        .collect();
+
+    let mut eps = format!(
+        "%!PS-Adobe-3.0 EPSF-3.0
+%%Creator: Font Scope
+%%BoundingBox: 0 0 {} {}
+%%HiResBoundingBox: 0 0 {} {}
+%%LanguageLevel: 2
+%%DocumentData: Clean7Bit
+%%Pages: 1
+%%EndComments
+%%BeginProlog
+{}%%EndProlog
+%%Page: 1 1
+gsave
+",
+        width.ceil(),
+        height.ceil(),
+        format_number(width),
+        format_number(height),
+        PROLOG
+    );
+
+    for layer in stroke_layers {
+        // SVGと同じく、ストロークの内側も同じ色で塗る
+        eps.push_str("gsave\n");
+        eps.push_str(&format!("{}\n", color_command(&layer.color, cmyk)?));
+        eps.push_str(&format!(
+            "{} setlinewidth {} setlinejoin {} setmiterlimit 1 setlinecap\n",
+            format_number(layer.width * 2.0 * PX_TO_PT),
+            stroke_join.postscript_code(),
+            format_number(MITER_LIMIT as f64)
+        ));
+        for path in &paths {
+            eps.push_str(path);
+            eps.push_str("B\n");
+        }
+        eps.push_str("grestore\n");
+    }
+
+    eps.push_str(&format!("{}\n", color_command(text_color, cmyk)?));
+    for path in &paths {
+        eps.push_str(path);
+        eps.push_str("f\n");
+    }
+
+    eps.push_str("grestore\nshowpage\n%%Trailer\n%%EOF\n");
+    Ok(eps)
+}
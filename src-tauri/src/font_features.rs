@@ -0,0 +1,192 @@
+//! GSUB/GPOSテーブルからフォントが対応するOpenTypeフィーチャーを取得
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use ttf_parser::gsub::{SingleSubstitution, SubstitutionSubtable};
+use ttf_parser::opentype_layout::{Coverage, LayoutTable};
+use ttf_parser::{Face, GlyphId};
+
+#[derive(serde::Serialize)]
+pub struct LanguageSystemInfo {
+    /// 言語タグ（デフォルト言語は"dflt"）
+    pub tag: String,
+    /// この言語で常に適用される必須フィーチャー
+    pub required_feature: Option<String>,
+    pub features: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ScriptInfo {
+    /// 用字系タグ（例: "kana", "hani", "latn", "DFLT"）
+    pub tag: String,
+    pub languages: Vec<LanguageSystemInfo>,
+}
+
+#[derive(serde::Serialize)]
+pub struct LayoutTableInfo {
+    pub scripts: Vec<ScriptInfo>,
+    /// テーブル内の全フィーチャータグ（重複なし、ソート済み）
+    pub features: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct GlyphSubstitution {
+    pub from_glyph: u16,
+    pub to_glyph: u16,
+    /// 置換元グリフの文字（cmapから逆引き、対応する文字が無ければNone）
+    pub from_char: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SingleSubstitutionFeature {
+    pub tag: String,
+    pub substitutions: Vec<GlyphSubstitution>,
+}
+
+#[derive(serde::Serialize)]
+pub struct FontFeatureList {
+    /// GSUBテーブルが無い場合はNone
+    pub gsub: Option<LayoutTableInfo>,
+    /// GPOSテーブルが無い場合はNone
+    pub gpos: Option<LayoutTableInfo>,
+    /// 単純置換（GSUB LookupType 1）を含むフィーチャーごとの置換一覧
+    pub single_substitutions: Vec<SingleSubstitutionFeature>,
+}
+
+pub fn collect_font_features(face: &Face) -> FontFeatureList {
+    let tables = face.tables();
+
+    FontFeatureList {
+        gsub: tables.gsub.map(|table| layout_table_info(&table)),
+        gpos: tables.gpos.map(|table| layout_table_info(&table)),
+        single_substitutions: tables
+            .gsub
+            .map(|table| single_substitutions(face, &table))
+            .unwrap_or_default(),
+    }
+}
+
+fn layout_table_info(table: &LayoutTable) -> LayoutTableInfo {
+    let feature_tag = |index: u16| table.features.get(index).map(|f| f.tag.to_string());
+
+    let scripts = table
+        .scripts
+        .into_iter()
+        .map(|script| {
+            // デフォルト言語を先頭に置く
+            let languages = script
+                .default_language
+                .into_iter()
+                .chain(script.languages)
+                .map(|lang| LanguageSystemInfo {
+                    tag: lang.tag.to_string(),
+                    required_feature: lang.required_feature.and_then(feature_tag),
+                    features: lang
+                        .feature_indices
+                        .into_iter()
+                        .filter_map(feature_tag)
+                        .collect::<BTreeSet<_>>()
+                        .into_iter()
+                        .collect(),
+                })
+                .collect();
+
+            ScriptInfo {
+                tag: script.tag.to_string(),
+                languages,
+            }
+        })
+        .collect();
+
+    let features = table
+        .features
+        .into_iter()
+        .map(|f| f.tag.to_string())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    LayoutTableInfo { scripts, features }
+}
+
+fn coverage_glyphs(coverage: &Coverage) -> Vec<GlyphId> {
+    match coverage {
+        Coverage::Format1 { glyphs } => glyphs.into_iter().collect(),
+        Coverage::Format2 { records } => records
+            .into_iter()
+            .flat_map(|r| (r.start.0..=r.end.0).map(GlyphId))
+            .collect(),
+    }
+}
+
+/// cmapを逆引きしてグリフIDから文字を求めるマップを作る
+/// 複数の文字が同じグリフを指す場合は最小のコードポイントを採用
+fn reverse_cmap(face: &Face) -> HashMap<u16, char> {
+    let mut map: HashMap<u16, char> = HashMap::new();
+
+    if let Some(cmap) = face.tables().cmap {
+        for subtable in cmap.subtables.into_iter().filter(|s| s.is_unicode()) {
+            subtable.codepoints(|cp| {
+                if let (Some(glyph_id), Some(ch)) = (subtable.glyph_index(cp), char::from_u32(cp)) {
+                    map.entry(glyph_id.0)
+                        .and_modify(|c| *c = (*c).min(ch))
+                        .or_insert(ch);
+                }
+            });
+        }
+    }
+
+    map
+}
+
+fn single_substitutions(face: &Face, table: &LayoutTable) -> Vec<SingleSubstitutionFeature> {
+    let chars = reverse_cmap(face);
+    // 同じタグのフィーチャーが用字系ごとに複数ある場合はまとめる
+    let mut by_tag: BTreeMap<String, BTreeMap<u16, u16>> = BTreeMap::new();
+
+    for feature in table.features {
+        for lookup_index in feature.lookup_indices {
+            let Some(lookup) = table.lookups.get(lookup_index) else {
+                continue;
+            };
+
+            for subtable in lookup.subtables.into_iter::<SubstitutionSubtable>() {
+                let SubstitutionSubtable::Single(single) = subtable else {
+                    continue;
+                };
+
+                let mappings = by_tag.entry(feature.tag.to_string()).or_default();
+                match single {
+                    SingleSubstitution::Format1 { coverage, delta } => {
+                        for glyph in coverage_glyphs(&coverage) {
+                            let to = (glyph.0 as i32 + delta as i32).rem_euclid(0x10000) as u16;
+                            mappings.entry(glyph.0).or_insert(to);
+                        }
+                    }
+                    SingleSubstitution::Format2 { coverage, substitutes } => {
+                        for glyph in coverage_glyphs(&coverage) {
+                            if let Some(to) = coverage.get(glyph).and_then(|i| substitutes.get(i)) {
+                                mappings.entry(glyph.0).or_insert(to.0);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    by_tag
+        .into_iter()
+        .filter(|(_, mappings)| !mappings.is_empty())
+        .map(|(tag, mappings)| SingleSubstitutionFeature {
+            tag,
+            substitutions: mappings
+                .into_iter()
+                .map(|(from, to)| GlyphSubstitution {
+                    from_glyph: from,
+                    to_glyph: to,
+                    from_char: chars.get(&from).map(|c| c.to_string()),
+                })
+                .collect(),
+        })
+        .collect()
+}
@@ -4,7 +4,31 @@ use font_kit::source::SystemSource;
 use rustybuzz::{Face as BuzzFace, UnicodeBuffer, Direction};
 use std::env;
 use std::fs;
-use std::sync::Arc;
+use tauri::Manager;
+
+mod boolean;
+mod catalog;
+mod cff;
+mod color;
+mod coverage;
+mod dxf;
+mod eps;
+mod font_features;
+mod licensing;
+mod mesh;
+mod metadata;
+mod offset;
+mod outline;
+mod pdf;
+mod plotter;
+mod raster;
+mod sfnt;
+mod subset;
+mod subset_layout;
+#[cfg(test)]
+mod test_font;
+mod variations;
+mod woff;
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -25,52 +49,373 @@ fn get_exe_dir() -> Result<String, String> {
     }
 }
 
+#[derive(serde::Serialize)]
+struct FontFaceInfo {
+    postscript_name: Option<String>,
+    full_name: String,
+    /// 100〜900（400 = Regular, 700 = Bold）
+    weight: f32,
+    /// 0.5〜2.0（1.0 = Normal）
+    stretch: f32,
+    /// "normal" | "italic" | "oblique"
+    style: String,
+    location: FontLocation,
+    /// 追加登録したファイル・フォルダのフォント（システムにインストールされていない）
+    user_font: bool,
+}
+
+#[derive(serde::Serialize)]
+struct FontFamilyInfo {
+    family: String,
+    faces: Vec<FontFaceInfo>,
+}
+
+fn style_name(style: font_kit::properties::Style) -> &'static str {
+    match style {
+        font_kit::properties::Style::Normal => "normal",
+        font_kit::properties::Style::Italic => "italic",
+        font_kit::properties::Style::Oblique => "oblique",
+    }
+}
+
+/// カタログのフェイスをファミリーごとにまとめる（ファミリー名順）
+fn catalog_families<'a>(faces: impl Iterator<Item = &'a catalog::CatalogFace>) -> Vec<FontFamilyInfo> {
+    let mut by_family: std::collections::BTreeMap<String, Vec<FontFaceInfo>> = Default::default();
+    for face in faces {
+        by_family.entry(face.family.clone()).or_default().push(FontFaceInfo {
+            postscript_name: face.postscript_name.clone(),
+            full_name: face.full_name.clone(),
+            weight: face.weight,
+            stretch: face.stretch,
+            style: face.style.clone(),
+            location: face.location.clone(),
+            user_font: face.user_font,
+        });
+    }
+
+    // 幅 → スタイル（normal, italic, obliqueの順）→ ウェイトの順に並べる
+    let style_rank = |style: &str| ["normal", "italic", "oblique"].iter().position(|s| *s == style);
+    by_family
+        .into_iter()
+        .map(|(family, mut faces)| {
+            faces.sort_by(|a, b| {
+                a.stretch
+                    .total_cmp(&b.stretch)
+                    .then_with(|| style_rank(&a.style).cmp(&style_rank(&b.style)))
+                    .then_with(|| a.weight.total_cmp(&b.weight))
+            });
+            FontFamilyInfo { family, faces }
+        })
+        .collect()
+}
+
 #[tauri::command]
-fn get_system_fonts() -> Vec<String> {
-    let source = SystemSource::new();
-    let mut fonts = Vec::new();
+fn get_system_fonts(catalog: tauri::State<'_, catalog::CatalogState>) -> Vec<FontFamilyInfo> {
+    catalog.with(|catalog| catalog_families(catalog.faces()))
+}
+
+/// 追加登録したフォントファイル・フォルダの一覧
+#[tauri::command]
+fn get_font_sources(catalog: tauri::State<'_, catalog::CatalogState>) -> Vec<String> {
+    catalog.with(|catalog| catalog.sources().to_vec())
+}
+
+/// インストールされていないフォントファイル（.ttf/.otf/.ttc/.woff/.woff2）やフォルダを登録する
+/// フォルダはサブフォルダも含めて読み込み、以降はシステムフォントと同様に一覧・書き出しに使える
+#[tauri::command]
+fn add_font_sources(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    paths: Vec<String>,
+) -> Result<catalog::CatalogRefreshSummary, String> {
+    catalog.update_sources(|sources| {
+        for path in paths {
+            if !std::path::Path::new(&path).exists() {
+                return Err(format!("Font source not found: {}", path));
+            }
+            if !sources.contains(&path) {
+                sources.push(path);
+            }
+        }
+        Ok(())
+    })
+}
 
-    if let Ok(families) = source.all_families() {
-        fonts = families;
-        fonts.sort();
+#[tauri::command]
+fn remove_font_source(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    path: String,
+) -> Result<catalog::CatalogRefreshSummary, String> {
+    catalog.update_sources(|sources| {
+        sources.retain(|s| *s != path);
+        Ok(())
+    })
+}
+
+/// フォントの追加・削除・更新をカタログに反映する
+#[tauri::command]
+fn refresh_font_catalog(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+) -> Result<catalog::CatalogRefreshSummary, String> {
+    catalog.refresh()
+}
+
+/// フォント一覧の絞り込み条件（指定した条件をすべて満たすフェイスのみ残す）
+#[derive(serde::Deserialize)]
+struct FontFilter {
+    /// ファミリー名の部分一致（大文字小文字を区別しない）
+    #[serde(default)]
+    name: Option<String>,
+    /// すべて収録している必要がある文字（空白は無視）
+    #[serde(default)]
+    characters: Option<String>,
+    /// 対応している必要がある用字系（ISO 15924: "Hira", "Kana", "Hani", "Latn", "Grek", "Cyrl"）
+    #[serde(default)]
+    scripts: Vec<String>,
+    /// 縦書き用メトリクス（vhea/vmtx）があるもののみ
+    #[serde(default)]
+    has_vertical_metrics: bool,
+    /// 等幅（postのisFixedPitch）のもののみ
+    #[serde(default)]
+    monospaced: bool,
+    /// バリアブルフォントのみ
+    #[serde(default)]
+    variable: bool,
+}
+
+fn face_matches_filter(face: &catalog::CatalogFace, filter: &FontFilter, required_chars: &[char]) -> bool {
+    (!filter.has_vertical_metrics || face.has_vertical_metrics)
+        && (!filter.monospaced || face.monospaced)
+        && (!filter.variable || face.variable)
+        && required_chars.iter().all(|c| face.has_char(*c))
+}
+
+/// 条件に合うフェイスを持つファミリーのみを返す（フェイスも条件に合うもののみ）
+#[tauri::command]
+fn search_system_fonts(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    filter: FontFilter,
+) -> Result<Vec<FontFamilyInfo>, String> {
+    let mut required_chars: Vec<char> = filter
+        .characters
+        .iter()
+        .flat_map(|text| text.chars())
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    for script in &filter.scripts {
+        let chars = coverage::script_chars(script)
+            .ok_or_else(|| format!("Unknown script: {:?}", script))?;
+        required_chars.extend(chars);
     }
+    required_chars.sort_unstable();
+    required_chars.dedup();
+
+    let name = filter.name.as_ref().map(|n| n.to_lowercase());
 
-    fonts
+    Ok(catalog.with(|catalog| {
+        catalog_families(catalog.faces().filter(|face| {
+            name.as_ref().is_none_or(|n| face.family.to_lowercase().contains(n))
+                && face_matches_filter(face, &filter, &required_chars)
+        }))
+    }))
 }
 
 #[tauri::command]
-fn get_font_family_name(font_name: &str) -> Option<String> {
+fn get_font_family_name(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    font_name: &str,
+) -> Option<String> {
+    catalog
+        .with(|catalog| catalog.find_family(font_name).is_some())
+        .then(|| font_name.to_string())
+}
+
+/// フォントファイル内のフェイスの位置
+/// .ttc/.otcなどのコレクションではfont_indexでメンバーを指定する
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+struct FontLocation {
+    path: String,
+    font_index: u32,
+}
+
+fn handle_location(handle: &font_kit::handle::Handle) -> Result<FontLocation, String> {
+    match handle {
+        font_kit::handle::Handle::Path { path, font_index } => Ok(FontLocation {
+            path: path.to_string_lossy().to_string(),
+            font_index: *font_index,
+        }),
+        font_kit::handle::Handle::Memory { .. } => {
+            Err("Font is loaded from memory, not a file".to_string())
+        }
+    }
+}
+
+/// フォント名からフォントファイルの位置を取得
+fn find_font(font_name: &str) -> Result<FontLocation, String> {
     let source = SystemSource::new();
 
     match source.select_best_match(
         &[FamilyName::Title(font_name.to_string())],
         &Properties::new(),
     ) {
-        Ok(_) => Some(font_name.to_string()),
-        Err(_) => None,
+        Ok(handle) => handle_location(&handle),
+        Err(e) => Err(format!("Failed to find font: {:?}", e)),
     }
 }
 
-#[tauri::command]
-fn get_font_file_path(font_name: &str) -> Result<String, String> {
+/// PostScript名からフォントファイルの位置を取得（ウェイト・スタイル違いのフェイスを特定できる）
+fn find_font_by_postscript_name(postscript_name: &str) -> Result<FontLocation, String> {
     let source = SystemSource::new();
 
-    match source.select_best_match(
-        &[FamilyName::Title(font_name.to_string())],
-        &Properties::new(),
-    ) {
-        Ok(handle) => {
-            match handle {
-                font_kit::handle::Handle::Path { path, font_index: _ } => {
-                    Ok(path.to_string_lossy().to_string())
-                }
-                font_kit::handle::Handle::Memory { .. } => {
-                    Err("Font is loaded from memory, not a file".to_string())
-                }
+    match source.select_by_postscript_name(postscript_name) {
+        Ok(handle) => handle_location(&handle),
+        Err(e) => Err(format!("Failed to find font: {:?}", e)),
+    }
+}
+
+/// 位置 > PostScript名 > フォント名の優先順でフェイスを決定
+/// 名前はカタログから探し、カタログに無い場合のみシステムに問い合わせる
+fn resolve_font(
+    catalog: &catalog::CatalogState,
+    font_name: &str,
+    postscript_name: Option<&str>,
+    location: Option<&FontLocation>,
+) -> Result<FontLocation, String> {
+    match (location, postscript_name) {
+        (Some(location), _) => Ok(location.clone()),
+        (None, Some(postscript_name)) => catalog
+            .with(|catalog| catalog.find_postscript_name(postscript_name).map(|f| f.location.clone()))
+            .map_or_else(|| find_font_by_postscript_name(postscript_name), Ok),
+        (None, None) => catalog
+            .with(|catalog| catalog.find_family(font_name).map(|f| f.location.clone()))
+            .map_or_else(|| find_font(font_name), Ok),
+    }
+}
+
+/// WOFF/WOFF2はsfntに展開して返す
+fn read_font_data(location: &FontLocation) -> Result<Vec<u8>, String> {
+    let data = fs::read(&location.path).map_err(|e| format!("Failed to read font file: {}", e))?;
+    woff::decode_if_needed(data)
+}
+
+fn parse_face(font_data: &[u8], font_index: u32) -> Result<ttf_parser::Face<'_>, String> {
+    ttf_parser::Face::parse(font_data, font_index)
+        .map_err(|e| format!("Failed to parse font: {:?}", e))
+}
+
+/// nameテーブルから名前を取得（英語名を優先し、無ければ最初に見つかったもの）
+fn face_name(face: &ttf_parser::Face, name_id: u16) -> Option<String> {
+    let mut fallback = None;
+
+    for name in face.names() {
+        if name.name_id != name_id {
+            continue;
+        }
+        if let Some(value) = name.to_string() {
+            if name.language() == ttf_parser::Language::English_UnitedStates {
+                return Some(value);
             }
+            fallback.get_or_insert(value);
         }
-        Err(e) => Err(format!("Failed to find font: {:?}", e)),
     }
+
+    fallback
+}
+
+#[tauri::command]
+fn get_font_file_path(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    font_name: &str,
+) -> Result<FontLocation, String> {
+    resolve_font(&catalog, font_name, None, None)
+}
+
+#[derive(serde::Serialize)]
+struct CollectionFace {
+    font_index: u32,
+    family_name: Option<String>,
+    subfamily_name: Option<String>,
+    postscript_name: Option<String>,
+}
+
+/// フォントファイル内の全フェイスを列挙（単体のフォントファイルでは1件）
+#[tauri::command]
+fn get_collection_faces(path: String) -> Result<Vec<CollectionFace>, String> {
+    let location = FontLocation { path, font_index: 0 };
+    let font_data = read_font_data(&location)?;
+    let count = ttf_parser::fonts_in_collection(&font_data).unwrap_or(1);
+
+    let mut faces = Vec::new();
+    for font_index in 0..count {
+        let face = parse_face(&font_data, font_index)?;
+        faces.push(CollectionFace {
+            font_index,
+            family_name: face_name(&face, ttf_parser::name_id::TYPOGRAPHIC_FAMILY)
+                .or_else(|| face_name(&face, ttf_parser::name_id::FAMILY)),
+            subfamily_name: face_name(&face, ttf_parser::name_id::TYPOGRAPHIC_SUBFAMILY)
+                .or_else(|| face_name(&face, ttf_parser::name_id::SUBFAMILY)),
+            postscript_name: face_name(&face, ttf_parser::name_id::POST_SCRIPT_NAME),
+        });
+    }
+
+    Ok(faces)
+}
+
+/// フォントが対応するスクリプト・言語・OpenTypeフィーチャーを取得
+#[tauri::command]
+fn get_font_features(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    font_name: &str,
+    font_location: Option<FontLocation>,
+) -> Result<font_features::FontFeatureList, String> {
+    let location = resolve_font(&catalog, font_name, None, font_location.as_ref())?;
+    let font_data = read_font_data(&location)?;
+    let face = parse_face(&font_data, location.font_index)?;
+
+    Ok(font_features::collect_font_features(&face))
+}
+
+/// フォントの名前・ライセンス・OS/2・headの情報を取得
+#[tauri::command]
+fn get_font_metadata(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    font_name: &str,
+    font_location: Option<FontLocation>,
+) -> Result<metadata::FontMetadata, String> {
+    let location = resolve_font(&catalog, font_name, None, font_location.as_ref())?;
+    let font_data = read_font_data(&location)?;
+    let face = parse_face(&font_data, location.font_index)?;
+
+    Ok(metadata::collect_font_metadata(&face))
+}
+
+/// バリアブルフォントの軸・名前付きインスタンスを取得（可変でないフォントでは空）
+#[tauri::command]
+fn get_font_variations(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    font_name: &str,
+    font_location: Option<FontLocation>,
+) -> Result<variations::FontVariations, String> {
+    let location = resolve_font(&catalog, font_name, None, font_location.as_ref())?;
+    let font_data = read_font_data(&location)?;
+    let face = parse_face(&font_data, location.font_index)?;
+
+    Ok(variations::collect_font_variations(&face))
+}
+
+/// 文字集合（JIS X 0208/0213・常用漢字・Latin-1など）ごとの収録状況を取得
+/// required_textを指定すると、その文字がすべて収録されているかも確認する
+#[tauri::command]
+fn get_font_coverage(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    font_name: &str,
+    font_location: Option<FontLocation>,
+    required_text: Option<String>,
+) -> Result<coverage::FontCoverage, String> {
+    let location = resolve_font(&catalog, font_name, None, font_location.as_ref())?;
+    let font_data = read_font_data(&location)?;
+    let face = parse_face(&font_data, location.font_index)?;
+
+    Ok(coverage::collect_font_coverage(&face, required_text.as_deref()))
 }
 
 #[derive(serde::Deserialize)]
@@ -80,9 +425,41 @@ struct StrokeLayer {
     color: String,
 }
 
+#[derive(serde::Deserialize)]
+struct FontFeature {
+    /// OpenTypeフィーチャータグ（例: "palt", "liga", "ss01"）
+    tag: String,
+    enabled: bool,
+    /// 値（salt/aaltなどで代替字形の番号を指定する場合のみ、省略時は1）
+    #[serde(default)]
+    value: Option<u32>,
+    /// 適用範囲（改行を除いたテキスト全体での文字インデックス、endは含まない）、省略時は全体
+    #[serde(default)]
+    start: Option<usize>,
+    #[serde(default)]
+    end: Option<usize>,
+}
+
+/// プライマリフォントに無い文字を描画するフォールバックフォント
+#[derive(serde::Deserialize)]
+struct FallbackFont {
+    font_name: String,
+    #[serde(default)]
+    postscript_name: Option<String>,
+    #[serde(default)]
+    font_location: Option<FontLocation>,
+}
+
 #[derive(serde::Deserialize)]
 struct SvgExportRequest {
     font_name: String,
+    /// PostScript名でフェイスを指定する場合（例: "NotoSansJP-Black"）
+    #[serde(default)]
+    postscript_name: Option<String>,
+    /// フェイスを直接指定する場合（get_system_fontsのlocationやコレクション内の特定メンバー）
+    /// 指定が無ければpostscript_name、font_nameの順で検索
+    #[serde(default)]
+    font_location: Option<FontLocation>,
     text: String,
     font_size: f64,
     text_color: String,
@@ -91,10 +468,56 @@ struct SvgExportRequest {
     export_mode: String,
     /// true = 縦書き, false = 横書き
     vertical: bool,
+    /// シェイピング時に指定するOpenTypeフィーチャー
+    #[serde(default)]
+    features: Vec<FontFeature>,
+    /// バリアブルフォントの軸の座標（省略した軸はデフォルト値）
+    #[serde(default)]
+    variations: Vec<variations::AxisCoordinate>,
+    /// 埋め込みが制限されたフォント（Restricted License）でもアウトラインを書き出す
+    #[serde(default)]
+    allow_restricted_license: bool,
+    /// フォントに無い文字が含まれる場合は書き出さずにエラーにする
+    #[serde(default)]
+    fail_on_missing_glyphs: bool,
+    /// プライマリフォントに無い文字を先頭から順に探すフォント（例: 欧文フォント → 和文ゴシック）
+    #[serde(default)]
+    fallback_fonts: Vec<FallbackFont>,
+    /// ストロークの角の結合方法（"round" | "miter" | "bevel"、省略時は"round"）
+    #[serde(default)]
+    stroke_join: Option<String>,
+    /// ストロークをstroke属性ではなく、幾何的に計算した塗りのアウトラインとして書き出す（SVGのみ）
+    /// "path_only"でもストロークのアウトラインを書き出す
+    #[serde(default)]
+    outline_strokes: bool,
+    /// 全文字のアウトラインを1つの複合パスに結合し、重なりを取り除く（曲線は折れ線になる）
+    /// SVGではストロークもレイヤーごとに結合する（DXF・G-code・HPGLにも適用、3Dモデルは常に結合する）
+    #[serde(default)]
+    union_outlines: bool,
+    /// 文字ごとの<g>ではなく、ストロークのレイヤーごと（id="stroke-N"）と塗り（id="fill"）を
+    /// 最上位の<g>レイヤーにする（InkscapeやIllustratorでレイヤーとして読み込める、SVGのみ）
+    #[serde(default)]
+    layer_groups: bool,
+}
+
+/// フォントに無いため書き出されなかった文字
+#[derive(serde::Serialize)]
+struct MissingGlyph {
+    /// 改行を除いたテキスト全体での文字インデックス（<g id="char-N">と同じ）
+    char_index: usize,
+    character: String,
+    codepoint: u32,
+}
+
+#[derive(serde::Serialize)]
+struct SvgExportResult {
+    svg: String,
+    warnings: Vec<licensing::ExportWarning>,
+    missing_glyphs: Vec<MissingGlyph>,
 }
 
 struct PathBuilder {
-    path_data: String,
+    segments: Vec<outline::Segment>,
     scale: f64,
     offset_x: f64,
     offset_y: f64,
@@ -103,7 +526,7 @@ struct PathBuilder {
 impl PathBuilder {
     fn new(scale: f64, offset_x: f64, offset_y: f64) -> Self {
         Self {
-            path_data: String::new(),
+            segments: Vec::new(),
             scale,
             offset_x,
             offset_y,
@@ -124,13 +547,13 @@ impl ttf_parser::OutlineBuilder for PathBuilder {
     fn move_to(&mut self, x: f32, y: f32) {
         let tx = self.transform_x(x);
         let ty = self.transform_y(y);
-        self.path_data.push_str(&format!("M{:.2} {:.2}", tx, ty));
+        self.segments.push(outline::Segment::MoveTo(tx, ty));
     }
 
     fn line_to(&mut self, x: f32, y: f32) {
         let tx = self.transform_x(x);
         let ty = self.transform_y(y);
-        self.path_data.push_str(&format!("L{:.2} {:.2}", tx, ty));
+        self.segments.push(outline::Segment::LineTo(tx, ty));
     }
 
     fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
@@ -138,7 +561,7 @@ impl ttf_parser::OutlineBuilder for PathBuilder {
         let ty1 = self.transform_y(y1);
         let tx = self.transform_x(x);
         let ty = self.transform_y(y);
-        self.path_data.push_str(&format!("Q{:.2} {:.2} {:.2} {:.2}", tx1, ty1, tx, ty));
+        self.segments.push(outline::Segment::QuadTo(tx1, ty1, tx, ty));
     }
 
     fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
@@ -148,11 +571,11 @@ impl ttf_parser::OutlineBuilder for PathBuilder {
         let ty2 = self.transform_y(y2);
         let tx = self.transform_x(x);
         let ty = self.transform_y(y);
-        self.path_data.push_str(&format!("C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2}", tx1, ty1, tx2, ty2, tx, ty));
+        self.segments.push(outline::Segment::CurveTo(tx1, ty1, tx2, ty2, tx, ty));
     }
 
     fn close(&mut self) {
-        self.path_data.push('Z');
+        self.segments.push(outline::Segment::Close);
     }
 }
 
@@ -166,31 +589,274 @@ fn escape_xml_char(ch: char) -> String {
     }
 }
 
-/// 横書き用SVG生成
-fn generate_horizontal_svg(
-    face: &ttf_parser::Face,
-    request: &SvgExportRequest,
+fn escape_xml(text: &str) -> String {
+    text.chars().map(escape_xml_char).collect()
+}
+
+/// シェイピング結果のクラスタ（1つ以上の文字と、それに対応するグリフ群）
+/// 合字や結合文字は複数の文字が1クラスタに、複雑な文字体系では1文字が複数グリフになる
+struct ShapedCluster {
+    text: String,      // クラスタに含まれる文字列
+    char_index: usize, // テキスト全体での先頭文字のインデックス
+    face_index: usize, // 描画に使うフェイス（0 = プライマリ、1以降 = フォールバック）
+    glyphs: Vec<(rustybuzz::GlyphInfo, rustybuzz::GlyphPosition)>,
+}
+
+/// 書き出しに使うフェイス（プライマリとフォールバック）
+struct ExportFace<'a> {
+    face: BuzzFace<'a>,
+    /// <g data-font>に記録する名前（PostScript名、無ければ指定されたフォント名）
+    name: String,
     scale: f64,
-    is_path_only: bool,
-    include_stroke: bool,
-    enabled_stroke_layers: &[&StrokeLayer],
-) -> String {
-    let lines: Vec<&str> = request.text.lines().collect();
-    let line_height = request.font_size * 1.2;
+}
+
+fn parse_tag(tag: &str) -> Result<ttf_parser::Tag, String> {
+    if tag.is_empty() || tag.len() > 4 || !tag.bytes().all(|b| (0x20..=0x7E).contains(&b)) {
+        return Err(format!("Invalid OpenType tag: {:?}", tag));
+    }
+    Ok(ttf_parser::Tag::from_bytes_lossy(tag.as_bytes()))
+}
+
+/// リクエストのフィーチャー指定を1行分のrustybuzzフィーチャーに変換
+/// 範囲はテキスト全体の文字インデックスから行内のバイトオフセット（cluster値）に変換する
+fn line_features(
+    features: &[FontFeature],
+    line: &str,
+    char_base: usize,
+) -> Result<Vec<rustybuzz::Feature>, String> {
+    let line_chars = line.chars().count();
+    let mut result = Vec::new();
+
+    for feature in features {
+        let tag = parse_tag(&feature.tag)?;
+        let value = if feature.enabled {
+            feature.value.unwrap_or(1)
+        } else {
+            0
+        };
+
+        // 行と範囲の重なりを行内の文字インデックスで求める
+        let start = feature.start.unwrap_or(0).saturating_sub(char_base);
+        let end = feature
+            .end
+            .map(|e| e.saturating_sub(char_base).min(line_chars))
+            .unwrap_or(line_chars);
+        if feature.start.is_some() || feature.end.is_some() {
+            if start >= end {
+                continue;
+            }
+            let byte_offset = |index: usize| {
+                line.char_indices()
+                    .nth(index)
+                    .map(|(i, _)| i)
+                    .unwrap_or(line.len())
+            };
+            result.push(rustybuzz::Feature::new(tag, value, byte_offset(start)..byte_offset(end)));
+        } else {
+            result.push(rustybuzz::Feature::new(tag, value, ..));
+        }
+    }
+
+    Ok(result)
+}
+
+/// 1行をrustybuzzでシェイピング
+/// 横書きでは用字系から方向を推定する（アラビア文字などは右から左）
+fn shape_line(
+    face: &BuzzFace,
+    line: &str,
+    vertical: bool,
+    features: &[rustybuzz::Feature],
+) -> rustybuzz::GlyphBuffer {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(line);
+    buffer.guess_segment_properties();
+    if vertical {
+        buffer.set_direction(Direction::TopToBottom);
+    }
+
+    rustybuzz::shape(face, features, buffer)
+}
+
+/// シェイピング結果をクラスタ単位にまとめる
+/// グリフは表示順（RTLでは論理順の逆）のまま並ぶ
+fn group_clusters(
+    line: &str,
+    char_base: usize,
+    face_index: usize,
+    glyph_buffer: &rustybuzz::GlyphBuffer,
+) -> Vec<ShapedCluster> {
+    let glyph_infos = glyph_buffer.glyph_infos();
+    let glyph_positions = glyph_buffer.glyph_positions();
+
+    // クラスタの終端を求めるため、開始位置（バイトオフセット）を昇順に並べる
+    let mut cluster_starts: Vec<usize> = glyph_infos.iter().map(|i| i.cluster as usize).collect();
+    cluster_starts.sort_unstable();
+    cluster_starts.dedup();
+
+    let mut clusters: Vec<ShapedCluster> = Vec::new();
+
+    for (info, pos) in glyph_infos.iter().zip(glyph_positions.iter()) {
+        let start = info.cluster as usize;
+
+        if let Some(last) = clusters.last_mut() {
+            if last.glyphs[0].0.cluster == info.cluster {
+                last.glyphs.push((*info, *pos));
+                continue;
+            }
+        }
+
+        let end = cluster_starts
+            .iter()
+            .find(|&&s| s > start)
+            .copied()
+            .unwrap_or(line.len());
+
+        clusters.push(ShapedCluster {
+            text: line[start..end].to_string(),
+            char_index: char_base + line[..start].chars().count(),
+            face_index,
+            glyphs: vec![(*info, *pos)],
+        });
+    }
+
+    clusters
+}
+
+/// 結合文字・異体字セレクタ・ZWJなど、直前の文字と同じフェイスで描画すべき文字
+fn continues_cluster(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0300..=0x036F
+            | 0x1AB0..=0x1AFF
+            | 0x1DC0..=0x1DFF
+            | 0x200C..=0x200D
+            | 0x20D0..=0x20FF
+            | 0x3099..=0x309A
+            | 0xFE00..=0xFE0F
+            | 0xFE20..=0xFE2F
+            | 0x1F3FB..=0x1F3FF
+            | 0xE0020..=0xE007F
+            | 0xE0100..=0xE01EF
+    )
+}
+
+/// 1行を文字ごとに描画するフェイスで区切り、ランごとにシェイピングしてクラスタを表示順に返す
+/// フェイスはチェーンの先頭から最初に収録しているもの（どれにも無ければプライマリ）
+fn shape_line_runs(
+    faces: &[ExportFace],
+    line: &str,
+    char_base: usize,
+    vertical: bool,
+    features: &[FontFeature],
+) -> Result<Vec<ShapedCluster>, String> {
+    // ランの開始位置（行内のバイトオフセット）とフェイス
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (offset, c) in line.char_indices() {
+        let current = runs.last().map(|&(_, face_index)| face_index);
+        let face_index = match current {
+            // 空白は今のフェイスにあればランを切らない
+            Some(current)
+                if continues_cluster(c)
+                    || (c.is_whitespace() && faces[current].face.glyph_index(c).is_some()) =>
+            {
+                current
+            }
+            _ => faces
+                .iter()
+                .position(|f| f.face.glyph_index(c).is_some())
+                .unwrap_or(0),
+        };
+        if current != Some(face_index) {
+            runs.push((offset, face_index));
+        }
+    }
+
+    let mut run_clusters = Vec::new();
+    for (i, &(start, face_index)) in runs.iter().enumerate() {
+        let end = runs.get(i + 1).map(|&(s, _)| s).unwrap_or(line.len());
+        let run = &line[start..end];
+        let run_char_base = char_base + line[..start].chars().count();
+
+        let run_features = line_features(features, run, run_char_base)?;
+        let glyph_buffer = shape_line(&faces[face_index].face, run, vertical, &run_features);
+        run_clusters.push(group_clusters(run, run_char_base, face_index, &glyph_buffer));
+    }
+
+    // 右から左の行ではランも逆順に並べる
+    if !vertical {
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(line);
+        buffer.guess_segment_properties();
+        if buffer.direction() == Direction::RightToLeft {
+            run_clusters.reverse();
+        }
+    }
+
+    Ok(run_clusters.into_iter().flatten().collect())
+}
+
+/// シェイピング結果で.notdefになった文字を列挙
+/// 結合文字などでクラスタ内の一部だけが無い場合は、cmapに無い文字を報告する
+fn find_missing_glyphs(faces: &[ExportFace], lines: &[Vec<ShapedCluster>]) -> Vec<MissingGlyph> {
+    let mut missing = Vec::new();
+
+    for cluster in lines.iter().flatten() {
+        if cluster.glyphs.iter().all(|(info, _)| info.glyph_id != 0) {
+            continue;
+        }
+
+        let face = &faces[cluster.face_index].face;
+        let unmapped: Vec<(usize, char)> = cluster
+            .text
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| face.glyph_index(*c).is_none())
+            .collect();
+        let chars = if unmapped.is_empty() {
+            cluster.text.chars().take(1).enumerate().collect()
+        } else {
+            unmapped
+        };
+
+        missing.extend(chars.into_iter().map(|(offset, c)| MissingGlyph {
+            char_index: cluster.char_index + offset,
+            character: c.to_string(),
+            codepoint: c as u32,
+        }));
+    }
+
+    // RTLの行ではクラスタが表示順に並ぶため、テキストの順に戻す
+    missing.sort_by_key(|m| m.char_index);
+    missing
+}
+
+/// 横書きの配置（rustybuzzでシェイピングし、カーニング・合字・GPOSオフセットを反映）
+fn layout_horizontal(
+    faces: &[ExportFace],
+    mut line_clusters: Vec<Vec<ShapedCluster>>,
+    font_size: f64,
+) -> outline::TextLayout {
+    let line_height = font_size * 1.2;
 
     // 各行の幅を計算
     let mut max_width: f64 = 0.0;
     let mut line_widths: Vec<f64> = Vec::new();
 
-    for line in &lines {
-        let mut width: f64 = 0.0;
-        for ch in line.chars() {
-            if let Some(glyph_id) = face.glyph_index(ch) {
-                if let Some(advance) = face.glyph_hor_advance(glyph_id) {
-                    width += (advance as f64) * scale;
-                }
-            }
+    for clusters in line_clusters.iter_mut() {
+        // cmapに無い文字（.notdef）は描画も送りもしない
+        for cluster in clusters.iter_mut() {
+            cluster.glyphs.retain(|(info, _)| info.glyph_id != 0);
         }
+
+        let width: f64 = clusters
+            .iter()
+            .flat_map(|c| {
+                let scale = faces[c.face_index].scale;
+                c.glyphs.iter().map(move |(_, pos)| (pos.x_advance as f64) * scale)
+            })
+            .sum();
+
         line_widths.push(width);
         if width > max_width {
             max_width = width;
@@ -199,20 +865,12 @@ fn generate_horizontal_svg(
 
     let padding = 20.0;
     let svg_width = max_width + padding * 2.0;
-    let total_height = (lines.len() as f64) * line_height;
+    let total_height = (line_clusters.len() as f64) * line_height;
     let svg_height = total_height + padding * 2.0;
 
-    let mut svg_content = format!(
-        r#"<?xml version="1.0" encoding="UTF-8"?>
-<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}">
-"#,
-        svg_width, svg_height, svg_width, svg_height
-    );
-
-    let mut char_index: usize = 0;
-
-    for (line_index, line) in lines.iter().enumerate() {
-        if line.is_empty() {
+    let mut glyphs = Vec::new();
+    for (line_index, clusters) in line_clusters.iter().enumerate() {
+        if clusters.is_empty() {
             continue;
         }
 
@@ -222,420 +880,1135 @@ fn generate_horizontal_svg(
 
         let mut cursor_x = start_x;
 
-        for ch in line.chars() {
-            if ch.is_whitespace() {
-                if let Some(glyph_id) = face.glyph_index(ch) {
-                    if let Some(advance) = face.glyph_hor_advance(glyph_id) {
-                        cursor_x += (advance as f64) * scale;
-                    }
-                }
-                char_index += 1;
+        for cluster in clusters {
+            let export_face = &faces[cluster.face_index];
+            let (face, scale) = (&export_face.face, export_face.scale);
+
+            // クラスタ内の全グリフのパスを連結
+            let mut segments = Vec::new();
+            for (info, pos) in &cluster.glyphs {
+                // GPOSのx/yオフセットをグリフの原点に反映
+                let origin_x = cursor_x + (pos.x_offset as f64) * scale;
+                let origin_y = baseline_y - (pos.y_offset as f64) * scale;
+
+                let mut builder = PathBuilder::new(scale, origin_x, origin_y);
+                face.outline_glyph(ttf_parser::GlyphId(info.glyph_id as u16), &mut builder);
+                segments.extend(builder.segments);
+
+                cursor_x += (pos.x_advance as f64) * scale;
+            }
+
+            if segments.is_empty() {
+                continue;
+            }
+
+            glyphs.push(outline::GlyphOutline {
+                char_index: cluster.char_index,
+                text: cluster.text.clone(),
+                font_name: export_face.name.clone(),
+                segments,
+            });
+        }
+    }
+
+    outline::TextLayout {
+        width: svg_width,
+        height: svg_height,
+        glyphs,
+    }
+}
+
+/// 縦書きの配置（rustybuzzでvert featureを適用し、GPOSオフセットを反映）
+fn layout_vertical(
+    faces: &[ExportFace],
+    mut line_clusters: Vec<Vec<ShapedCluster>>,
+    font_size: f64,
+) -> outline::TextLayout {
+    let line_height = font_size * 1.2; // 列間隔
+
+    // 各列の高さを計算
+    let mut max_height: f64 = 0.0;
+    for clusters in line_clusters.iter_mut() {
+        // cmapに無い文字（.notdef）は描画も送りもしない（横書きと同じ）
+        for cluster in clusters.iter_mut() {
+            cluster.glyphs.retain(|(info, _)| info.glyph_id != 0);
+        }
+
+        let height: f64 = clusters
+            .iter()
+            .flat_map(|c| {
+                // rustybuzzのy_advanceは負の値で返ってくる（上から下へ進むため）
+                // 結合文字など送りの無いグリフは0になる
+                let scale = faces[c.face_index].scale;
+                c.glyphs.iter().map(move |(_, pos)| -(pos.y_advance as f64) * scale)
+            })
+            .sum();
+        if height > max_height {
+            max_height = height;
+        }
+    }
+
+    // パディングを大きめに取る（文字がはみ出さないように）
+    let padding = font_size * 0.5 + 20.0;
+    let svg_width = (line_clusters.len() as f64) * line_height + padding * 2.0;
+    let svg_height = max_height + padding * 2.0;
+
+    // 縦書きは右から左に列を配置
+    let mut glyphs = Vec::new();
+    for (col_index, clusters) in line_clusters.iter().enumerate() {
+        if clusters.is_empty() {
+            continue;
+        }
+
+        // 右から左へ配置（col_index=0が一番右）
+        let col_center_x = svg_width - padding - (col_index as f64 + 0.5) * line_height;
+
+        // cursor_yは各グリフの縦書き原点のY座標（SVG座標系）
+        // 最初の文字の縦書き原点はpaddingの位置から開始
+        let mut cursor_y = padding;
+
+        for cluster in clusters {
+            let export_face = &faces[cluster.face_index];
+            let (face, scale) = (&export_face.face, export_face.scale);
+
+            // クラスタ内の全グリフのパスを連結
+            let mut segments = Vec::new();
+            for (info, pos) in &cluster.glyphs {
+                // rustybuzzの縦書きのオフセットは、縦書き原点（列の中心・グリフの上端）から
+                // グリフの原点までの位置で、GPOSのx/yオフセットも含む
+                let origin_x = col_center_x + (pos.x_offset as f64) * scale;
+                let origin_y = cursor_y - (pos.y_offset as f64) * scale;
+
+                let mut builder = PathBuilder::new(scale, origin_x, origin_y);
+                face.outline_glyph(ttf_parser::GlyphId(info.glyph_id as u16), &mut builder);
+                segments.extend(builder.segments);
+
+                // 縦方向に進める
+                cursor_y -= (pos.y_advance as f64) * scale;
+            }
+
+            if segments.is_empty() {
                 continue;
             }
 
-            if let Some(glyph_id) = face.glyph_index(ch) {
-                let mut builder = PathBuilder::new(scale, cursor_x, baseline_y);
-                face.outline_glyph(glyph_id, &mut builder);
-                let path_data = &builder.path_data;
+            glyphs.push(outline::GlyphOutline {
+                char_index: cluster.char_index,
+                text: cluster.text.clone(),
+                font_name: export_face.name.clone(),
+                segments,
+            });
+        }
+    }
+
+    outline::TextLayout {
+        width: svg_width,
+        height: svg_height,
+        glyphs,
+    }
+}
+
+/// SVGでアウトラインを結合するときに曲線を折れ線にする許容誤差（px）
+const SVG_UNION_TOLERANCE: f64 = 0.05;
+
+/// Inkscapeのレイヤー指定（inkscape:groupmode）に使う名前空間
+const INKSCAPE_NAMESPACE: &str = "http://www.inkscape.org/namespaces/inkscape";
 
-                if !path_data.is_empty() {
-                    let escaped_char = escape_xml_char(ch);
+/// namespacesは<svg>に追加する名前空間の宣言（例: ` xmlns:inkscape="..."`）
+fn svg_header(layout: &outline::TextLayout, namespaces: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg"{} width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}">
+"#,
+        namespaces, layout.width, layout.height, layout.width, layout.height
+    )
+}
 
-                    // 各文字を<g>でグループ化（複数パスの文字に対応）
-                    svg_content.push_str(&format!(
-                        r#"  <g id="char-{}" data-char="{}">"#,
-                        char_index, escaped_char
+/// 配置したアウトラインからSVGを生成
+/// outline_strokesがtrueなら、ストロークを元の塗りと結合した塗りのアウトラインにする
+fn render_svg(
+    layout: &outline::TextLayout,
+    text_color: &str,
+    is_path_only: bool,
+    include_stroke: bool,
+    enabled_stroke_layers: &[&StrokeLayer],
+    stroke_join: offset::LineJoin,
+    outline_strokes: bool,
+) -> String {
+    let mut svg_content = svg_header(layout, "");
+
+    for glyph in &layout.glyphs {
+        let path_data = outline::svg_path_data(&glyph.segments);
+
+        // 各文字を<g>でグループ化（複数パスの文字に対応）
+        svg_content.push_str(&format!(
+            r#"  <g id="char-{}" data-char="{}" data-font="{}">"#,
+            glyph.char_index,
+            escape_xml(&glyph.text),
+            escape_xml(&glyph.font_name)
+        ));
+        svg_content.push('\n');
+
+        if is_path_only {
+            // パスのみ（アウトライン化したストロークも属性なしで含める）
+            if outline_strokes {
+                for layer in enabled_stroke_layers.iter() {
+                    let stroke_data = outline::svg_path_data(&outline_glyph_stroke(
+                        &glyph.segments,
+                        layer.width,
+                        stroke_join,
                     ));
+                    svg_content.push_str(&format!(r#"    <path d="{}"/>"#, stroke_data));
                     svg_content.push('\n');
-
-                    if is_path_only {
-                        // パスのみ
-                        svg_content.push_str(&format!(r#"    <path d="{}"/>"#, path_data));
-                        svg_content.push('\n');
-                    } else {
-                        // 塗り/ストロークあり
-                        if include_stroke && !enabled_stroke_layers.is_empty() {
-                            for layer in enabled_stroke_layers.iter() {
-                                svg_content.push_str(&format!(
-                                    r#"    <path d="{}" fill="{}" stroke="{}" stroke-width="{:.1}" stroke-linejoin="round" stroke-linecap="round"/>"#,
-                                    path_data, layer.color, layer.color, layer.width * 2.0
-                                ));
-                                svg_content.push('\n');
-                            }
-                        }
+                }
+            }
+            svg_content.push_str(&format!(r#"    <path d="{}"/>"#, path_data));
+            svg_content.push('\n');
+        } else {
+            // 塗り/ストロークあり
+            if include_stroke && !enabled_stroke_layers.is_empty() {
+                for layer in enabled_stroke_layers.iter() {
+                    if outline_strokes {
+                        let stroke_data = outline::svg_path_data(&outline_glyph_stroke(
+                            &glyph.segments,
+                            layer.width,
+                            stroke_join,
+                        ));
                         svg_content.push_str(&format!(
                             r#"    <path d="{}" fill="{}"/>"#,
-                            path_data, request.text_color
+                            stroke_data, layer.color
+                        ));
+                    } else {
+                        svg_content.push_str(&format!(
+                            r#"    <path d="{}" fill="{}" stroke="{}" stroke-width="{:.1}" stroke-linejoin="{}" stroke-linecap="round"/>"#,
+                            path_data, layer.color, layer.color, layer.width * 2.0, stroke_join.svg_name()
                         ));
-                        svg_content.push('\n');
                     }
-
-                    svg_content.push_str("  </g>\n");
-                }
-
-                if let Some(advance) = face.glyph_hor_advance(glyph_id) {
-                    cursor_x += (advance as f64) * scale;
+                    svg_content.push('\n');
                 }
             }
-
-            char_index += 1;
+            svg_content.push_str(&format!(
+                r#"    <path d="{}" fill="{}"/>"#,
+                path_data, text_color
+            ));
+            svg_content.push('\n');
         }
+
+        svg_content.push_str("  </g>\n");
     }
 
     svg_content.push_str("</svg>");
     svg_content
 }
 
-/// 縦書き用のPathBuilder
-/// OpenType仕様に基づき、縦書きでは:
-/// - Y座標: glyph_y_origin (top side bearing + bbox top) から下方向へ描画
-/// - X座標: グリフの水平方向中心を列の中心に配置
-struct VerticalPathBuilder {
-    path_data: String,
-    scale: f64,
-    col_center_x: f64,    // 列の中心X座標
-    glyph_top_y: f64,     // グリフの配置位置（SVG座標系での上端）
-    glyph_hor_advance: f64, // グリフの水平advance（中央揃え用）
+/// 1文字のストロークの範囲と元の塗りを結合した、ストロークレイヤーの形
+/// （内側が打ち消されないよう、ストロークの範囲だけでなく塗りも含める）
+fn outline_glyph_stroke(
+    segments: &[outline::Segment],
+    width: f64,
+    stroke_join: offset::LineJoin,
+) -> Vec<outline::Segment> {
+    let stroke = offset::stroke_outline(segments, width, stroke_join);
+    boolean::union([segments, stroke.as_slice()], SVG_UNION_TOLERANCE)
 }
 
-impl VerticalPathBuilder {
-    fn new(scale: f64, col_center_x: f64, glyph_top_y: f64, glyph_hor_advance: f64) -> Self {
-        Self {
-            path_data: String::new(),
-            scale,
-            col_center_x,
-            glyph_top_y,
-            glyph_hor_advance,
+/// ストロークの範囲と元の塗りを合わせた、ストロークレイヤーの形を全文字で結合する
+/// fillはunion_fillで結合済みの塗り（レイヤーごとに塗りを結合し直さないよう使い回す）
+fn union_stroke_layer(
+    layout: &outline::TextLayout,
+    fill: &[outline::Segment],
+    width: f64,
+    stroke_join: offset::LineJoin,
+) -> Vec<outline::Segment> {
+    let stroke_outlines: Vec<Vec<outline::Segment>> = layout
+        .glyphs
+        .iter()
+        .map(|glyph| offset::stroke_outline(&glyph.segments, width, stroke_join))
+        .collect();
+    boolean::union(
+        std::iter::once(fill).chain(stroke_outlines.iter().map(Vec::as_slice)),
+        SVG_UNION_TOLERANCE,
+    )
+}
+
+/// 全文字の塗りのアウトラインを結合する
+fn union_fill(layout: &outline::TextLayout) -> Vec<outline::Segment> {
+    boolean::union(
+        layout.glyphs.iter().map(|glyph| glyph.segments.as_slice()),
+        SVG_UNION_TOLERANCE,
+    )
+}
+
+/// 全文字のアウトラインを結合したSVGを生成
+/// ストロークのレイヤーごとと塗りで1つずつの複合パスにする（ストロークは幾何的に計算する）
+fn render_union_svg(
+    layout: &outline::TextLayout,
+    text_color: &str,
+    is_path_only: bool,
+    stroke_layers: &[&StrokeLayer],
+    stroke_join: offset::LineJoin,
+) -> String {
+    let mut svg_content = svg_header(layout, "");
+    let push_path = |svg_content: &mut String, segments: &[outline::Segment], color: &str| {
+        let path_data = outline::svg_path_data(segments);
+        if is_path_only {
+            svg_content.push_str(&format!(r#"  <path d="{}"/>"#, path_data));
+        } else {
+            svg_content.push_str(&format!(r#"  <path d="{}" fill="{}"/>"#, path_data, color));
         }
+        svg_content.push('\n');
+    };
+
+    let fill = union_fill(layout);
+    for layer in stroke_layers {
+        let segments = union_stroke_layer(layout, &fill, layer.width, stroke_join);
+        push_path(&mut svg_content, &segments, &layer.color);
     }
+    push_path(&mut svg_content, &fill, text_color);
 
-    fn transform_x(&self, x: f32) -> f64 {
-        // グリフ座標系のx=0は左端、x=hor_advanceは右端
-        // グリフを水平方向中央揃えにするため、x - hor_advance/2 でオフセット
-        let glyph_center_offset = self.glyph_hor_advance / 2.0;
-        self.col_center_x + ((x as f64) * self.scale - glyph_center_offset)
+    svg_content.push_str("</svg>");
+    svg_content
+}
+
+/// ストロークのレイヤーごとと塗りを最上位の<g>レイヤーにしたSVGを生成
+/// 色や線の指定はレイヤーの<g>にまとめ、各レイヤーには全文字を<g id="{レイヤー}-char-N">で入れる
+/// stroke_layersは（ストロークレイヤーの番号, レイヤー）を外側から並べたもの
+fn render_layered_svg(
+    layout: &outline::TextLayout,
+    text_color: &str,
+    is_path_only: bool,
+    stroke_layers: &[(usize, &StrokeLayer)],
+    stroke_join: offset::LineJoin,
+    outline_strokes: bool,
+    union_outlines: bool,
+) -> String {
+    let mut svg_content = svg_header(
+        layout,
+        &format!(r#" xmlns:inkscape="{}""#, INKSCAPE_NAMESPACE),
+    );
+    // Illustratorはidを、Inkscapeはinkscape:labelをレイヤー名にする
+    let open_layer = |svg_content: &mut String, id: &str, attributes: &str| {
+        svg_content.push_str(&format!(
+            r#"  <g id="{}" inkscape:groupmode="layer" inkscape:label="{}" data-name="{}"{}>"#,
+            id, id, id, attributes
+        ));
+        svg_content.push('\n');
+    };
+    let push_glyph = |svg_content: &mut String, id: &str, glyph: &outline::GlyphOutline, paths: &[&str]| {
+        svg_content.push_str(&format!(
+            r#"    <g id="{}-char-{}" data-char="{}" data-font="{}">"#,
+            id,
+            glyph.char_index,
+            escape_xml(&glyph.text),
+            escape_xml(&glyph.font_name)
+        ));
+        svg_content.push('\n');
+        for path_data in paths {
+            svg_content.push_str(&format!(r#"      <path d="{}"/>"#, path_data));
+            svg_content.push('\n');
+        }
+        svg_content.push_str("    </g>\n");
+    };
+    let push_union = |svg_content: &mut String, segments: &[outline::Segment]| {
+        svg_content.push_str(&format!(r#"    <path d="{}"/>"#, outline::svg_path_data(segments)));
+        svg_content.push('\n');
+    };
+
+    // 塗りはストロークの各レイヤーにも含めるため、一度だけ求めて使い回す
+    let fill_union = union_outlines.then(|| union_fill(layout));
+    let fill_paths: Vec<String> = match union_outlines {
+        true => Vec::new(),
+        false => layout
+            .glyphs
+            .iter()
+            .map(|glyph| outline::svg_path_data(&glyph.segments))
+            .collect(),
+    };
+
+    for (number, layer) in stroke_layers {
+        let id = format!("stroke-{}", number);
+        let attributes = if is_path_only {
+            String::new()
+        } else if outline_strokes || union_outlines {
+            format!(r#" fill="{}""#, layer.color)
+        } else {
+            format!(
+                r#" fill="{}" stroke="{}" stroke-width="{:.1}" stroke-linejoin="{}" stroke-linecap="round""#,
+                layer.color,
+                layer.color,
+                layer.width * 2.0,
+                stroke_join.svg_name()
+            )
+        };
+        open_layer(&mut svg_content, &id, &attributes);
+
+        if let Some(fill) = &fill_union {
+            push_union(&mut svg_content, &union_stroke_layer(layout, fill, layer.width, stroke_join));
+        } else {
+            for (glyph, fill_path) in layout.glyphs.iter().zip(&fill_paths) {
+                if outline_strokes {
+                    let stroke = outline_glyph_stroke(&glyph.segments, layer.width, stroke_join);
+                    push_glyph(&mut svg_content, &id, glyph, &[&outline::svg_path_data(&stroke)]);
+                } else {
+                    push_glyph(&mut svg_content, &id, glyph, &[fill_path]);
+                }
+            }
+        }
+        svg_content.push_str("  </g>\n");
     }
 
-    fn transform_y(&self, y: f32) -> f64 {
-        // フォント座標系: Y上が正、原点はベースライン上
-        // SVG座標系: Y下が正
-        // glyph_top_yはグリフを配置する位置（SVG座標系での上端付近）
-        // フォント座標のyを反転してSVG座標に変換
-        self.glyph_top_y - (y as f64) * self.scale
+    let attributes = if is_path_only {
+        String::new()
+    } else {
+        format!(r#" fill="{}""#, text_color)
+    };
+    open_layer(&mut svg_content, "fill", &attributes);
+    if let Some(fill) = &fill_union {
+        push_union(&mut svg_content, fill);
+    } else {
+        for (glyph, fill_path) in layout.glyphs.iter().zip(&fill_paths) {
+            push_glyph(&mut svg_content, "fill", glyph, &[fill_path]);
+        }
     }
+    svg_content.push_str("  </g>\n");
+
+    svg_content.push_str("</svg>");
+    svg_content
 }
 
-impl ttf_parser::OutlineBuilder for VerticalPathBuilder {
-    fn move_to(&mut self, x: f32, y: f32) {
-        let tx = self.transform_x(x);
-        let ty = self.transform_y(y);
-        self.path_data.push_str(&format!("M{:.2} {:.2}", tx, ty));
+/// 書き出し形式によらない、配置済みのアウトラインと確認結果
+struct TextExport {
+    layout: outline::TextLayout,
+    warnings: Vec<licensing::ExportWarning>,
+    missing_glyphs: Vec<MissingGlyph>,
+}
+
+/// フォントを読み込んでテキストをシェイピングし、アウトラインを配置する
+/// 埋め込み許可とフォントに無い文字の確認もここで行う
+fn layout_text_export(
+    catalog: &catalog::CatalogState,
+    request: &SvgExportRequest,
+) -> Result<TextExport, String> {
+    // フォントファイルの位置を取得（コレクションの場合はフェイス番号も）
+    // 先頭がプライマリ、以降がフォールバック
+    let mut font_sources = vec![(
+        request.font_name.as_str(),
+        resolve_font(
+            catalog,
+            &request.font_name,
+            request.postscript_name.as_deref(),
+            request.font_location.as_ref(),
+        )?,
+    )];
+    for fallback in &request.fallback_fonts {
+        let location = resolve_font(
+            catalog,
+            &fallback.font_name,
+            fallback.postscript_name.as_deref(),
+            fallback.font_location.as_ref(),
+        )?;
+        font_sources.push((fallback.font_name.as_str(), location));
     }
 
-    fn line_to(&mut self, x: f32, y: f32) {
-        let tx = self.transform_x(x);
-        let ty = self.transform_y(y);
-        self.path_data.push_str(&format!("L{:.2} {:.2}", tx, ty));
+    // フォントファイルを読み込み
+    let font_data = font_sources
+        .iter()
+        .map(|(_, location)| read_font_data(location))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // 横書き・縦書きともにrustybuzzでシェイピングし、同じフェイスからアウトラインを取得
+    let mut faces = Vec::new();
+    for ((font_name, location), data) in font_sources.iter().zip(&font_data) {
+        let face = parse_face(data, location.font_index)?;
+        let name = face_name(&face, ttf_parser::name_id::POST_SCRIPT_NAME)
+            .unwrap_or_else(|| font_name.to_string());
+        let scale = request.font_size / face.units_per_em() as f64;
+        faces.push(ExportFace {
+            face: BuzzFace::from_face(face),
+            name,
+            scale,
+        });
     }
 
-    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
-        let tx1 = self.transform_x(x1);
-        let ty1 = self.transform_y(y1);
-        let tx = self.transform_x(x);
-        let ty = self.transform_y(y);
-        self.path_data.push_str(&format!("Q{:.2} {:.2} {:.2} {:.2}", tx1, ty1, tx, ty));
+    // 埋め込み許可を確認（制限されたフォントは明示的な許可が無ければ書き出さない）
+    let mut warnings = licensing::check_outline_export(&faces[0].face, request.allow_restricted_license)?;
+
+    // バリアブルフォントの軸を設定（シェイピングとアウトラインの両方に反映される）
+    // フォールバックは同じ軸を持つ場合のみ反映する
+    for coordinate in &request.variations {
+        let tag = parse_tag(&coordinate.tag)?;
+        for (i, export_face) in faces.iter_mut().enumerate() {
+            if export_face.face.set_variation(tag, coordinate.value).is_none() && i == 0 {
+                return Err(format!("Font has no variation axis {:?}", coordinate.tag));
+            }
+        }
     }
 
-    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
-        let tx1 = self.transform_x(x1);
-        let ty1 = self.transform_y(y1);
-        let tx2 = self.transform_x(x2);
-        let ty2 = self.transform_y(y2);
-        let tx = self.transform_x(x);
-        let ty = self.transform_y(y);
-        self.path_data.push_str(&format!("C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2}", tx1, ty1, tx2, ty2, tx, ty));
+    // 文字ごとに描画するフェイスを選んでシェイピング
+    let mut line_clusters = Vec::new();
+    let mut char_base: usize = 0;
+    for line in request.text.lines() {
+        line_clusters.push(shape_line_runs(
+            &faces,
+            line,
+            char_base,
+            request.vertical,
+            &request.features,
+        )?);
+        char_base += line.chars().count();
     }
 
-    fn close(&mut self) {
-        self.path_data.push('Z');
+    // 実際に使われたフォールバックの埋め込み許可も確認
+    let mut used_fallbacks: Vec<usize> = line_clusters
+        .iter()
+        .flatten()
+        .map(|c| c.face_index)
+        .filter(|&i| i != 0)
+        .collect();
+    used_fallbacks.sort_unstable();
+    used_fallbacks.dedup();
+    for i in used_fallbacks {
+        warnings.extend(licensing::check_outline_export(
+            &faces[i].face,
+            request.allow_restricted_license,
+        )?);
     }
-}
 
-/// 縦書き用SVG生成（rustybuzzでvert featureを適用）
-fn generate_vertical_svg(
-    font_data: &[u8],
-    face: &ttf_parser::Face,
-    request: &SvgExportRequest,
-    scale: f64,
-    is_path_only: bool,
-    include_stroke: bool,
-    enabled_stroke_layers: &[&StrokeLayer],
-) -> Result<String, String> {
-    // rustybuzz用のフォントフェイスを作成
-    let font_data_arc = Arc::new(font_data.to_vec());
-    let buzz_face = BuzzFace::from_slice(&font_data_arc, 0)
-        .ok_or("Failed to create rustybuzz face")?;
-
-    let lines: Vec<&str> = request.text.lines().collect();
-    let line_height = request.font_size * 1.2; // 列間隔
-
-    // グリフ情報を収集
-    struct GlyphInfo {
-        glyph_id: ttf_parser::GlyphId,
-        y_advance: f64,          // 縦方向の送り量（スケール適用済み）
-        ch: char,
-        glyph_hor_advance: f64,  // 水平方向のadvance（中央揃え用、スケール適用済み）
-        glyph_height: f64,       // グリフの高さ（bbox_top - bbox_bottom、スケール適用済み）
-        glyph_y_origin: f64,     // 縦書き原点Y（スケール適用済み）
-    }
-
-    let mut column_infos: Vec<Vec<GlyphInfo>> = Vec::new();
-    let mut max_height: f64 = 0.0;
+    // フォントに無い文字を確認（書き出し時は.notdefを描画せずに詰める）
+    let missing_glyphs = find_missing_glyphs(&faces, &line_clusters);
+    if request.fail_on_missing_glyphs && !missing_glyphs.is_empty() {
+        let list: Vec<String> = missing_glyphs
+            .iter()
+            .map(|m| format!("{:?} (U+{:04X}) at {}", m.character, m.codepoint, m.char_index))
+            .collect();
+        return Err(format!("Font has no glyphs for: {}", list.join(", ")));
+    }
 
-    for line in &lines {
-        // rustybuzzでシェイピング（縦書きモード）
-        let mut buffer = UnicodeBuffer::new();
-        buffer.push_str(line);
-        buffer.set_direction(Direction::TopToBottom);
+    let layout = if request.vertical {
+        layout_vertical(&faces, line_clusters, request.font_size)
+    } else {
+        layout_horizontal(&faces, line_clusters, request.font_size)
+    };
 
-        let glyph_buffer = rustybuzz::shape(&buzz_face, &[], buffer);
-        let glyph_infos = glyph_buffer.glyph_infos();
-        let glyph_positions = glyph_buffer.glyph_positions();
+    Ok(TextExport {
+        layout,
+        warnings,
+        missing_glyphs,
+    })
+}
 
-        let chars: Vec<char> = line.chars().collect();
-        let mut column_glyphs: Vec<GlyphInfo> = Vec::new();
-        let mut height: f64 = 0.0;
+/// 有効なストロークレイヤーを外側から順に返す
+fn enabled_stroke_layers(stroke_layers: &[StrokeLayer]) -> Vec<&StrokeLayer> {
+    stroke_layers.iter().filter(|l| l.enabled).rev().collect()
+}
 
-        for (i, (info, pos)) in glyph_infos.iter().zip(glyph_positions.iter()).enumerate() {
-            let glyph_id = ttf_parser::GlyphId(info.glyph_id as u16);
-            let ch = if i < chars.len() { chars[i] } else { '?' };
+/// 塗りで描く形式（PNG・PDFなど）で使うストロークレイヤー
+/// export_modeが"fill_and_stroke"以外ならストロークは描かない
+fn painted_stroke_layers(request: &SvgExportRequest) -> Vec<&StrokeLayer> {
+    if request.export_mode == "fill_and_stroke" {
+        enabled_stroke_layers(&request.stroke_layers)
+    } else {
+        Vec::new()
+    }
+}
 
-            // 縦書きの送り量を取得
-            // rustybuzzのy_advanceは負の値で返ってくる（上から下へ進むため）
-            let y_advance = if pos.y_advance != 0 {
-                -(pos.y_advance as f64) * scale
-            } else {
-                // フォールバック: 縦書きadvanceを使用、なければフォントサイズ
-                face.glyph_ver_advance(glyph_id)
-                    .map(|adv| (adv as f64) * scale)
-                    .unwrap_or(request.font_size)
-            };
+#[tauri::command]
+fn generate_svg(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    request: SvgExportRequest,
+) -> Result<SvgExportResult, String> {
+    let stroke_join = offset::LineJoin::parse(request.stroke_join.as_deref().unwrap_or("round"))?;
+    let export = layout_text_export(&catalog, &request)?;
 
-            // 水平方向のadvance（中央揃え用）
-            let glyph_hor_advance = face.glyph_hor_advance(glyph_id)
-                .map(|adv| (adv as f64) * scale)
-                .unwrap_or(request.font_size);
-
-            // グリフの縦書き原点Y座標を取得
-            // OpenType仕様: y_origin = top_side_bearing + bbox.y_max
-            // ttf-parserのglyph_y_originはVORGテーブルから直接取得（CFFフォント用）
-            // TrueTypeフォントではbboxとtop_side_bearingから計算
-            let glyph_y_origin = if let Some(y_origin) = face.glyph_y_origin(glyph_id) {
-                // VORGテーブルがある場合（CFFフォント）
-                (y_origin as f64) * scale
-            } else if let Some(bbox) = face.glyph_bounding_box(glyph_id) {
-                // TrueTypeフォント: bbox.y_max + top_side_bearing
-                let tsb = face.glyph_ver_side_bearing(glyph_id).unwrap_or(0);
-                ((bbox.y_max as i32 + tsb as i32) as f64) * scale
-            } else {
-                // フォールバック: ascenderを使用
-                face.ascender() as f64 * scale
-            };
+    // エクスポートモードの判定
+    let is_path_only = request.export_mode == "path_only";
+    let include_stroke = request.export_mode == "fill_and_stroke";
 
-            // グリフの高さ（境界ボックスから）
-            let glyph_height = if let Some(bbox) = face.glyph_bounding_box(glyph_id) {
-                ((bbox.y_max - bbox.y_min) as f64) * scale
+    // 有効なストロークレイヤーを取得（逆順で外側から）
+    let stroke_layers = enabled_stroke_layers(&request.stroke_layers);
+
+    let svg = if request.layer_groups {
+        // レイヤー名はストロークレイヤーの番号（無効なものも数える）にする
+        let layers: Vec<(usize, &StrokeLayer)> =
+            if include_stroke || (is_path_only && request.outline_strokes) {
+                request
+                    .stroke_layers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, layer)| layer.enabled)
+                    .map(|(index, layer)| (index + 1, layer))
+                    .rev()
+                    .collect()
             } else {
-                request.font_size
+                Vec::new()
             };
+        render_layered_svg(
+            &export.layout,
+            &request.text_color,
+            is_path_only,
+            &layers,
+            stroke_join,
+            request.outline_strokes,
+            request.union_outlines,
+        )
+    } else if request.union_outlines {
+        // "path_only"ではoutline_strokesの指定がある場合だけストロークを含める
+        let union_layers = if include_stroke || (is_path_only && request.outline_strokes) {
+            stroke_layers
+        } else {
+            Vec::new()
+        };
+        render_union_svg(
+            &export.layout,
+            &request.text_color,
+            is_path_only,
+            &union_layers,
+            stroke_join,
+        )
+    } else {
+        render_svg(
+            &export.layout,
+            &request.text_color,
+            is_path_only,
+            include_stroke,
+            &stroke_layers,
+            stroke_join,
+            request.outline_strokes,
+        )
+    };
 
-            column_glyphs.push(GlyphInfo {
-                glyph_id,
-                y_advance,
-                ch,
-                glyph_hor_advance,
-                glyph_height,
-                glyph_y_origin,
-            });
+    Ok(SvgExportResult {
+        svg,
+        warnings: export.warnings,
+        missing_glyphs: export.missing_glyphs,
+    })
+}
 
-            height += y_advance;
-        }
+#[derive(serde::Deserialize)]
+struct PngExportRequest {
+    /// フォント・テキスト・ストロークの指定（generate_svgと同じ）
+    /// export_modeが"fill_and_stroke"以外ならストロークは描かない
+    #[serde(flatten)]
+    base: SvgExportRequest,
+    /// 解像度（SVGの1pxを1/96インチとする）、指定するとscaleより優先
+    #[serde(default)]
+    dpi: Option<f64>,
+    /// SVGのサイズに対する倍率（省略時は1）
+    #[serde(default)]
+    scale: Option<f64>,
+    /// 背景色（"#RRGGBB"）、背景画像も無ければ透明
+    #[serde(default)]
+    background_color: Option<String>,
+    /// 背景画像のパス（背景色より優先）
+    #[serde(default)]
+    background_image_path: Option<String>,
+    /// 背景色・背景画像の指定に関わらず背景を透明にする
+    #[serde(default)]
+    transparent: bool,
+}
 
-        column_infos.push(column_glyphs);
-        if height > max_height {
-            max_height = height;
-        }
+#[derive(serde::Serialize)]
+struct PngExportResult {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    warnings: Vec<licensing::ExportWarning>,
+    missing_glyphs: Vec<MissingGlyph>,
+}
+
+/// generate_svgと同じアウトラインをPNGにラスタライズする
+#[tauri::command]
+fn generate_png(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    request: PngExportRequest,
+) -> Result<PngExportResult, String> {
+    let scale = match request.dpi {
+        Some(dpi) => dpi / raster::CSS_DPI,
+        None => request.scale.unwrap_or(1.0),
+    };
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(format!("Invalid scale: {}", scale));
     }
 
-    // パディングを大きめに取る（文字がはみ出さないように）
-    let padding = request.font_size * 0.5 + 20.0;
-    let svg_width = (lines.len() as f64) * line_height + padding * 2.0;
-    let svg_height = max_height + padding * 2.0;
+    let export = layout_text_export(&catalog, &request.base)?;
 
-    let mut svg_content = format!(
-        r#"<?xml version="1.0" encoding="UTF-8"?>
-<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}">
-"#,
-        svg_width, svg_height, svg_width, svg_height
-    );
+    let background = match (&request.background_image_path, &request.background_color) {
+        _ if request.transparent => raster::Background::Transparent,
+        (Some(path), _) => raster::Background::Image(path),
+        (None, Some(color)) => raster::Background::Color(color),
+        (None, None) => raster::Background::Transparent,
+    };
+    let stroke_layers = painted_stroke_layers(&request.base);
+    let stroke_join = offset::LineJoin::parse(request.base.stroke_join.as_deref().unwrap_or("round"))?;
+
+    let pixmap = raster::render(
+        &export.layout,
+        scale,
+        &background,
+        &request.base.text_color,
+        &stroke_layers,
+        stroke_join,
+    )?;
+    let data = raster::encode_png(&pixmap, scale * raster::CSS_DPI)?;
+
+    Ok(PngExportResult {
+        data,
+        width: pixmap.width(),
+        height: pixmap.height(),
+        warnings: export.warnings,
+        missing_glyphs: export.missing_glyphs,
+    })
+}
 
-    let mut char_index: usize = 0;
+/// PDF・EPS書き出しの指定
+#[derive(serde::Deserialize)]
+struct VectorExportRequest {
+    /// フォント・テキスト・ストロークの指定（generate_svgと同じ）
+    /// export_modeが"fill_and_stroke"以外ならストロークは描かない
+    #[serde(flatten)]
+    base: SvgExportRequest,
+    /// 色をDeviceCMYKで書き出す（RGBからの単純変換、黒はK100）
+    #[serde(default)]
+    cmyk: bool,
+}
 
-    // 縦書きは右から左に列を配置
-    for (col_index, column_glyphs) in column_infos.iter().enumerate() {
-        if column_glyphs.is_empty() {
-            continue;
-        }
+#[derive(serde::Serialize)]
+struct PdfExportResult {
+    data: Vec<u8>,
+    warnings: Vec<licensing::ExportWarning>,
+    missing_glyphs: Vec<MissingGlyph>,
+}
 
-        // 右から左へ配置（col_index=0が一番右）
-        let col_center_x = svg_width - padding - (col_index as f64 + 0.5) * line_height;
+/// generate_svgと同じアウトラインを、テキストの範囲の大きさの1ページのPDFにする
+#[tauri::command]
+fn generate_pdf(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    request: VectorExportRequest,
+) -> Result<PdfExportResult, String> {
+    let export = layout_text_export(&catalog, &request.base)?;
+    let stroke_join = offset::LineJoin::parse(request.base.stroke_join.as_deref().unwrap_or("round"))?;
+    let data = pdf::render_pdf(
+        &export.layout,
+        &request.base.text_color,
+        &painted_stroke_layers(&request.base),
+        stroke_join,
+        request.cmyk,
+    )?;
+
+    Ok(PdfExportResult {
+        data,
+        warnings: export.warnings,
+        missing_glyphs: export.missing_glyphs,
+    })
+}
 
-        // cursor_yは各グリフの「縦書き原点」のY座標（SVG座標系）
-        // 最初の文字の縦書き原点はpaddingの位置から開始
-        let mut cursor_y = padding;
+#[derive(serde::Serialize)]
+struct EpsExportResult {
+    eps: String,
+    warnings: Vec<licensing::ExportWarning>,
+    missing_glyphs: Vec<MissingGlyph>,
+}
 
-        for glyph_info in column_glyphs {
-            if glyph_info.ch.is_whitespace() {
-                cursor_y += glyph_info.y_advance;
-                char_index += 1;
-                continue;
-            }
+/// generate_svgと同じアウトラインを、2次ベジェを3次に変換してEPSにする
+#[tauri::command]
+fn generate_eps(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    request: VectorExportRequest,
+) -> Result<EpsExportResult, String> {
+    let export = layout_text_export(&catalog, &request.base)?;
+    let stroke_join = offset::LineJoin::parse(request.base.stroke_join.as_deref().unwrap_or("round"))?;
+    let eps = eps::render_eps(
+        &export.layout,
+        &request.base.text_color,
+        &painted_stroke_layers(&request.base),
+        stroke_join,
+        request.cmyk,
+    )?;
+
+    Ok(EpsExportResult {
+        eps,
+        warnings: export.warnings,
+        missing_glyphs: export.missing_glyphs,
+    })
+}
 
-            // グリフの配置位置
-            // glyph_top_y: SVG座標系でのグリフ描画開始Y位置
-            // 縦書き原点(cursor_y)から、フォント座標系の原点位置分だけオフセット
-            let glyph_top_y = cursor_y + glyph_info.glyph_y_origin;
-
-            let mut builder = VerticalPathBuilder::new(
-                scale,
-                col_center_x,
-                glyph_top_y,
-                glyph_info.glyph_hor_advance,
-            );
-            face.outline_glyph(glyph_info.glyph_id, &mut builder);
-            let path_data = &builder.path_data;
-
-            if !path_data.is_empty() {
-                let escaped_char = escape_xml_char(glyph_info.ch);
-
-                // 各文字を<g>でグループ化（複数パスの文字に対応）
-                svg_content.push_str(&format!(
-                    r#"  <g id="char-{}" data-char="{}">"#,
-                    char_index, escaped_char
-                ));
-                svg_content.push('\n');
-
-                if is_path_only {
-                    // パスのみ
-                    svg_content.push_str(&format!(r#"    <path d="{}"/>"#, path_data));
-                    svg_content.push('\n');
-                } else {
-                    // 塗り/ストロークあり
-                    if include_stroke && !enabled_stroke_layers.is_empty() {
-                        for layer in enabled_stroke_layers.iter() {
-                            svg_content.push_str(&format!(
-                                r#"    <path d="{}" fill="{}" stroke="{}" stroke-width="{:.1}" stroke-linejoin="round" stroke-linecap="round"/>"#,
-                                path_data, layer.color, layer.color, layer.width * 2.0
-                            ));
-                            svg_content.push('\n');
-                        }
-                    }
-                    svg_content.push_str(&format!(
-                        r#"    <path d="{}" fill="{}"/>"#,
-                        path_data, request.text_color
-                    ));
-                    svg_content.push('\n');
-                }
+#[derive(serde::Deserialize)]
+struct DxfExportRequest {
+    /// フォント・テキストの指定（generate_svgと同じ、ストロークは書き出さない）
+    #[serde(flatten)]
+    base: SvgExportRequest,
+    /// フォントサイズ（1em）に相当する高さ（mm）
+    text_height_mm: f64,
+    /// "polyline"（折れ線のLWPOLYLINE） | "spline"（SPLINE）、省略時は"polyline"
+    #[serde(default)]
+    curve_mode: Option<String>,
+    /// 折れ線にするとき（union_outlinesで結合するときも含む）の許容誤差（mm、省略時は0.05）
+    #[serde(default)]
+    tolerance_mm: Option<f64>,
+}
 
-                svg_content.push_str("  </g>\n");
-            }
+#[derive(serde::Serialize)]
+struct DxfExportResult {
+    dxf: String,
+    /// アウトライン全体の大きさ（mm）
+    width_mm: f64,
+    height_mm: f64,
+    warnings: Vec<licensing::ExportWarning>,
+    missing_glyphs: Vec<MissingGlyph>,
+}
 
-            // 縦方向に進める
-            cursor_y += glyph_info.y_advance;
-            char_index += 1;
-        }
+/// generate_svgと同じアウトラインを、カッティングプロッター・レーザー加工機向けのDXF（mm）にする
+#[tauri::command]
+fn generate_dxf(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    request: DxfExportRequest,
+) -> Result<DxfExportResult, String> {
+    if !request.text_height_mm.is_finite() || request.text_height_mm <= 0.0 {
+        return Err(format!("Invalid text height: {}", request.text_height_mm));
     }
+    let tolerance = request.tolerance_mm.unwrap_or(0.05);
+    if !tolerance.is_finite() || tolerance <= 0.0 {
+        return Err(format!("Invalid tolerance: {}", tolerance));
+    }
+    let curve_mode = match request.curve_mode.as_deref().unwrap_or("polyline") {
+        "polyline" => dxf::CurveMode::Polyline(tolerance),
+        "spline" => dxf::CurveMode::Spline,
+        other => return Err(format!("Unknown curve mode: {:?}", other)),
+    };
 
-    svg_content.push_str("</svg>");
-    Ok(svg_content)
+    let export = layout_text_export(&catalog, &request.base)?;
+    let scale = request.text_height_mm / request.base.font_size;
+    let layout = if request.base.union_outlines {
+        boolean::union_layout(&export.layout, tolerance / scale)
+    } else {
+        export.layout
+    };
+    let bounds = layout.painted_bounds(&[], offset::LineJoin::Round);
+
+    Ok(DxfExportResult {
+        dxf: dxf::render_dxf(&layout, scale, &curve_mode),
+        width_mm: bounds.width() * scale,
+        height_mm: bounds.height() * scale,
+        warnings: export.warnings,
+        missing_glyphs: export.missing_glyphs,
+    })
 }
 
-#[tauri::command]
-fn generate_svg(request: SvgExportRequest) -> Result<String, String> {
-    let source = SystemSource::new();
+#[derive(serde::Deserialize)]
+struct PlotterExportRequest {
+    /// フォント・テキストの指定（generate_svgと同じ、ストロークは書き出さない）
+    #[serde(flatten)]
+    base: SvgExportRequest,
+    /// フォントサイズ（1em）に相当する高さ（mm）
+    text_height_mm: f64,
+    /// "gcode" | "hpgl"
+    format: String,
+    /// 曲線を折れ線にするときの許容誤差（mm、省略時は0.05）
+    #[serde(default)]
+    tolerance_mm: Option<f64>,
+    /// G-codeの送り速度（mm/min、省略時は1000）
+    #[serde(default)]
+    feed_rate: Option<f64>,
+    /// G-codeでペンを上げる命令（省略時は"G0 Z5"）
+    #[serde(default)]
+    pen_up: Option<String>,
+    /// G-codeでペンを下ろす命令（省略時は"G1 Z0"）
+    #[serde(default)]
+    pen_down: Option<String>,
+    /// trueなら移動距離を短くする並べ替えをせず、文字の順に描く
+    #[serde(default)]
+    keep_contour_order: bool,
+}
 
-    // フォントファイルのパスを取得
-    let font_path = match source.select_best_match(
-        &[FamilyName::Title(request.font_name.clone())],
-        &Properties::new(),
-    ) {
-        Ok(handle) => {
-            match handle {
-                font_kit::handle::Handle::Path { path, font_index: _ } => path,
-                font_kit::handle::Handle::Memory { .. } => {
-                    return Err("Font is loaded from memory, not a file".to_string());
-                }
+#[derive(serde::Serialize)]
+struct PlotterExportResult {
+    /// G-codeまたはHPGLの命令列
+    commands: String,
+    warnings: Vec<licensing::ExportWarning>,
+    missing_glyphs: Vec<MissingGlyph>,
+}
+
+/// generate_svgと同じアウトラインを折れ線にして、ペンプロッター・CNC彫刻向けのG-code・HPGLにする
+#[tauri::command]
+fn generate_plotter(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    request: PlotterExportRequest,
+) -> Result<PlotterExportResult, String> {
+    if !request.text_height_mm.is_finite() || request.text_height_mm <= 0.0 {
+        return Err(format!("Invalid text height: {}", request.text_height_mm));
+    }
+    let tolerance = request.tolerance_mm.unwrap_or(0.05);
+    if !tolerance.is_finite() || tolerance <= 0.0 {
+        return Err(format!("Invalid tolerance: {}", tolerance));
+    }
+    let format = match request.format.as_str() {
+        "gcode" => {
+            let feed_rate = request.feed_rate.unwrap_or(1000.0);
+            if !feed_rate.is_finite() || feed_rate <= 0.0 {
+                return Err(format!("Invalid feed rate: {}", feed_rate));
             }
+            plotter::PlotterFormat::GCode(plotter::GCodeOptions {
+                feed_rate,
+                pen_up: request.pen_up.clone().unwrap_or_else(|| "G0 Z5".to_string()),
+                pen_down: request.pen_down.clone().unwrap_or_else(|| "G1 Z0".to_string()),
+            })
         }
-        Err(e) => return Err(format!("Failed to find font: {:?}", e)),
+        "hpgl" => plotter::PlotterFormat::Hpgl,
+        other => return Err(format!("Unknown plotter format: {:?}", other)),
     };
 
-    // フォントファイルを読み込み
-    let font_data = fs::read(&font_path)
-        .map_err(|e| format!("Failed to read font file: {}", e))?;
+    let export = layout_text_export(&catalog, &request.base)?;
+    let scale = request.text_height_mm / request.base.font_size;
+    let layout = if request.base.union_outlines {
+        boolean::union_layout(&export.layout, tolerance / scale)
+    } else {
+        export.layout
+    };
 
-    let face = ttf_parser::Face::parse(&font_data, 0)
-        .map_err(|e| format!("Failed to parse font: {:?}", e))?;
+    Ok(PlotterExportResult {
+        commands: plotter::render_plotter(
+            &layout,
+            scale,
+            tolerance,
+            !request.keep_contour_order,
+            &format,
+        ),
+        warnings: export.warnings,
+        missing_glyphs: export.missing_glyphs,
+    })
+}
 
-    let units_per_em = face.units_per_em() as f64;
-    let scale = request.font_size / units_per_em;
+#[derive(serde::Deserialize)]
+struct MeshExportRequest {
+    /// フォント・テキストの指定（generate_svgと同じ、ストロークは含めない）
+    #[serde(flatten)]
+    base: SvgExportRequest,
+    /// フォントサイズ（1em）に相当する高さ（mm）
+    text_height_mm: f64,
+    /// "stl" | "obj" | "glb"
+    format: String,
+    /// 押し出す厚さ（mm）
+    depth_mm: f64,
+    /// 上面の縁を面取りする幅（mm、省略時は面取りしない）
+    #[serde(default)]
+    bevel_mm: Option<f64>,
+    /// 曲線を折れ線にするときの許容誤差（mm、省略時は0.05）
+    #[serde(default)]
+    tolerance_mm: Option<f64>,
+}
 
-    // エクスポートモードの判定
-    let is_path_only = request.export_mode == "path_only";
-    let include_stroke = request.export_mode == "fill_and_stroke";
+#[derive(serde::Serialize)]
+struct MeshExportResult {
+    data: Vec<u8>,
+    triangle_count: usize,
+    warnings: Vec<licensing::ExportWarning>,
+    missing_glyphs: Vec<MissingGlyph>,
+}
 
-    // 有効なストロークレイヤーを取得（逆順で外側から）
-    let enabled_stroke_layers: Vec<&StrokeLayer> = request.stroke_layers
+/// generate_svgと同じアウトラインを押し出して、3Dプリント向けのSTL・OBJ・glTFにする
+#[tauri::command]
+fn generate_mesh(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    request: MeshExportRequest,
+) -> Result<MeshExportResult, String> {
+    if !request.text_height_mm.is_finite() || request.text_height_mm <= 0.0 {
+        return Err(format!("Invalid text height: {}", request.text_height_mm));
+    }
+    if !request.depth_mm.is_finite() || request.depth_mm <= 0.0 {
+        return Err(format!("Invalid depth: {}", request.depth_mm));
+    }
+    let bevel = request.bevel_mm.unwrap_or(0.0);
+    if !bevel.is_finite() || bevel < 0.0 || bevel >= request.depth_mm {
+        return Err(format!("Invalid bevel: {}", bevel));
+    }
+    let tolerance = request.tolerance_mm.unwrap_or(0.05);
+    if !tolerance.is_finite() || tolerance <= 0.0 {
+        return Err(format!("Invalid tolerance: {}", tolerance));
+    }
+    let format = match request.format.as_str() {
+        "stl" => mesh::MeshFormat::Stl,
+        "obj" => mesh::MeshFormat::Obj,
+        "glb" => mesh::MeshFormat::Glb,
+        other => return Err(format!("Unknown mesh format: {:?}", other)),
+    };
+
+    let export = layout_text_export(&catalog, &request.base)?;
+    let scale = request.text_height_mm / request.base.font_size;
+    let extrusion = mesh::Extrusion {
+        depth: request.depth_mm,
+        bevel,
+    };
+    // 重なった輪郭（隣り合う文字・バリアブルフォントの輪郭）が交わる立体にならないよう、常に結合する
+    let layout = boolean::union_layout(&export.layout, tolerance / scale);
+    let text_mesh = mesh::extrude(&layout, scale, tolerance, &extrusion)?;
+
+    let data = match format {
+        mesh::MeshFormat::Stl => mesh::encode_stl(&text_mesh),
+        mesh::MeshFormat::Obj => mesh::encode_obj(&text_mesh).into_bytes(),
+        mesh::MeshFormat::Glb => mesh::encode_glb(&text_mesh, &request.base.text_color)?,
+    };
+
+    Ok(MeshExportResult {
+        data,
+        triangle_count: text_mesh.triangles.len(),
+        warnings: export.warnings,
+        missing_glyphs: export.missing_glyphs,
+    })
+}
+
+#[derive(serde::Deserialize)]
+struct SubsetExportRequest {
+    font_name: String,
+    #[serde(default)]
+    postscript_name: Option<String>,
+    #[serde(default)]
+    font_location: Option<FontLocation>,
+    /// 残す文字（改行は無視する）
+    #[serde(default)]
+    text: String,
+    /// テキストに加えて残す文字集合のID（get_font_coverageと同じ、例: "joyo", "latin1"）
+    #[serde(default)]
+    charsets: Vec<String>,
+    /// 既定に加えて有効・無効にするGSUBフィーチャー（範囲指定のあるものは無効化しない）
+    #[serde(default)]
+    features: Vec<FontFeature>,
+    /// 縦書き用の字形（vert/vrt2）も残す
+    #[serde(default)]
+    vertical: bool,
+    /// "sfnt"（アウトラインに合わせてTTFまたはOTF） | "woff2"
+    format: String,
+    #[serde(default)]
+    allow_restricted_license: bool,
+}
+
+#[derive(serde::Serialize)]
+struct SubsetExportResult {
+    data: Vec<u8>,
+    /// 保存時の拡張子（"ttf" | "otf" | "woff2"）
+    extension: String,
+    /// 残したグリフ数
+    glyph_count: usize,
+    /// 元のフォントのグリフ数
+    total_glyphs: u16,
+    /// 書き出したフォントのバイト数
+    size: usize,
+    warnings: Vec<licensing::ExportWarning>,
+    /// テキスト中でフォントに無い文字
+    missing_glyphs: Vec<MissingGlyph>,
+}
+
+/// テキスト（と文字集合）に必要なグリフだけを残したフォントを作る
+#[tauri::command]
+fn export_font_subset(
+    catalog: tauri::State<'_, catalog::CatalogState>,
+    request: SubsetExportRequest,
+) -> Result<SubsetExportResult, String> {
+    let location = resolve_font(
+        &catalog,
+        &request.font_name,
+        request.postscript_name.as_deref(),
+        request.font_location.as_ref(),
+    )?;
+    let font_data = read_font_data(&location)?;
+    let face = parse_face(&font_data, location.font_index)?;
+
+    let mut warnings = licensing::check_subset_export(&face, request.allow_restricted_license)?;
+
+    // 改行（CRLFのCRを含む）の扱いと文字インデックスはlayout_text_exportと同じにする
+    let text_chars: Vec<char> = request.text.lines().flat_map(str::chars).collect();
+    let mut chars = text_chars.clone();
+    for id in &request.charsets {
+        chars.extend(coverage::charset_chars(id).ok_or_else(|| format!("Unknown charset: {:?}", id))?);
+    }
+
+    let mut feature_overrides = Vec::new();
+    for feature in &request.features {
+        // 一部の範囲だけ無効にしたフィーチャーは他の範囲で使われるため残す
+        let ranged = feature.start.is_some() || feature.end.is_some();
+        if feature.enabled || !ranged {
+            feature_overrides.push((parse_tag(&feature.tag)?, feature.enabled));
+        }
+    }
+
+    let missing_glyphs = text_chars
         .iter()
-        .filter(|l| l.enabled)
-        .rev()
+        .enumerate()
+        .filter(|(_, c)| face.glyph_index(**c).is_none())
+        .map(|(char_index, c)| MissingGlyph {
+            char_index,
+            character: c.to_string(),
+            codepoint: *c as u32,
+        })
         .collect();
 
-    if request.vertical {
-        generate_vertical_svg(
-            &font_data,
-            &face,
-            &request,
-            scale,
-            is_path_only,
-            include_stroke,
-            &enabled_stroke_layers,
-        )
-    } else {
-        Ok(generate_horizontal_svg(
-            &face,
-            &request,
-            scale,
-            is_path_only,
-            include_stroke,
-            &enabled_stroke_layers,
-        ))
+    let subset = subset::subset_font(&face, &chars, &feature_overrides, request.vertical)?;
+    if !subset.dropped_color_tables.is_empty() {
+        warnings.push(licensing::ExportWarning {
+            code: "color_glyphs_dropped".to_string(),
+            message: format!(
+                "Color glyph tables ({}) are not supported in subsets and were removed; color glyphs will use their outlines if any.",
+                subset.dropped_color_tables.join(", ")
+            ),
+        });
     }
+    let (data, extension) = match request.format.as_str() {
+        "sfnt" => (subset.to_sfnt(), if subset.is_cff() { "otf" } else { "ttf" }),
+        "woff2" => (woff::encode_woff2(subset.flavor, &subset.tables)?, "woff2"),
+        other => return Err(format!("Unknown font format: {:?}", other)),
+    };
+
+    Ok(SubsetExportResult {
+        size: data.len(),
+        data,
+        extension: extension.to_string(),
+        glyph_count: subset.glyph_count,
+        total_glyphs: face.number_of_glyphs(),
+        warnings,
+        missing_glyphs,
+    })
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .setup(|app| {
+            // インストール先のフォルダは書き込めないことがあるため、インデックスはキャッシュフォルダに置く
+            let index_path = app
+                .path()
+                .app_cache_dir()
+                .ok()
+                .map(|dir| dir.join(catalog::CATALOG_FILENAME));
+            let catalog = catalog::CatalogState::new(index_path);
+            catalog.refresh_in_background();
+            app.manage(catalog);
+            Ok(())
+        })
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .invoke_handler(tauri::generate_handler![
             greet,
             get_system_fonts,
+            search_system_fonts,
+            refresh_font_catalog,
+            get_font_sources,
+            add_font_sources,
+            remove_font_source,
             get_font_family_name,
             get_font_file_path,
+            get_font_features,
+            get_font_variations,
+            get_font_metadata,
+            get_font_coverage,
+            get_collection_faces,
             get_exe_dir,
-            generate_svg
+            generate_svg,
+            generate_png,
+            generate_pdf,
+            generate_eps,
+            generate_dxf,
+            generate_plotter,
+            generate_mesh,
+            export_font_subset
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
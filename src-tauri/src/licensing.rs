@@ -0,0 +1,113 @@
+//! アウトライン書き出し前の埋め込み許可（OS/2 fsType）とライセンスの確認
+
+use ttf_parser::{name_id, Face, Tag};
+
+use crate::face_name;
+use crate::metadata::embedding_level;
+use crate::sfnt::read_u16;
+
+/// エクスポート時の警告
+#[derive(serde::Serialize)]
+pub struct ExportWarning {
+    /// "restricted_license" | "bitmap_embedding_only" | "preview_and_print" | "no_subsetting"
+    /// | "color_glyphs_dropped"
+    pub code: String,
+    pub message: String,
+}
+
+/// アウトラインの書き出しが許可されているか確認する
+/// Restricted License・ビットマップ埋め込みのみのフォントはallow_restrictedが無ければ拒否し、
+/// 許可された場合や印刷・プレビューのみ許可のフォントでは警告を返す
+pub fn check_outline_export(face: &Face, allow_restricted: bool) -> Result<Vec<ExportWarning>, String> {
+    let Some(fs_type) = face
+        .raw_face()
+        .table(Tag::from_bytes(b"OS/2"))
+        .and_then(|data| read_u16(data, 8))
+    else {
+        return Ok(Vec::new());
+    };
+
+    let font_name = face_name(face, name_id::FULL_NAME).unwrap_or_else(|| "This font".to_string());
+    let license = match face_name(face, name_id::LICENSE_URL) {
+        Some(url) => format!(" License: {}", url),
+        None => String::new(),
+    };
+
+    let mut warnings = Vec::new();
+    let mut refusals = Vec::new();
+
+    if embedding_level(fs_type) == "restricted" {
+        refusals.push(ExportWarning {
+            code: "restricted_license".to_string(),
+            message: format!(
+                "{} has Restricted License embedding (OS/2 fsType); converting it to outlines is not permitted without a separate license.{}",
+                font_name, license
+            ),
+        });
+    } else if embedding_level(fs_type) == "preview_and_print" {
+        warnings.push(ExportWarning {
+            code: "preview_and_print".to_string(),
+            message: format!(
+                "{} only permits Preview & Print embedding (OS/2 fsType); check the license before using outlines in artwork.{}",
+                font_name, license
+            ),
+        });
+    }
+
+    if fs_type & 0x0200 != 0 {
+        refusals.push(ExportWarning {
+            code: "bitmap_embedding_only".to_string(),
+            message: format!(
+                "{} only permits bitmap embedding (OS/2 fsType); outline data may not be embedded.{}",
+                font_name, license
+            ),
+        });
+    }
+
+    finish_check(refusals, warnings, allow_restricted)
+}
+
+/// サブセットフォントとして埋め込めるか確認する
+/// アウトライン書き出しの条件に加え、サブセット化禁止（fsType 0x0100）のフォントも拒否する
+pub fn check_subset_export(face: &Face, allow_restricted: bool) -> Result<Vec<ExportWarning>, String> {
+    let mut results = check_outline_export(face, true)?;
+
+    let fs_type = face
+        .raw_face()
+        .table(Tag::from_bytes(b"OS/2"))
+        .and_then(|data| read_u16(data, 8))
+        .unwrap_or(0);
+    if fs_type & 0x0100 != 0 {
+        let font_name = face_name(face, name_id::FULL_NAME).unwrap_or_else(|| "This font".to_string());
+        results.push(ExportWarning {
+            code: "no_subsetting".to_string(),
+            message: format!(
+                "{} does not permit subsetting (OS/2 fsType); only the full font may be embedded.",
+                font_name
+            ),
+        });
+    }
+
+    let (refusals, warnings) = results
+        .into_iter()
+        .partition(|w| w.code != "preview_and_print");
+    finish_check(refusals, warnings, allow_restricted)
+}
+
+fn finish_check(
+    mut refusals: Vec<ExportWarning>,
+    warnings: Vec<ExportWarning>,
+    allow_restricted: bool,
+) -> Result<Vec<ExportWarning>, String> {
+    if !refusals.is_empty() && !allow_restricted {
+        let messages: Vec<String> = refusals.into_iter().map(|w| w.message).collect();
+        return Err(format!(
+            "{} Set allow_restricted_license to export anyway.",
+            messages.join(" ")
+        ));
+    }
+
+    // 明示的に許可された場合も警告として結果に残す
+    refusals.extend(warnings);
+    Ok(refusals)
+}
@@ -0,0 +1,693 @@
+//! 配置したアウトラインを押し出した立体（STL・OBJ・glTF）
+//! 座標は左下を原点、Y軸を上向き、Z軸を押し出し方向とし、単位はmm（glTFのみm）
+
+use std::collections::HashMap;
+
+use spade::handles::FixedVertexHandle;
+use spade::{ConstrainedDelaunayTriangulation, Point2, Triangulation};
+
+use crate::offset::LineJoin;
+use crate::outline::{flatten, format_number, TextLayout};
+
+type Point = (f64, f64);
+
+/// 出力形式
+pub enum MeshFormat {
+    /// バイナリSTL
+    Stl,
+    Obj,
+    /// バイナリglTF（.glb）
+    Glb,
+}
+
+/// 押し出しの設定（単位はmm）
+pub struct Extrusion {
+    pub depth: f64,
+    /// 上面の縁を45°に面取りする幅（0なら面取りしない）
+    /// 線の太さの半分を超えて上面の輪郭が潰れる場合はエラーにする
+    pub bevel: f64,
+}
+
+/// 頂点を共有した三角形の集まり（三角形は外から見て反時計回り）
+#[derive(Default)]
+pub struct Mesh {
+    pub positions: Vec<[f64; 3]>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+/// 塗りつぶす輪郭とその中の穴
+struct Polygon {
+    outer: Vec<Point>,
+    holes: Vec<Vec<Point>>,
+}
+
+/// 符号付き面積（Y軸上向きで反時計回りなら正）
+fn signed_area(points: &[Point]) -> f64 {
+    let mut area = 0.0;
+    for (i, &(x0, y0)) in points.iter().enumerate() {
+        let (x1, y1) = points[(i + 1) % points.len()];
+        area += x0 * y1 - x1 * y0;
+    }
+    area / 2.0
+}
+
+fn contains(points: &[Point], (x, y): Point) -> bool {
+    let mut inside = false;
+    for (i, &(x0, y0)) in points.iter().enumerate() {
+        let (x1, y1) = points[(i + 1) % points.len()];
+        if (y0 > y) != (y1 > y) && x < x0 + (y - y0) * (x1 - x0) / (y1 - y0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// 1文字分の輪郭を、塗りつぶす輪郭と穴に分ける
+/// 最も大きな輪郭と同じ向きの輪郭を塗り、逆向きでいずれかの塗りの中にある輪郭を穴とする
+/// 塗りの輪郭は反時計回り、穴は時計回りに揃える（どちらも進行方向の左側が中身になる）
+fn glyph_polygons(contours: Vec<Vec<Point>>) -> Vec<Polygon> {
+    let areas: Vec<f64> = contours.iter().map(|c| signed_area(c)).collect();
+    let Some(fill_sign) = areas
+        .iter()
+        .copied()
+        .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+        .map(f64::signum)
+    else {
+        return Vec::new();
+    };
+
+    let mut polygons: Vec<Polygon> = Vec::new();
+    let mut holes = Vec::new();
+    for (mut contour, area) in contours.into_iter().zip(&areas) {
+        if fill_sign < 0.0 {
+            contour.reverse();
+        }
+        if area.signum() == fill_sign {
+            polygons.push(Polygon { outer: contour, holes: Vec::new() });
+        } else {
+            holes.push(contour);
+        }
+    }
+
+    for hole in holes {
+        // 穴を含む塗りのうち最も小さいものに割り当てる
+        let parent = polygons
+            .iter()
+            .enumerate()
+            .filter(|(_, polygon)| contains(&polygon.outer, hole[0]))
+            .min_by(|(_, a), (_, b)| signed_area(&a.outer).total_cmp(&signed_area(&b.outer)))
+            .map(|(i, _)| i);
+        match parent {
+            Some(i) => polygons[i].holes.push(hole),
+            // どの塗りにも含まれない逆向きの輪郭は、ノンゼロ規則では塗られる
+            None => {
+                let mut outer = hole;
+                outer.reverse();
+                polygons.push(Polygon { outer, holes: Vec::new() });
+            }
+        }
+    }
+    polygons
+}
+
+/// 輪郭を中身の側へamountだけ内側にずらす（頂点ごとに隣の辺から等距離の位置）
+fn inset(points: &[Point], amount: f64) -> Vec<Point> {
+    let n = points.len();
+    let left_normal = |a: Point, b: Point| {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let length = dx.hypot(dy).max(1e-12);
+        (-dy / length, dx / length)
+    };
+    (0..n)
+        .map(|i| {
+            let previous = points[(i + n - 1) % n];
+            let point = points[i];
+            let next = points[(i + 1) % n];
+            let n1 = left_normal(previous, point);
+            let n2 = left_normal(point, next);
+            let (bx, by) = (n1.0 + n2.0, n1.1 + n2.1);
+            let length = bx.hypot(by);
+            if length < 1e-9 {
+                return (point.0 + n1.0 * amount, point.1 + n1.1 * amount);
+            }
+            // 鋭い角で頂点が飛び出しすぎないよう、ずらす距離は幅の4倍までにする
+            let cos = ((bx * n1.0 + by * n1.1) / length).max(0.25);
+            let distance = amount / cos;
+            (point.0 + bx / length * distance, point.1 + by / length * distance)
+        })
+        .collect()
+}
+
+/// 2つの線分が互いの内側で交わるか（端点での接触や重なりは含めない）
+fn segments_cross(a: Point, b: Point, c: Point, d: Point) -> bool {
+    let orient = |p: Point, q: Point, r: Point| (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0);
+    orient(c, d, a) * orient(c, d, b) < 0.0 && orient(a, b, c) * orient(a, b, d) < 0.0
+}
+
+/// 内側にずらした輪郭が潰れていないか（先頭が塗りの輪郭、残りが穴）
+/// 元の輪郭と向きが同じで、どの辺も（別の輪郭の辺とも）交わらず、
+/// 穴が塗りの外に出たり別の穴を囲んだりしていなければよい
+fn inset_is_valid(contours: &[&Vec<Point>], insets: &[Vec<Point>]) -> bool {
+    if contours
+        .iter()
+        .zip(insets)
+        .any(|(contour, inset)| signed_area(contour).signum() != signed_area(inset).signum())
+    {
+        return false;
+    }
+
+    // （輪郭の番号, 辺の番号, 始点, 終点）
+    let edges: Vec<(usize, usize, Point, Point)> = insets
+        .iter()
+        .enumerate()
+        .flat_map(|(ring, points)| {
+            (0..points.len()).map(move |i| (ring, i, points[i], points[(i + 1) % points.len()]))
+        })
+        .collect();
+    for (i, &(ring_a, index_a, a0, a1)) in edges.iter().enumerate() {
+        for &(ring_b, index_b, b0, b1) in &edges[i + 1..] {
+            if ring_a == ring_b {
+                let n = insets[ring_a].len();
+                if index_b == index_a + 1 || (index_a == 0 && index_b == n - 1) {
+                    continue;
+                }
+            }
+            if a0.0.max(a1.0) < b0.0.min(b1.0)
+                || b0.0.max(b1.0) < a0.0.min(a1.0)
+                || a0.1.max(a1.1) < b0.1.min(b1.1)
+                || b0.1.max(b1.1) < a0.1.min(a1.1)
+            {
+                continue;
+            }
+            if segments_cross(a0, a1, b0, b1) {
+                return false;
+            }
+        }
+    }
+
+    // 辺が交わらないので、輪郭の内外は1つの頂点で判定できる
+    if let Some((outer, holes)) = insets.split_first() {
+        for (i, hole) in holes.iter().enumerate() {
+            if !contains(outer, hole[0])
+                || holes.iter().enumerate().any(|(j, other)| i != j && contains(hole, other[0]))
+            {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+impl Mesh {
+    fn add_ring(&mut self, points: &[Point], z: f64) -> Vec<u32> {
+        let start = self.positions.len() as u32;
+        self.positions.extend(points.iter().map(|&(x, y)| [x, y, z]));
+        (start..self.positions.len() as u32).collect()
+    }
+
+    /// 同じ頂点数の下側と上側の輪郭をつなぐ側面
+    fn add_wall(&mut self, lower: &[u32], upper: &[u32]) {
+        let n = lower.len();
+        for i in 0..n {
+            let j = (i + 1) % n;
+            self.triangles.push([lower[i], lower[j], upper[j]]);
+            self.triangles.push([lower[i], upper[j], upper[i]]);
+        }
+    }
+
+    /// 輪郭と穴を制約付きドロネー分割して面にする（upがtrueなら+Z向き）
+    /// 輪郭の頂点をすべて使うため、側面と同じ辺で閉じる
+    /// 輪郭どうしが接する・交わるなどして側面の辺が面の辺にならない場合はエラーにする
+    fn add_cap(&mut self, rings: &[(&[Point], &[u32])], up: bool) -> Result<(), String> {
+        let mut cdt = ConstrainedDelaunayTriangulation::<Point2<f64>>::new();
+        let mut vertex_indices: HashMap<FixedVertexHandle, u32> = HashMap::new();
+        let mut ring_handles = Vec::with_capacity(rings.len());
+        for (points, ring) in rings {
+            let mut handles = Vec::with_capacity(points.len());
+            for (&(x, y), &index) in points.iter().zip(ring.iter()) {
+                let handle = cdt
+                    .insert(Point2::new(x, y))
+                    .map_err(|e| format!("Failed to triangulate outline: {}", e))?;
+                // 同じ位置の頂点をまとめると、接する点の側面の縦の辺を4つの三角形が共有してしまう
+                if vertex_indices.insert(handle, index).is_some() {
+                    return Err(format!("Failed to triangulate outline: contours touch at ({}, {})", x, y));
+                }
+                handles.push(handle);
+            }
+            ring_handles.push(handles);
+        }
+        for handles in &ring_handles {
+            for i in 0..handles.len() {
+                let (from, to) = (handles[i], handles[(i + 1) % handles.len()]);
+                // 交わる辺は追加されず、途中に他の頂点がある辺は分割される
+                if cdt.try_add_constraint(from, to).is_empty() || !cdt.exists_constraint(from, to) {
+                    return Err("Failed to triangulate outline: contour edges intersect".to_string());
+                }
+            }
+        }
+
+        // 凸包の外側から隣の三角形へ渡っていき、輪郭の辺を奇数回越えた三角形を中身とする
+        // （重心が輪郭の内側かで判定すると、ほぼ一直線に並んだ頂点の細い三角形を誤る）
+        let mut inside: Vec<Option<bool>> = vec![None; cdt.num_all_faces()];
+        let mut stack = Vec::new();
+        for edge in cdt.convex_hull() {
+            for side in [edge, edge.rev()] {
+                if let Some(face) = side.face().as_inner() {
+                    if inside[face.fix().index()].is_none() {
+                        inside[face.fix().index()] = Some(edge.is_constraint_edge());
+                        stack.push(face);
+                    }
+                }
+            }
+        }
+        while let Some(face) = stack.pop() {
+            let face_inside = inside[face.fix().index()] == Some(true);
+            for edge in face.adjacent_edges() {
+                if let Some(neighbor) = edge.rev().face().as_inner() {
+                    let neighbor_inside = &mut inside[neighbor.fix().index()];
+                    if neighbor_inside.is_none() {
+                        *neighbor_inside = Some(face_inside != edge.is_constraint_edge());
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        for face in cdt.inner_faces() {
+            if inside[face.fix().index()] != Some(true) {
+                continue;
+            }
+            let [a, b, c] = face
+                .vertices()
+                .map(|v| vertex_indices.get(&v.fix()).copied())
+                .map(|index| index.ok_or("Failed to triangulate outline: unexpected vertex".to_string()));
+            let (a, b, c) = (a?, b?, c?);
+            // 三角形の頂点は反時計回りに並ぶ
+            if up {
+                self.triangles.push([a, b, c]);
+            } else {
+                self.triangles.push([a, c, b]);
+            }
+        }
+        Ok(())
+    }
+
+    /// 1つの輪郭（穴を含む）を押し出した閉じた立体を追加する
+    fn add_extruded(&mut self, polygon: &Polygon, extrusion: &Extrusion) -> Result<(), String> {
+        let contours: Vec<&Vec<Point>> = std::iter::once(&polygon.outer).chain(&polygon.holes).collect();
+        let wall_top = extrusion.depth - extrusion.bevel;
+
+        let top_points: Vec<Vec<Point>> = if extrusion.bevel > 0.0 {
+            let insets: Vec<Vec<Point>> = contours.iter().map(|c| inset(c, extrusion.bevel)).collect();
+            if !inset_is_valid(&contours, &insets) {
+                return Err(format!(
+                    "Bevel is too wide for the outline (must be less than half of the stroke width): {}",
+                    extrusion.bevel
+                ));
+            }
+            insets
+        } else {
+            contours.iter().map(|c| c.to_vec()).collect()
+        };
+
+        let bottom: Vec<Vec<u32>> = contours.iter().map(|c| self.add_ring(c, 0.0)).collect();
+        let upper: Vec<Vec<u32>> = contours.iter().map(|c| self.add_ring(c, wall_top)).collect();
+        for (lower, upper) in bottom.iter().zip(&upper) {
+            self.add_wall(lower, upper);
+        }
+
+        let top: Vec<Vec<u32>> = if extrusion.bevel > 0.0 {
+            let top: Vec<Vec<u32>> = top_points
+                .iter()
+                .map(|c| self.add_ring(c, extrusion.depth))
+                .collect();
+            for (lower, upper) in upper.iter().zip(&top) {
+                self.add_wall(lower, upper);
+            }
+            top
+        } else {
+            upper
+        };
+
+        let bottom_rings: Vec<(&[Point], &[u32])> = contours
+            .iter()
+            .zip(&bottom)
+            .map(|(c, r)| (c.as_slice(), r.as_slice()))
+            .collect();
+        self.add_cap(&bottom_rings, false)?;
+        let top_rings: Vec<(&[Point], &[u32])> = top_points
+            .iter()
+            .zip(&top)
+            .map(|(c, r)| (c.as_slice(), r.as_slice()))
+            .collect();
+        self.add_cap(&top_rings, true)
+    }
+
+    fn normal(&self, [a, b, c]: [u32; 3]) -> [f64; 3] {
+        let [pa, pb, pc] = [a, b, c].map(|i| self.positions[i as usize]);
+        let u = [pb[0] - pa[0], pb[1] - pa[1], pb[2] - pa[2]];
+        let v = [pc[0] - pa[0], pc[1] - pa[1], pc[2] - pa[2]];
+        let n = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        if length == 0.0 { [0.0; 3] } else { n.map(|c| c / length) }
+    }
+}
+
+/// 折れ線の連続する同じ点を除く（始点と終点の重なりも含む）
+fn dedup_points(points: Vec<Point>) -> Vec<Point> {
+    let same = |a: &Point, b: &Point| (a.0 - b.0).hypot(a.1 - b.1) <= 1e-9;
+    let mut result: Vec<Point> = Vec::with_capacity(points.len());
+    for point in points {
+        if !result.last().is_some_and(|last| same(last, &point)) {
+            result.push(point);
+        }
+    }
+    while result.len() > 1 && same(&result[0], &result[result.len() - 1]) {
+        result.pop();
+    }
+    result
+}
+
+/// テキストのアウトラインを許容誤差tolerance_mm以内の折れ線にして押し出す
+/// scaleはpxからmmへの倍率、輪郭は重なりの無いもの（boolean::union_layoutで結合したもの）とする
+pub fn extrude(
+    layout: &TextLayout,
+    scale: f64,
+    tolerance_mm: f64,
+    extrusion: &Extrusion,
+) -> Result<Mesh, String> {
+    let bounds = layout.painted_bounds(&[], LineJoin::Round);
+    let mut mesh = Mesh::default();
+    for glyph in &layout.glyphs {
+        let contours: Vec<Vec<Point>> = flatten(&glyph.segments, tolerance_mm / scale)
+            .into_iter()
+            .map(|polyline| {
+                dedup_points(
+                    polyline
+                        .points
+                        .into_iter()
+                        .map(|(x, y)| ((x - bounds.min_x) * scale, (bounds.max_y - y) * scale))
+                        .collect(),
+                )
+            })
+            .filter(|points| points.len() >= 3 && signed_area(points).abs() > 1e-9)
+            .collect();
+
+        for polygon in glyph_polygons(contours) {
+            mesh.add_extruded(&polygon, extrusion)?;
+        }
+    }
+    Ok(mesh)
+}
+
+/// バイナリSTL（単位はmm）
+pub fn encode_stl(mesh: &Mesh) -> Vec<u8> {
+    let mut out = Vec::with_capacity(84 + mesh.triangles.len() * 50);
+    let mut header = [0u8; 80];
+    header[..10].copy_from_slice(b"Font Scope");
+    out.extend_from_slice(&header);
+    out.extend_from_slice(&(mesh.triangles.len() as u32).to_le_bytes());
+    for &triangle in &mesh.triangles {
+        let normal = mesh.normal(triangle);
+        let vertices = triangle.map(|i| mesh.positions[i as usize]);
+        for value in normal.iter().chain(vertices.iter().flatten()) {
+            out.extend_from_slice(&(*value as f32).to_le_bytes());
+        }
+        out.extend_from_slice(&0u16.to_le_bytes());
+    }
+    out
+}
+
+/// Wavefront OBJ（単位はmm）
+pub fn encode_obj(mesh: &Mesh) -> String {
+    let mut out = String::from("# Font Scope\no text\n");
+    for [x, y, z] in &mesh.positions {
+        out.push_str(&format!("v {} {} {}\n", format_number(*x), format_number(*y), format_number(*z)));
+    }
+    for [a, b, c] in &mesh.triangles {
+        out.push_str(&format!("f {} {} {}\n", a + 1, b + 1, c + 1));
+    }
+    out
+}
+
+/// sRGBの色成分（0〜255）をglTFの線形の値にする
+fn srgb_to_linear(value: u8) -> f64 {
+    let c = value as f64 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// バイナリglTF（単位はm、材質の色はcolor）
+pub fn encode_glb(mesh: &Mesh, color: &str) -> Result<Vec<u8>, String> {
+    if mesh.triangles.is_empty() {
+        return Err("No outlines to export".to_string());
+    }
+    let [r, g, b, a] = crate::color::parse_color(color)?;
+
+    let mut binary = Vec::new();
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for position in &mesh.positions {
+        for axis in 0..3 {
+            let value = (position[axis] / 1000.0) as f32;
+            min[axis] = min[axis].min(value as f64);
+            max[axis] = max[axis].max(value as f64);
+            binary.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    let positions_length = binary.len();
+    for index in mesh.triangles.iter().flatten() {
+        binary.extend_from_slice(&index.to_le_bytes());
+    }
+    let indices_length = binary.len() - positions_length;
+
+    let json = serde_json::json!({
+        "asset": { "version": "2.0", "generator": "Font Scope" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": "text" }],
+        "meshes": [{
+            "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }]
+        }],
+        "materials": [{
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a as f64 / 255.0],
+                "metallicFactor": 0.0,
+                "roughnessFactor": 0.8
+            },
+            "alphaMode": if a == 255 { "OPAQUE" } else { "BLEND" }
+        }],
+        "accessors": [
+            {
+                "bufferView": 0, "componentType": 5126, "count": mesh.positions.len(),
+                "type": "VEC3", "min": min, "max": max
+            },
+            {
+                "bufferView": 1, "componentType": 5125, "count": mesh.triangles.len() * 3,
+                "type": "SCALAR"
+            }
+        ],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": positions_length, "target": 34962 },
+            { "buffer": 0, "byteOffset": positions_length, "byteLength": indices_length, "target": 34963 }
+        ],
+        "buffers": [{ "byteLength": binary.len() }]
+    });
+
+    // チャンクは4バイト境界に揃える（JSONは空白、バイナリは0で埋める）
+    let mut json = json.to_string().into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    binary.resize(binary.len().next_multiple_of(4), 0);
+
+    let mut out = Vec::with_capacity(28 + json.len() + binary.len());
+    out.extend_from_slice(b"glTF");
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&((12 + 8 + json.len() + 8 + binary.len()) as u32).to_le_bytes());
+    out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"JSON");
+    out.extend_from_slice(&json);
+    out.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"BIN\0");
+    out.extend_from_slice(&binary);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outline::{GlyphOutline, Segment};
+
+    fn rectangle(x0: f64, y0: f64, x1: f64, y1: f64) -> Vec<Segment> {
+        vec![
+            Segment::MoveTo(x0, y0),
+            Segment::LineTo(x1, y0),
+            Segment::LineTo(x1, y1),
+            Segment::LineTo(x0, y1),
+            Segment::Close,
+        ]
+    }
+
+    /// 10mm角に4mm角の穴が開いた板を深さ2mmで押し出す
+    fn framed_square(bevel: f64) -> Mesh {
+        let mut segments = rectangle(0.0, 0.0, 10.0, 10.0);
+        segments.extend(rectangle(3.0, 7.0, 7.0, 3.0));
+        let layout = TextLayout {
+            width: 10.0,
+            height: 10.0,
+            glyphs: vec![GlyphOutline {
+                char_index: 0,
+                text: "口".to_string(),
+                font_name: "Test".to_string(),
+                segments,
+            }],
+        };
+        extrude(&layout, 1.0, 0.01, &Extrusion { depth: 2.0, bevel }).unwrap()
+    }
+
+    /// 三角形の向きから求めた体積（外向きなら正）
+    fn volume(positions: &[[f64; 3]], triangles: &[[u32; 3]]) -> f64 {
+        triangles
+            .iter()
+            .map(|triangle| {
+                let [a, b, c] = triangle.map(|i| positions[i as usize]);
+                (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+                    + a[2] * (b[0] * c[1] - b[1] * c[0]))
+                    / 6.0
+            })
+            .sum()
+    }
+
+    /// 中心(cx, cy)、半径rの円（3次ベジェ4本、clockwiseなら逆回り）
+    fn circle(cx: f64, cy: f64, r: f64, clockwise: bool) -> Vec<Segment> {
+        let k = r * 0.552_284_75;
+        let s = if clockwise { -1.0 } else { 1.0 };
+        vec![
+            Segment::MoveTo(cx + r, cy),
+            Segment::CurveTo(cx + r, cy + s * k, cx + k, cy + s * r, cx, cy + s * r),
+            Segment::CurveTo(cx - k, cy + s * r, cx - r, cy + s * k, cx - r, cy),
+            Segment::CurveTo(cx - r, cy - s * k, cx - k, cy - s * r, cx, cy - s * r),
+            Segment::CurveTo(cx + k, cy - s * r, cx + r, cy - s * k, cx + r, cy),
+            Segment::Close,
+        ]
+    }
+
+    /// 各辺がちょうど2つの三角形に、逆向きに1回ずつ使われている
+    fn assert_manifold(mesh: &Mesh) {
+        let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+        for &[a, b, c] in &mesh.triangles {
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                *edges.entry((from, to)).or_default() += 1;
+            }
+        }
+        for (&(from, to), &count) in &edges {
+            assert_eq!(count, 1, "edge {}-{}", from, to);
+            assert_eq!(edges.get(&(to, from)), Some(&1), "edge {}-{}", from, to);
+        }
+    }
+
+    #[test]
+    fn extruded_mesh_is_closed() {
+        for bevel in [0.0, 0.5] {
+            let mesh = framed_square(bevel);
+            assert_manifold(&mesh);
+            assert!(volume(&mesh.positions, &mesh.triangles) > 0.0);
+        }
+        // (10×10 − 4×4) × 2
+        let mesh = framed_square(0.0);
+        assert!((volume(&mesh.positions, &mesh.triangles) - 168.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn curved_and_overlapping_outlines_are_manifold() {
+        // 「O」（曲線の輪郭と穴）と、重なった2つの長方形を結合した十字
+        let mut o = circle(5.0, 5.0, 5.0, false);
+        o.extend(circle(5.0, 5.0, 3.0, true));
+        let mut cross = rectangle(12.0, 3.0, 22.0, 7.0);
+        cross.extend(rectangle(15.0, 0.0, 19.0, 10.0));
+        let glyph = |char_index: usize, text: &str, segments: Vec<Segment>| GlyphOutline {
+            char_index,
+            text: text.to_string(),
+            font_name: "Test".to_string(),
+            segments,
+        };
+        let layout = TextLayout {
+            width: 22.0,
+            height: 10.0,
+            glyphs: vec![glyph(0, "O", o), glyph(1, "+", cross)],
+        };
+        let layout = crate::boolean::union_layout(&layout, 0.01);
+
+        for bevel in [0.0, 0.3] {
+            let mesh = extrude(&layout, 1.0, 0.01, &Extrusion { depth: 2.0, bevel }).unwrap();
+            assert_manifold(&mesh);
+            assert!(volume(&mesh.positions, &mesh.triangles) > 0.0);
+        }
+        // (π × (5² − 3²) + 10 × 4 × 2 − 4 × 4) × 2（曲線を折れ線にした分だけ小さい）
+        let mesh = extrude(&layout, 1.0, 0.01, &Extrusion { depth: 2.0, bevel: 0.0 }).unwrap();
+        let expected = (std::f64::consts::PI * 16.0 + 64.0) * 2.0;
+        assert!((volume(&mesh.positions, &mesh.triangles) - expected).abs() < 1.0);
+    }
+
+    #[test]
+    fn stl_round_trips_triangles() {
+        let mesh = framed_square(0.0);
+        let stl = encode_stl(&mesh);
+        let count = u32::from_le_bytes(stl[80..84].try_into().unwrap()) as usize;
+        assert_eq!(count, mesh.triangles.len());
+        assert_eq!(stl.len(), 84 + count * 50);
+
+        let read_f32 = |offset: usize| f32::from_le_bytes(stl[offset..offset + 4].try_into().unwrap()) as f64;
+        for (i, triangle) in mesh.triangles.iter().enumerate() {
+            let record = 84 + i * 50;
+            for (j, &vertex) in triangle.iter().enumerate() {
+                for axis in 0..3 {
+                    let value = read_f32(record + 12 + j * 12 + axis * 4);
+                    assert!((value - mesh.positions[vertex as usize][axis]).abs() < 1e-5);
+                }
+            }
+            // 法線は単位ベクトル
+            let normal = [0, 1, 2].map(|axis| read_f32(record + axis * 4));
+            assert!((normal.iter().map(|n| n * n).sum::<f64>() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn glb_round_trips_positions_and_indices() {
+        let mesh = framed_square(0.0);
+        let glb = encode_glb(&mesh, "#336699").unwrap();
+        let read_u32 = |offset: usize| u32::from_le_bytes(glb[offset..offset + 4].try_into().unwrap()) as usize;
+
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(read_u32(8), glb.len());
+        let json_length = read_u32(12);
+        assert_eq!(&glb[16..20], b"JSON");
+        let json: serde_json::Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+        let binary_start = 20 + json_length + 8;
+        assert_eq!(&glb[binary_start - 4..binary_start], b"BIN\0");
+        let binary = &glb[binary_start..binary_start + read_u32(binary_start - 8)];
+
+        let view = |accessor: usize| {
+            let view = &json["bufferViews"][json["accessors"][accessor]["bufferView"].as_u64().unwrap() as usize];
+            let offset = view["byteOffset"].as_u64().unwrap() as usize;
+            &binary[offset..offset + view["byteLength"].as_u64().unwrap() as usize]
+        };
+        let positions: Vec<f64> = view(0)
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64 * 1000.0)
+            .collect();
+        let indices: Vec<u32> = view(1)
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+
+        assert_eq!(json["accessors"][0]["count"].as_u64(), Some(mesh.positions.len() as u64));
+        for (decoded, original) in positions.iter().zip(mesh.positions.iter().flatten()) {
+            assert!((decoded - original).abs() < 1e-3);
+        }
+        assert_eq!(indices, mesh.triangles.iter().flatten().copied().collect::<Vec<_>>());
+        assert_eq!(json["materials"][0]["alphaMode"], "OPAQUE");
+    }
+}
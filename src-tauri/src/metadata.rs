@@ -0,0 +1,264 @@
+//! name/OS/2/headテーブルからフォントの詳細情報（名前・ライセンス・埋め込み許可など）を取得
+
+use ttf_parser::{name_id, Face, PlatformId, Tag};
+
+use crate::face_name;
+use crate::sfnt::{read_fixed, read_i64, read_tag, read_u16, read_u32};
+
+#[derive(serde::Serialize)]
+pub struct NameRecordInfo {
+    pub name_id: u16,
+    /// 名前の種類（"family", "license"など、未定義のIDは"other"）
+    pub kind: String,
+    /// "unicode" | "macintosh" | "windows" | "other"
+    pub platform: String,
+    pub language_id: u16,
+    /// 言語名（例: "Japanese_Japan"、Windows以外は"Unknown"の場合あり）
+    pub language: String,
+    pub value: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct Os2Info {
+    pub version: u16,
+    /// 100〜900
+    pub weight_class: u16,
+    /// 1〜9（5 = Normal）
+    pub width_class: u16,
+    /// fsTypeの生の値
+    pub fs_type: u16,
+    /// "installable" | "restricted" | "preview_and_print" | "editable"
+    pub embedding: String,
+    pub no_subsetting: bool,
+    pub bitmap_embedding_only: bool,
+    pub panose: [u8; 10],
+    /// ulUnicodeRange1〜4
+    pub unicode_ranges: [u32; 4],
+    /// ulCodePageRange1〜2（version 0では0）
+    pub code_page_ranges: [u32; 2],
+    /// ulCodePageRangeのビットに対応するコードページ番号（例: 932 = 日本語）
+    pub code_pages: Vec<u16>,
+    pub vendor_id: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct HeadInfo {
+    pub units_per_em: u16,
+    pub font_revision: f32,
+    /// ISO 8601形式（UTC）
+    pub created: Option<String>,
+    pub modified: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct FontMetadata {
+    // 主要な名前（英語名を優先）
+    pub family_name: Option<String>,
+    pub subfamily_name: Option<String>,
+    pub full_name: Option<String>,
+    pub postscript_name: Option<String>,
+    pub version: Option<String>,
+    pub designer: Option<String>,
+    pub manufacturer: Option<String>,
+    pub copyright: Option<String>,
+    pub license: Option<String>,
+    pub license_url: Option<String>,
+    /// nameテーブルの全レコード（全言語）
+    pub names: Vec<NameRecordInfo>,
+    pub os2: Option<Os2Info>,
+    pub head: HeadInfo,
+    pub number_of_glyphs: u16,
+    pub is_variable: bool,
+}
+
+pub fn collect_font_metadata(face: &Face) -> FontMetadata {
+    FontMetadata {
+        family_name: face_name(face, name_id::TYPOGRAPHIC_FAMILY)
+            .or_else(|| face_name(face, name_id::FAMILY)),
+        subfamily_name: face_name(face, name_id::TYPOGRAPHIC_SUBFAMILY)
+            .or_else(|| face_name(face, name_id::SUBFAMILY)),
+        full_name: face_name(face, name_id::FULL_NAME),
+        postscript_name: face_name(face, name_id::POST_SCRIPT_NAME),
+        version: face_name(face, name_id::VERSION),
+        designer: face_name(face, name_id::DESIGNER),
+        manufacturer: face_name(face, name_id::MANUFACTURER),
+        copyright: face_name(face, name_id::COPYRIGHT_NOTICE),
+        license: face_name(face, name_id::LICENSE),
+        license_url: face_name(face, name_id::LICENSE_URL),
+        names: name_records(face),
+        os2: os2_info(face),
+        head: head_info(face),
+        number_of_glyphs: face.number_of_glyphs(),
+        is_variable: face.is_variable(),
+    }
+}
+
+fn name_kind(id: u16) -> &'static str {
+    match id {
+        name_id::COPYRIGHT_NOTICE => "copyright",
+        name_id::FAMILY => "family",
+        name_id::SUBFAMILY => "subfamily",
+        name_id::UNIQUE_ID => "unique_id",
+        name_id::FULL_NAME => "full_name",
+        name_id::VERSION => "version",
+        name_id::POST_SCRIPT_NAME => "postscript_name",
+        name_id::TRADEMARK => "trademark",
+        name_id::MANUFACTURER => "manufacturer",
+        name_id::DESIGNER => "designer",
+        name_id::DESCRIPTION => "description",
+        name_id::VENDOR_URL => "vendor_url",
+        name_id::DESIGNER_URL => "designer_url",
+        name_id::LICENSE => "license",
+        name_id::LICENSE_URL => "license_url",
+        name_id::TYPOGRAPHIC_FAMILY => "typographic_family",
+        name_id::TYPOGRAPHIC_SUBFAMILY => "typographic_subfamily",
+        name_id::COMPATIBLE_FULL => "compatible_full",
+        name_id::SAMPLE_TEXT => "sample_text",
+        name_id::WWS_FAMILY => "wws_family",
+        name_id::WWS_SUBFAMILY => "wws_subfamily",
+        _ => "other",
+    }
+}
+
+fn name_records(face: &Face) -> Vec<NameRecordInfo> {
+    face.names()
+        .into_iter()
+        .filter_map(|name| {
+            let value = name.to_string().or_else(|| {
+                // Mac RomanはASCIIのみの場合に限りそのまま読む
+                let is_mac_roman = name.platform_id == PlatformId::Macintosh && name.encoding_id == 0;
+                (is_mac_roman && name.name.is_ascii())
+                    .then(|| String::from_utf8_lossy(name.name).to_string())
+            })?;
+
+            let platform = match name.platform_id {
+                PlatformId::Unicode => "unicode",
+                PlatformId::Macintosh => "macintosh",
+                PlatformId::Windows => "windows",
+                _ => "other",
+            };
+
+            Some(NameRecordInfo {
+                name_id: name.name_id,
+                kind: name_kind(name.name_id).to_string(),
+                platform: platform.to_string(),
+                language_id: name.language_id,
+                language: format!("{:?}", name.language()),
+                value,
+            })
+        })
+        .collect()
+}
+
+/// fsTypeの埋め込み許可レベル
+/// 複数のビットが立っている場合は最も制限の緩いものを採用（OpenType仕様）
+pub fn embedding_level(fs_type: u16) -> &'static str {
+    if fs_type & 0x0008 != 0 {
+        "editable"
+    } else if fs_type & 0x0004 != 0 {
+        "preview_and_print"
+    } else if fs_type & 0x0002 != 0 {
+        "restricted"
+    } else {
+        "installable"
+    }
+}
+
+/// ulCodePageRangeのビット番号とコードページ番号の対応
+/// （29 = Macintosh Roman、30 = OEM、31 = Symbolはコードページ番号が無いため除外）
+const CODE_PAGE_BITS: [(u32, u16); 31] = [
+    (0, 1252), (1, 1250), (2, 1251), (3, 1253), (4, 1254), (5, 1255), (6, 1256),
+    (7, 1257), (8, 1258), (16, 874), (17, 932), (18, 936), (19, 949), (20, 950),
+    (21, 1361), (48, 869), (49, 866), (50, 865), (51, 864), (52, 863), (53, 862),
+    (54, 861), (55, 860), (56, 857), (57, 855), (58, 852), (59, 775), (60, 737),
+    (61, 708), (62, 850), (63, 437),
+];
+
+fn os2_info(face: &Face) -> Option<Os2Info> {
+    let data = face.raw_face().table(Tag::from_bytes(b"OS/2"))?;
+
+    let version = read_u16(data, 0)?;
+    let fs_type = read_u16(data, 8)?;
+
+    let mut panose = [0u8; 10];
+    panose.copy_from_slice(data.get(32..42)?);
+
+    let unicode_ranges = [
+        read_u32(data, 42)?,
+        read_u32(data, 46)?,
+        read_u32(data, 50)?,
+        read_u32(data, 54)?,
+    ];
+
+    let code_page_ranges = if version >= 1 {
+        [read_u32(data, 78).unwrap_or(0), read_u32(data, 82).unwrap_or(0)]
+    } else {
+        [0, 0]
+    };
+    let code_page_bits = (code_page_ranges[1] as u64) << 32 | code_page_ranges[0] as u64;
+    let code_pages = CODE_PAGE_BITS
+        .iter()
+        .filter(|(bit, _)| code_page_bits & (1 << bit) != 0)
+        .map(|(_, code_page)| *code_page)
+        .collect();
+
+    Some(Os2Info {
+        version,
+        weight_class: read_u16(data, 4)?,
+        width_class: read_u16(data, 6)?,
+        fs_type,
+        embedding: embedding_level(fs_type).to_string(),
+        no_subsetting: fs_type & 0x0100 != 0,
+        bitmap_embedding_only: fs_type & 0x0200 != 0,
+        panose,
+        unicode_ranges,
+        code_page_ranges,
+        code_pages,
+        vendor_id: read_tag(data, 58)?.to_string().trim_end().to_string(),
+    })
+}
+
+fn head_info(face: &Face) -> HeadInfo {
+    let data = face.raw_face().table(Tag::from_bytes(b"head")).unwrap_or_default();
+
+    HeadInfo {
+        units_per_em: face.units_per_em(),
+        font_revision: read_fixed(data, 4).unwrap_or(0.0),
+        created: read_i64(data, 20).and_then(format_long_date_time),
+        modified: read_i64(data, 28).and_then(format_long_date_time),
+    }
+}
+
+/// LONGDATETIME（1904-01-01 00:00 UTCからの秒数）をISO 8601形式に変換
+fn format_long_date_time(seconds: i64) -> Option<String> {
+    // 1904-01-01から1970-01-01までの秒数
+    const MAC_EPOCH_OFFSET: i64 = 2_082_844_800;
+    if seconds <= 0 {
+        return None;
+    }
+
+    let unix = seconds - MAC_EPOCH_OFFSET;
+    let days = unix.div_euclid(86_400);
+    let secs = unix.rem_euclid(86_400);
+
+    // 1970-01-01からの日数を年月日に変換（Howard Hinnantのcivil_from_days）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    ))
+}
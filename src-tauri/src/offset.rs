@@ -0,0 +1,97 @@
+//! ストロークを幾何的に計算し、塗りのアウトラインにする
+//! SVGのstroke属性を無視・拡大縮小するツールでも同じ見た目にするため
+
+use tiny_skia::{LineCap, PathSegment, Stroke};
+
+use crate::outline::Segment;
+
+/// 曲線を近似する細かさ（1pxの1/4程度の誤差にする）
+const RESOLUTION_SCALE: f32 = 4.0;
+
+/// マイター結合の上限（SVGのstroke-miterlimitの初期値と同じ）
+pub const MITER_LIMIT: f32 = 4.0;
+
+/// 角の結合方法
+#[derive(Clone, Copy)]
+pub enum LineJoin {
+    Round,
+    Miter,
+    Bevel,
+}
+
+impl LineJoin {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "round" => Ok(Self::Round),
+            "miter" => Ok(Self::Miter),
+            "bevel" => Ok(Self::Bevel),
+            other => Err(format!("Unknown stroke join: {:?}", other)),
+        }
+    }
+
+    /// SVGのstroke-linejoinの値
+    pub fn svg_name(self) -> &'static str {
+        match self {
+            Self::Round => "round",
+            Self::Miter => "miter",
+            Self::Bevel => "bevel",
+        }
+    }
+
+    /// PDFのj演算子・PostScriptのsetlinejoinの値
+    pub fn postscript_code(self) -> u8 {
+        match self {
+            Self::Miter => 0,
+            Self::Round => 1,
+            Self::Bevel => 2,
+        }
+    }
+
+    pub fn to_skia(self) -> tiny_skia::LineJoin {
+        match self {
+            Self::Round => tiny_skia::LineJoin::Round,
+            Self::Miter => tiny_skia::LineJoin::Miter,
+            Self::Bevel => tiny_skia::LineJoin::Bevel,
+        }
+    }
+
+    /// 太さwidth * 2のストロークが中心線からはみ出す最大の距離
+    /// マイターの角は鋭いほど長く伸び、MITER_LIMITで打ち切られる
+    pub fn extent(self, width: f64) -> f64 {
+        match self {
+            Self::Miter => width * MITER_LIMIT as f64,
+            Self::Round | Self::Bevel => width,
+        }
+    }
+}
+
+/// アウトラインを太さwidth * 2で描いたストロークの範囲
+/// 結果はノンゼロ規則で塗るアウトライン（元のアウトラインの内側は含まない）
+pub fn stroke_outline(segments: &[Segment], width: f64, join: LineJoin) -> Vec<Segment> {
+    let Some(path) = crate::raster::skia_path(segments) else {
+        return Vec::new();
+    };
+    let stroke = Stroke {
+        width: (width * 2.0) as f32,
+        miter_limit: MITER_LIMIT,
+        line_cap: LineCap::Round,
+        line_join: join.to_skia(),
+        ..Stroke::default()
+    };
+    let Some(outline) = path.stroke(&stroke, RESOLUTION_SCALE) else {
+        return Vec::new();
+    };
+
+    outline
+        .segments()
+        .map(|segment| match segment {
+            PathSegment::MoveTo(p) => Segment::MoveTo(p.x as f64, p.y as f64),
+            PathSegment::LineTo(p) => Segment::LineTo(p.x as f64, p.y as f64),
+            PathSegment::QuadTo(p1, p) => Segment::QuadTo(p1.x as f64, p1.y as f64, p.x as f64, p.y as f64),
+            PathSegment::CubicTo(p1, p2, p) => Segment::CurveTo(
+                p1.x as f64, p1.y as f64, p2.x as f64, p2.y as f64, p.x as f64, p.y as f64,
+            ),
+            PathSegment::Close => Segment::Close,
+        })
+        .collect()
+}
@@ -0,0 +1,277 @@
+//! 書き出し用に配置したグリフのアウトライン
+//! 座標はSVGと同じ（左上が原点、Y軸は下向き、単位はpx）
+
+use crate::offset::LineJoin;
+use crate::StrokeLayer;
+
+/// SVGのpx（1/96インチ）からPDF・PostScriptのpt（1/72インチ）への倍率
+pub const PX_TO_PT: f64 = 0.75;
+
+/// パスの構成要素
+#[derive(Clone, Copy)]
+pub enum Segment {
+    MoveTo(f64, f64),
+    LineTo(f64, f64),
+    QuadTo(f64, f64, f64, f64),
+    CurveTo(f64, f64, f64, f64, f64, f64),
+    Close,
+}
+
+/// 1文字（クラスタ）分のアウトライン
+pub struct GlyphOutline {
+    /// 改行を除いたテキスト全体での文字インデックス
+    pub char_index: usize,
+    /// クラスタに含まれる文字列
+    pub text: String,
+    /// 描画に使ったフォントの名前（PostScript名、無ければ指定されたフォント名）
+    pub font_name: String,
+    pub segments: Vec<Segment>,
+}
+
+/// テキスト全体の配置結果
+pub struct TextLayout {
+    pub width: f64,
+    pub height: f64,
+    pub glyphs: Vec<GlyphOutline>,
+}
+
+/// SVGのd属性の文字列にする
+pub fn svg_path_data(segments: &[Segment]) -> String {
+    let mut path_data = String::new();
+    for segment in segments {
+        match *segment {
+            Segment::MoveTo(x, y) => path_data.push_str(&format!("M{:.2} {:.2}", x, y)),
+            Segment::LineTo(x, y) => path_data.push_str(&format!("L{:.2} {:.2}", x, y)),
+            Segment::QuadTo(x1, y1, x, y) => {
+                path_data.push_str(&format!("Q{:.2} {:.2} {:.2} {:.2}", x1, y1, x, y))
+            }
+            Segment::CurveTo(x1, y1, x2, y2, x, y) => path_data.push_str(&format!(
+                "C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2}",
+                x1, y1, x2, y2, x, y
+            )),
+            Segment::Close => path_data.push('Z'),
+        }
+    }
+    path_data
+}
+
+/// 範囲（SVG座標系）
+#[derive(Clone, Copy)]
+pub struct Bounds {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Bounds {
+    fn point(x: f64, y: f64) -> Self {
+        Self { min_x: x, min_y: y, max_x: x, max_y: y }
+    }
+
+    fn include(&mut self, x: f64, y: f64) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
+    }
+
+    /// 四方に広げる（ストロークの太さの分など）
+    pub fn expand(&self, amount: f64) -> Self {
+        Self {
+            min_x: self.min_x - amount,
+            min_y: self.min_y - amount,
+            max_x: self.max_x + amount,
+            max_y: self.max_y + amount,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+}
+
+/// 2次ベジェの制御点を3次ベジェの2つの制御点に変換する
+pub fn quad_to_cubic(start: (f64, f64), control: (f64, f64), end: (f64, f64)) -> ((f64, f64), (f64, f64)) {
+    (
+        (start.0 + (control.0 - start.0) * 2.0 / 3.0, start.1 + (control.1 - start.1) * 2.0 / 3.0),
+        (end.0 + (control.0 - end.0) * 2.0 / 3.0, end.1 + (control.1 - end.1) * 2.0 / 3.0),
+    )
+}
+
+/// 3次ベジェの1軸で、導関数が0になる0<t<1の位置
+fn cubic_extrema(p0: f64, p1: f64, p2: f64, p3: f64) -> Vec<f64> {
+    // B'(t)/3 = a t^2 + b t + c
+    let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+    let b = 2.0 * (p0 - 2.0 * p1 + p2);
+    let c = p1 - p0;
+
+    let roots = if a.abs() < 1e-12 {
+        if b.abs() < 1e-12 { Vec::new() } else { vec![-c / b] }
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            Vec::new()
+        } else {
+            let sqrt = discriminant.sqrt();
+            vec![(-b + sqrt) / (2.0 * a), (-b - sqrt) / (2.0 * a)]
+        }
+    };
+    roots.into_iter().filter(|t| *t > 0.0 && *t < 1.0).collect()
+}
+
+fn cubic_at(p0: f64, p1: f64, p2: f64, p3: f64, t: f64) -> f64 {
+    let mt = 1.0 - t;
+    mt * mt * mt * p0 + 3.0 * mt * mt * t * p1 + 3.0 * mt * t * t * p2 + t * t * t * p3
+}
+
+/// アウトラインの範囲（曲線は制御点ではなく実際の膨らみまで含める）
+pub fn segments_bounds(segments: &[Segment]) -> Option<Bounds> {
+    let mut bounds: Option<Bounds> = None;
+    let mut include = |x: f64, y: f64| match bounds.as_mut() {
+        Some(b) => b.include(x, y),
+        None => bounds = Some(Bounds::point(x, y)),
+    };
+
+    let mut current = (0.0, 0.0);
+    for segment in segments {
+        match *segment {
+            Segment::MoveTo(x, y) | Segment::LineTo(x, y) => {
+                include(x, y);
+                current = (x, y);
+            }
+            Segment::QuadTo(..) | Segment::CurveTo(..) => {
+                let ((x1, y1), (x2, y2), (x, y)) = match *segment {
+                    Segment::QuadTo(x1, y1, x, y) => {
+                        let (c1, c2) = quad_to_cubic(current, (x1, y1), (x, y));
+                        (c1, c2, (x, y))
+                    }
+                    Segment::CurveTo(x1, y1, x2, y2, x, y) => ((x1, y1), (x2, y2), (x, y)),
+                    _ => unreachable!(),
+                };
+                for t in cubic_extrema(current.0, x1, x2, x)
+                    .into_iter()
+                    .chain(cubic_extrema(current.1, y1, y2, y))
+                {
+                    include(
+                        cubic_at(current.0, x1, x2, x, t),
+                        cubic_at(current.1, y1, y2, y, t),
+                    );
+                }
+                include(x, y);
+                current = (x, y);
+            }
+            Segment::Close => {}
+        }
+    }
+    bounds
+}
+
+impl TextLayout {
+    /// 全文字のアウトラインの範囲（アウトラインが無ければNone）
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.glyphs
+            .iter()
+            .filter_map(|glyph| segments_bounds(&glyph.segments))
+            .reduce(|mut acc, b| {
+                acc.include(b.min_x, b.min_y);
+                acc.include(b.max_x, b.max_y);
+                acc
+            })
+    }
+
+    /// ストロークも含めて描画される範囲（アウトラインが無ければレイアウト全体）
+    pub fn painted_bounds(&self, stroke_layers: &[&StrokeLayer], stroke_join: LineJoin) -> Bounds {
+        let stroke_extent = stroke_layers
+            .iter()
+            .map(|l| stroke_join.extent(l.width))
+            .fold(0.0, f64::max);
+        self.bounds()
+            .map(|b| b.expand(stroke_extent))
+            .unwrap_or(Bounds {
+                min_x: 0.0,
+                min_y: 0.0,
+                max_x: self.width,
+                max_y: self.height,
+            })
+    }
+}
+
+/// 2次ベジェを3次ベジェに変換したアウトライン（PDF・EPSなど2次ベジェの無い形式用）
+pub fn to_cubics(segments: &[Segment]) -> Vec<Segment> {
+    let mut current = (0.0, 0.0);
+    segments
+        .iter()
+        .map(|segment| match *segment {
+            Segment::MoveTo(x, y) | Segment::LineTo(x, y) | Segment::CurveTo(.., x, y) => {
+                current = (x, y);
+                *segment
+            }
+            Segment::QuadTo(x1, y1, x, y) => {
+                let (c1, c2) = quad_to_cubic(current, (x1, y1), (x, y));
+                current = (x, y);
+                Segment::CurveTo(c1.0, c1.1, c2.0, c2.1, x, y)
+            }
+            Segment::Close => *segment,
+        })
+        .collect()
+}
+
+/// 小数点以下3桁までの数値（末尾の0は省く）
+pub fn format_number(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+/// 曲線を折れ線に分割した輪郭（閉じた輪郭は始点を末尾に繰り返さない）
+pub struct Polyline {
+    pub points: Vec<(f64, f64)>,
+    pub closed: bool,
+}
+
+/// 曲線を許容誤差tolerance以内の折れ線にする（Wangの式で分割数を決める）
+pub fn flatten(segments: &[Segment], tolerance: f64) -> Vec<Polyline> {
+    let tolerance = tolerance.max(1e-6);
+    let mut polylines = Vec::new();
+    let mut points: Vec<(f64, f64)> = Vec::new();
+    let mut finish = |points: &mut Vec<(f64, f64)>, closed: bool| {
+        if closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() > 1 {
+            polylines.push(Polyline { points: std::mem::take(points), closed });
+        }
+        points.clear();
+    };
+
+    for segment in to_cubics(segments) {
+        match segment {
+            Segment::MoveTo(x, y) => {
+                finish(&mut points, false);
+                points.push((x, y));
+            }
+            Segment::LineTo(x, y) => points.push((x, y)),
+            Segment::CurveTo(x1, y1, x2, y2, x, y) => {
+                let (x0, y0) = points.last().copied().unwrap_or((0.0, 0.0));
+                // 2階差分の最大値から、誤差がtolerance以下になる分割数を求める
+                let dd = ((x0 - 2.0 * x1 + x2).hypot(y0 - 2.0 * y1 + y2))
+                    .max((x1 - 2.0 * x2 + x).hypot(y1 - 2.0 * y2 + y));
+                let steps = ((0.75 * dd / tolerance).sqrt().ceil() as usize).clamp(1, 1000);
+                for i in 1..=steps {
+                    let t = i as f64 / steps as f64;
+                    points.push((cubic_at(x0, x1, x2, x, t), cubic_at(y0, y1, y2, y, t)));
+                }
+            }
+            Segment::QuadTo(..) => unreachable!(),
+            Segment::Close => finish(&mut points, true),
+        }
+    }
+    finish(&mut points, false);
+
+    polylines
+}
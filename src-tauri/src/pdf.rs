@@ -0,0 +1,161 @@
+//! 配置したアウトラインをベクターパスのPDF（1ページ）にする
+
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::color::color_components;
+use crate::offset::{LineJoin, MITER_LIMIT};
+use crate::outline::{format_number, to_cubics, Bounds, Segment, TextLayout, PX_TO_PT};
+use crate::StrokeLayer;
+
+/// 塗り・線の色を設定する演算子（"rg"/"RG"またはCMYKの"k"/"K"）
+fn color_operator(color: &str, cmyk: bool, stroke: bool) -> Result<String, String> {
+    let components: Vec<String> = color_components(color, cmyk)?
+        .into_iter()
+        .map(format_number)
+        .collect();
+    let operator = match (cmyk, stroke) {
+        (false, false) => "rg",
+        (false, true) => "RG",
+        (true, false) => "k",
+        (true, true) => "K",
+    };
+    Ok(format!("{} {}", components.join(" "), operator))
+}
+
+/// ページ座標（左下が原点、単位はpt）のパス構築演算子
+fn path_operators(segments: &[Segment], page: &Bounds) -> String {
+    let x = |x: f64| format_number((x - page.min_x) * PX_TO_PT);
+    let y = |y: f64| format_number((page.max_y - y) * PX_TO_PT);
+
+    // PDFには2次ベジェが無いため3次に変換する
+    let mut ops = String::new();
+    for segment in to_cubics(segments) {
+        match segment {
+            Segment::MoveTo(px, py) => ops.push_str(&format!("{} {} m\n", x(px), y(py))),
+            Segment::LineTo(px, py) => ops.push_str(&format!("{} {} l\n", x(px), y(py))),
+            Segment::CurveTo(x1, y1, x2, y2, px, py) => ops.push_str(&format!(
+                "{} {} {} {} {} {} c\n",
+                x(x1), y(y1), x(x2), y(y2), x(px), y(py)
+            )),
+            Segment::QuadTo(..) => unreachable!(),
+            Segment::Close => ops.push_str("h\n"),
+        }
+    }
+    ops
+}
+
+/// ページの内容（ストロークは外側のレイヤーから全文字分を描き、最後に塗りを描く）
+fn content_stream(
+    layout: &TextLayout,
+    page: &Bounds,
+    text_color: &str,
+    stroke_layers: &[&StrokeLayer],
+    stroke_join: LineJoin,
+    cmyk: bool,
+) -> Result<String, String> {
+    let paths: Vec<String> = layout
+        .glyphs
+        .iter()
+        .map(|glyph| path_operators(&glyph.segments, page))
+        .collect();
+
+    let mut content = String::new();
+    for layer in stroke_layers {
+        // SVGと同じく、ストロークの内側も同じ色で塗る
+        content.push_str("q\n");
+        content.push_str(&format!("{}\n", color_operator(&layer.color, cmyk, false)?));
+        content.push_str(&format!("{}\n", color_operator(&layer.color, cmyk, true)?));
+        content.push_str(&format!(
+            "{} w 1 J {} j {} M\n",
+            format_number(layer.width * 2.0 * PX_TO_PT),
+            stroke_join.postscript_code(),
+            format_number(MITER_LIMIT as f64)
+        ));
+        for path in &paths {
+            content.push_str(path);
+            content.push_str("B\n");
+        }
+        content.push_str("Q\n");
+    }
+
+    content.push_str("q\n");
+    content.push_str(&format!("{}\n", color_operator(text_color, cmyk, false)?));
+    for path in &paths {
+        content.push_str(path);
+        content.push_str("f\n");
+    }
+    content.push_str("Q\n");
+
+    Ok(content)
+}
+
+/// アウトラインとストロークが収まる大きさの1ページのPDFを作る
+/// cmykがtrueなら色をDeviceCMYKに変換する（色の不透明度は反映しない）
+pub fn render_pdf(
+    layout: &TextLayout,
+    text_color: &str,
+    stroke_layers: &[&StrokeLayer],
+    stroke_join: LineJoin,
+    cmyk: bool,
+) -> Result<Vec<u8>, String> {
+    let page = layout.painted_bounds(stroke_layers, stroke_join);
+
+    let content = content_stream(layout, &page, text_color, stroke_layers, stroke_join, cmyk)?;
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(content.as_bytes())
+        .map_err(|e| format!("Failed to write PDF: {}", e))?;
+    let compressed = encoder
+        .finish()
+        .map_err(|e| format!("Failed to write PDF: {}", e))?;
+
+    let mut stream_object = format!(
+        "<< /Length {} /Filter /FlateDecode >>\nstream\n",
+        compressed.len()
+    )
+    .into_bytes();
+    stream_object.extend_from_slice(&compressed);
+    stream_object.extend_from_slice(b"\nendstream");
+
+    let objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << >> /Contents 4 0 R >>",
+            format_number(page.width() * PX_TO_PT),
+            format_number(page.height() * PX_TO_PT)
+        )
+        .into_bytes(),
+        stream_object,
+        b"<< /Producer (Font Scope) >>".to_vec(),
+    ];
+
+    let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        out.extend_from_slice(object);
+        out.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref_offset = out.len();
+    out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    out.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            objects.len(),
+            xref_offset
+        )
+        .as_bytes(),
+    );
+
+    Ok(out)
+}
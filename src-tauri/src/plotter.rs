@@ -0,0 +1,256 @@
+//! 配置したアウトラインをペンプロッター・CNC彫刻向けのG-code・HPGLにする
+//! 座標は左下を原点、Y軸を上向き、単位はmm
+
+use crate::offset::LineJoin;
+use crate::outline::{flatten, format_number, Polyline, TextLayout};
+
+/// HPGLのプロッター単位（1単位 = 0.025mm）
+const HPGL_UNITS_PER_MM: f64 = 40.0;
+
+/// G-codeの設定
+pub struct GCodeOptions {
+    /// 描画時の送り速度（mm/min）
+    pub feed_rate: f64,
+    /// ペンを上げる（工具を退避する）命令
+    pub pen_up: String,
+    /// ペンを下ろす（工具を切り込む）命令
+    pub pen_down: String,
+}
+
+/// 出力形式
+pub enum PlotterFormat {
+    GCode(GCodeOptions),
+    Hpgl,
+}
+
+/// 全文字のアウトラインをmm単位の折れ線にする（閉じた輪郭は始点を末尾に繰り返す）
+fn layout_polylines(layout: &TextLayout, scale: f64, tolerance_mm: f64) -> Vec<Vec<(f64, f64)>> {
+    let bounds = layout.painted_bounds(&[], LineJoin::Round);
+    layout
+        .glyphs
+        .iter()
+        .flat_map(|glyph| flatten(&glyph.segments, tolerance_mm / scale))
+        .map(|Polyline { mut points, closed }| {
+            if closed {
+                points.push(points[0]);
+            }
+            points
+                .into_iter()
+                .map(|(x, y)| ((x - bounds.min_x) * scale, (bounds.max_y - y) * scale))
+                .collect()
+        })
+        .collect()
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// ペンを上げた移動が短くなるよう、現在位置から最も近い輪郭を順に選ぶ
+/// 閉じた輪郭は最も近い頂点から描き始め、開いた輪郭は近い方の端から描く
+fn order_for_travel(mut polylines: Vec<Vec<(f64, f64)>>) -> Vec<Vec<(f64, f64)>> {
+    let mut ordered = Vec::with_capacity(polylines.len());
+    let mut position = (0.0, 0.0);
+
+    while !polylines.is_empty() {
+        let mut best = (0, 0, f64::INFINITY);
+        for (i, points) in polylines.iter().enumerate() {
+            let closed = points.len() > 2 && points.first() == points.last();
+            let candidates: Vec<usize> = if closed {
+                (0..points.len() - 1).collect()
+            } else {
+                vec![0, points.len() - 1]
+            };
+            for start in candidates {
+                let d = distance(position, points[start]);
+                if d < best.2 {
+                    best = (i, start, d);
+                }
+            }
+        }
+
+        let (index, start, _) = best;
+        let mut points = polylines.swap_remove(index);
+        let closed = points.len() > 2 && points.first() == points.last();
+        if closed {
+            points.pop();
+            points.rotate_left(start);
+            points.push(points[0]);
+        } else if start != 0 {
+            points.reverse();
+        }
+        position = *points.last().unwrap();
+        ordered.push(points);
+    }
+    ordered
+}
+
+fn render_gcode(polylines: &[Vec<(f64, f64)>], options: &GCodeOptions) -> String {
+    let mut out = String::new();
+    out.push_str("(Font Scope)\n");
+    out.push_str("G21\n"); // mm
+    out.push_str("G90\n"); // 絶対座標
+    // ペンを下ろす命令がG1でも送り速度が決まっているよう、最初に設定する
+    out.push_str(&format!("F{}\n", format_number(options.feed_rate)));
+    out.push_str(&format!("{}\n", options.pen_up));
+    for points in polylines {
+        let (x, y) = points[0];
+        out.push_str(&format!("G0 X{} Y{}\n", format_number(x), format_number(y)));
+        out.push_str(&format!("{}\n", options.pen_down));
+        for (x, y) in points.iter().skip(1) {
+            out.push_str(&format!("G1 X{} Y{}\n", format_number(*x), format_number(*y)));
+        }
+        out.push_str(&format!("{}\n", options.pen_up));
+    }
+    out.push_str("G0 X0 Y0\n");
+    out.push_str("M2\n");
+    out
+}
+
+fn render_hpgl(polylines: &[Vec<(f64, f64)>]) -> String {
+    let unit = |v: f64| ((v * HPGL_UNITS_PER_MM).round() as i64).to_string();
+
+    let mut out = String::from("IN;SP1;\n");
+    for points in polylines {
+        let (x, y) = points[0];
+        out.push_str(&format!("PU{},{};\n", unit(x), unit(y)));
+        let coordinates: Vec<String> = points
+            .iter()
+            .skip(1)
+            .map(|(x, y)| format!("{},{}", unit(*x), unit(*y)))
+            .collect();
+        out.push_str(&format!("PD{};\n", coordinates.join(",")));
+    }
+    out.push_str("PU0,0;SP0;\n");
+    out
+}
+
+/// テキストのアウトラインを許容誤差tolerance_mm以内の折れ線にしてプロッター用の命令列にする
+/// scaleはpxからmmへの倍率、optimize_travelがfalseなら文字・輪郭の順に描く
+pub fn render_plotter(
+    layout: &TextLayout,
+    scale: f64,
+    tolerance_mm: f64,
+    optimize_travel: bool,
+    format: &PlotterFormat,
+) -> String {
+    let mut polylines = layout_polylines(layout, scale, tolerance_mm);
+    if optimize_travel {
+        polylines = order_for_travel(polylines);
+    }
+
+    match format {
+        PlotterFormat::GCode(options) => render_gcode(&polylines, options),
+        PlotterFormat::Hpgl => render_hpgl(&polylines),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outline::{GlyphOutline, Segment};
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<Segment> {
+        vec![
+            Segment::MoveTo(x, y),
+            Segment::LineTo(x + size, y),
+            Segment::LineTo(x + size, y + size),
+            Segment::LineTo(x, y + size),
+            Segment::Close,
+        ]
+    }
+
+    /// 10px角の正方形を2つ並べる（glyphsの順は右、左）
+    fn two_squares() -> TextLayout {
+        let glyph = |char_index: usize, segments: Vec<Segment>| GlyphOutline {
+            char_index,
+            text: "A".to_string(),
+            font_name: "Test".to_string(),
+            segments,
+        };
+        TextLayout {
+            width: 30.0,
+            height: 10.0,
+            glyphs: vec![glyph(0, square(20.0, 0.0, 10.0)), glyph(1, square(0.0, 0.0, 10.0))],
+        }
+    }
+
+    fn gcode_options() -> PlotterFormat {
+        PlotterFormat::GCode(GCodeOptions {
+            feed_rate: 1200.0,
+            pen_up: "G0 Z5".to_string(),
+            pen_down: "G1 Z-1".to_string(),
+        })
+    }
+
+    #[test]
+    fn gcode_draws_each_contour_between_pen_down_and_up() {
+        // 0.5mm/px、左下を原点にY軸を上向きにする
+        let gcode = render_plotter(&two_squares(), 0.5, 0.01, false, &gcode_options());
+        assert_eq!(
+            gcode,
+            "(Font Scope)\nG21\nG90\nF1200\nG0 Z5\n\
+             G0 X10 Y5\nG1 Z-1\nG1 X15 Y5\nG1 X15 Y0\nG1 X10 Y0\nG1 X10 Y5\nG0 Z5\n\
+             G0 X0 Y5\nG1 Z-1\nG1 X5 Y5\nG1 X5 Y0\nG1 X0 Y0\nG1 X0 Y5\nG0 Z5\n\
+             G0 X0 Y0\nM2\n"
+        );
+    }
+
+    #[test]
+    fn hpgl_moves_with_pen_up_and_draws_with_pen_down() {
+        // 1mm = 40単位
+        let hpgl = render_plotter(&two_squares(), 0.5, 0.01, false, &PlotterFormat::Hpgl);
+        assert_eq!(
+            hpgl,
+            "IN;SP1;\n\
+             PU400,200;\nPD600,200,600,0,400,0,400,200;\n\
+             PU0,200;\nPD200,200,200,0,0,0,0,200;\n\
+             PU0,0;SP0;\n"
+        );
+    }
+
+    #[test]
+    fn travel_optimization_starts_from_the_nearest_contour() {
+        let gcode = render_plotter(&two_squares(), 0.5, 0.01, true, &gcode_options());
+        let moves: Vec<&str> = gcode.lines().filter(|line| line.starts_with("G0 X")).collect();
+        // 原点に近い左の正方形の角から描き、描き終えた位置から近い角に移る
+        assert_eq!(moves, ["G0 X0 Y0", "G0 X10 Y0", "G0 X0 Y0"]);
+    }
+
+    /// 閉じた輪郭は同じ巡回順（始点は問わない）、開いた輪郭は同じか逆順の点列か
+    fn same_contour(a: &[(f64, f64)], b: &[(f64, f64)]) -> bool {
+        if a.len() != b.len() {
+            return false;
+        }
+        if a.first() == a.last() && b.first() == b.last() {
+            let n = a.len() - 1;
+            return (0..n).any(|shift| (0..n).all(|i| a[i] == b[(i + shift) % n]));
+        }
+        a == b || a.iter().rev().eq(b.iter())
+    }
+
+    #[test]
+    fn travel_optimization_keeps_contours_whole() {
+        let closed = |x: f64, y: f64| vec![(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0), (x, y)];
+        let polylines = vec![
+            closed(10.0, 10.0),
+            vec![(9.0, 0.0), (5.0, 1.0), (2.0, 0.5)],
+            closed(3.0, 4.0),
+            closed(0.5, 0.5),
+            vec![(20.0, 20.0), (12.0, 12.0)],
+        ];
+        let ordered = order_for_travel(polylines.clone());
+
+        assert_eq!(ordered.len(), polylines.len());
+        let mut used = vec![false; polylines.len()];
+        for contour in &ordered {
+            let index = (0..polylines.len())
+                .find(|&i| !used[i] && same_contour(contour, &polylines[i]))
+                .expect("contour was split or altered");
+            used[index] = true;
+        }
+        // 原点に最も近い頂点から描き始め、開いた輪郭は近い端から描く
+        assert_eq!(ordered[0][0], (0.5, 0.5));
+        assert_eq!(ordered[1], [(2.0, 0.5), (5.0, 1.0), (9.0, 0.0)]);
+    }
+}
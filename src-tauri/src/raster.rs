@@ -0,0 +1,259 @@
+//! 配置したアウトラインをPNGにラスタライズする
+
+use tiny_skia::{
+    Color, FillRule, FilterQuality, LineCap, Paint, PathBuilder, Pixmap, PixmapPaint, Stroke,
+    Transform,
+};
+
+use crate::offset::{LineJoin, MITER_LIMIT};
+use crate::outline::{Segment, TextLayout};
+use crate::StrokeLayer;
+
+/// SVGのpxを1/96インチとしてDPIを計算する
+pub const CSS_DPI: f64 = 96.0;
+
+/// 1辺の最大ピクセル数（巨大な倍率でメモリを使い切らないため）
+const MAX_IMAGE_SIZE: u32 = 16384;
+
+/// 背景の描画方法
+pub enum Background<'a> {
+    Transparent,
+    /// "#RRGGBB"などの色
+    Color(&'a str),
+    /// 画像ファイルのパス（縦横比を保って全体を覆うように拡大・縮小する）
+    Image(&'a str),
+}
+
+fn parse_color(color: &str) -> Result<Color, String> {
+    let [r, g, b, a] = crate::color::parse_color(color)?;
+    Ok(Color::from_rgba8(r, g, b, a))
+}
+
+/// アウトラインをtiny-skiaのパスに変換する（空のアウトラインはNone）
+pub fn skia_path(segments: &[Segment]) -> Option<tiny_skia::Path> {
+    let mut builder = PathBuilder::new();
+    for segment in segments {
+        match *segment {
+            Segment::MoveTo(x, y) => builder.move_to(x as f32, y as f32),
+            Segment::LineTo(x, y) => builder.line_to(x as f32, y as f32),
+            Segment::QuadTo(x1, y1, x, y) => builder.quad_to(x1 as f32, y1 as f32, x as f32, y as f32),
+            Segment::CurveTo(x1, y1, x2, y2, x, y) => builder.cubic_to(
+                x1 as f32, y1 as f32, x2 as f32, y2 as f32, x as f32, y as f32,
+            ),
+            Segment::Close => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+fn solid_paint(color: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint.anti_alias = true;
+    paint
+}
+
+/// 背景画像を読み込み、乗算済みアルファのPixmapにする
+fn load_background_image(path: &str) -> Result<Pixmap, String> {
+    let data = std::fs::read(path).map_err(|e| format!("Failed to load background image: {}", e))?;
+    let image = image::load_from_memory(&data)
+        .map_err(|e| format!("Failed to load background image: {}", e))?
+        .to_rgba8();
+
+    let (width, height) = image.dimensions();
+    let mut pixels = image.into_raw();
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+
+    tiny_skia::IntSize::from_wh(width, height)
+        .and_then(|size| Pixmap::from_vec(pixels, size))
+        .ok_or_else(|| "Failed to load background image: empty image".to_string())
+}
+
+fn draw_background(pixmap: &mut Pixmap, background: &Background) -> Result<(), String> {
+    match background {
+        Background::Transparent => {}
+        Background::Color(color) => pixmap.fill(parse_color(color)?),
+        Background::Image(path) => {
+            let image = load_background_image(path)?;
+            // CSSのbackground-size: coverと同じく、はみ出した部分は中央で切り取る
+            let scale = (pixmap.width() as f32 / image.width() as f32)
+                .max(pixmap.height() as f32 / image.height() as f32);
+            let offset_x = (pixmap.width() as f32 - image.width() as f32 * scale) / 2.0;
+            let offset_y = (pixmap.height() as f32 - image.height() as f32 * scale) / 2.0;
+            let paint = PixmapPaint {
+                quality: FilterQuality::Bicubic,
+                ..PixmapPaint::default()
+            };
+            pixmap.draw_pixmap(
+                0,
+                0,
+                image.as_ref(),
+                &paint,
+                Transform::from_row(scale, 0.0, 0.0, scale, offset_x, offset_y),
+                None,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// レイアウトを倍率scaleで描画する
+/// 画像はPDF・EPSと同じくストロークも含めて描画される範囲の大きさにする
+/// ストロークは外側のレイヤーから全文字分を重ね、最後に塗りを描くため、隣の文字の塗りを覆わない
+pub fn render(
+    layout: &TextLayout,
+    scale: f64,
+    background: &Background,
+    text_color: &str,
+    stroke_layers: &[&StrokeLayer],
+    stroke_join: LineJoin,
+) -> Result<Pixmap, String> {
+    let bounds = layout.painted_bounds(stroke_layers, stroke_join);
+    let width = (bounds.width() * scale).ceil();
+    let height = (bounds.height() * scale).ceil();
+    if !(1.0..=MAX_IMAGE_SIZE as f64).contains(&width) || !(1.0..=MAX_IMAGE_SIZE as f64).contains(&height) {
+        return Err(format!("Image size too large: {}x{}", width, height));
+    }
+
+    let mut pixmap = Pixmap::new(width as u32, height as u32)
+        .ok_or_else(|| format!("Image size too large: {}x{}", width, height))?;
+    draw_background(&mut pixmap, background)?;
+
+    let transform = Transform::from_scale(scale as f32, scale as f32)
+        .pre_translate(-bounds.min_x as f32, -bounds.min_y as f32);
+    let paths: Vec<tiny_skia::Path> = layout
+        .glyphs
+        .iter()
+        .filter_map(|glyph| skia_path(&glyph.segments))
+        .collect();
+
+    for layer in stroke_layers {
+        // SVGと同じく、ストロークの内側も同じ色で塗る
+        let paint = solid_paint(parse_color(&layer.color)?);
+        let stroke = Stroke {
+            width: (layer.width * 2.0) as f32,
+            line_cap: LineCap::Round,
+            line_join: stroke_join.to_skia(),
+            miter_limit: MITER_LIMIT,
+            ..Stroke::default()
+        };
+        for path in &paths {
+            pixmap.fill_path(path, &paint, FillRule::Winding, transform, None);
+            pixmap.stroke_path(path, &paint, &stroke, transform, None);
+        }
+    }
+
+    let paint = solid_paint(parse_color(text_color)?);
+    for path in &paths {
+        pixmap.fill_path(path, &paint, FillRule::Winding, transform, None);
+    }
+
+    Ok(pixmap)
+}
+
+/// RGBAのPNGにする（pHYsチャンクに解像度を記録する）
+pub fn encode_png(pixmap: &Pixmap, dpi: f64) -> Result<Vec<u8>, String> {
+    // tiny-skiaのピクセルは乗算済みアルファのため戻す
+    let data: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, pixmap.width(), pixmap.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let pixels_per_meter = (dpi / 0.0254).round() as u32;
+    encoder.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: pixels_per_meter,
+        yppu: pixels_per_meter,
+        unit: png::Unit::Meter,
+    }));
+
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    writer
+        .write_image_data(&data)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outline::GlyphOutline;
+
+    fn polygon_layout(points: &[(f64, f64)]) -> TextLayout {
+        let mut segments = vec![Segment::MoveTo(points[0].0, points[0].1)];
+        segments.extend(points[1..].iter().map(|&(x, y)| Segment::LineTo(x, y)));
+        segments.push(Segment::Close);
+        TextLayout {
+            width: 100.0,
+            height: 100.0,
+            glyphs: vec![GlyphOutline {
+                char_index: 0,
+                text: "A".to_string(),
+                font_name: "Test".to_string(),
+                segments,
+            }],
+        }
+    }
+
+    fn render_stroked(points: &[(f64, f64)], join: LineJoin) -> Pixmap {
+        let layer = StrokeLayer {
+            enabled: true,
+            width: 10.0,
+            color: "#FF0000".to_string(),
+        };
+        render(&polygon_layout(points), 1.0, &Background::Transparent, "#000000", &[&layer], join).unwrap()
+    }
+
+    const SQUARE: [(f64, f64); 4] = [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)];
+
+    /// 正方形の左上の角から外側へ(9, 9)pxの位置の不透明度
+    fn corner_alpha(join: LineJoin) -> u8 {
+        let pixmap = render_stroked(&SQUARE, join);
+        let margin = (pixmap.width() - 100) / 2;
+        pixmap.pixel(margin - 9, margin - 9).unwrap().alpha()
+    }
+
+    #[test]
+    fn canvas_includes_stroke() {
+        // ストロークの分だけ四方に10pxずつ広がる
+        let pixmap = render_stroked(&SQUARE, LineJoin::Round);
+        assert_eq!((pixmap.width(), pixmap.height()), (120, 120));
+    }
+
+    #[test]
+    fn stroke_join_shapes_corners() {
+        assert_eq!(corner_alpha(LineJoin::Miter), 255);
+        assert_eq!(corner_alpha(LineJoin::Round), 0);
+    }
+
+    #[test]
+    fn canvas_includes_miter_spikes() {
+        // 頂点の角度が約32°で、マイターは頂点から線幅の約3.6倍伸びる
+        let pixmap = render_stroked(&[(0.0, 100.0), (29.0, 0.0), (58.0, 100.0)], LineJoin::Miter);
+        let (width, height) = (pixmap.width(), pixmap.height());
+        let edges = (0..width)
+            .flat_map(|x| [(x, 0), (x, height - 1)])
+            .chain((0..height).flat_map(|y| [(0, y), (width - 1, y)]));
+        for (x, y) in edges {
+            assert_eq!(pixmap.pixel(x, y).unwrap().alpha(), 0, "({}, {})", x, y);
+        }
+    }
+}
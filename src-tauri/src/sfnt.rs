@@ -0,0 +1,199 @@
+//! フォントのテーブルを直接読むための補助関数（値はすべてビッグエンディアン）
+
+use ttf_parser::Tag;
+
+pub fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+pub fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+pub fn read_i64(data: &[u8], offset: usize) -> Option<i64> {
+    let bytes = data.get(offset..offset + 8)?;
+    Some(i64::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// 16.16固定小数点
+pub fn read_fixed(data: &[u8], offset: usize) -> Option<f32> {
+    read_u32(data, offset).map(|v| v as i32 as f32 / 65536.0)
+}
+
+pub fn read_tag(data: &[u8], offset: usize) -> Option<Tag> {
+    read_u32(data, offset).map(Tag)
+}
+
+/// ItemVariationStoreの長さ（参照しているリージョン・差分データの末尾まで）
+pub fn item_variation_store_len(data: &[u8]) -> Option<usize> {
+    let region_list = read_u32(data, 2)? as usize;
+    let axis_count = read_u16(data, region_list)? as usize;
+    let region_count = read_u16(data, region_list + 2)? as usize;
+    let data_count = read_u16(data, 6)? as usize;
+    let mut end = (region_list + 4 + region_count * axis_count * 6).max(8 + data_count * 4);
+
+    for i in 0..data_count {
+        let offset = read_u32(data, 8 + i * 4)? as usize;
+        let item_count = read_u16(data, offset)? as usize;
+        let word_delta_count = read_u16(data, offset + 2)? as usize;
+        let region_index_count = read_u16(data, offset + 4)? as usize;
+        // 上位ビットが立っていれば、差分は4バイトと2バイト（通常は2バイトと1バイト）
+        let word_count = word_delta_count & 0x7FFF;
+        let word_size = if word_delta_count & 0x8000 != 0 { 4 } else { 2 };
+        let row_size = word_count * word_size + region_index_count.saturating_sub(word_count) * word_size / 2;
+        end = end.max(offset + 6 + region_index_count * 2 + item_count * row_size);
+    }
+
+    (end <= data.len()).then_some(end)
+}
+
+/// sfntのテーブル（タグとデータ）
+pub struct SfntTable {
+    pub tag: [u8; 4],
+    pub data: Vec<u8>,
+}
+
+pub fn table_checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// テーブルをsfnt（フォントが複数ならTTC）として並べる
+/// fontsは（sfntバージョン, 使うテーブルの番号）の一覧で、テーブルデータは共有される
+pub fn build_sfnt(fonts: &[(u32, Vec<usize>)], tables: &[SfntTable]) -> Vec<u8> {
+    let is_collection = fonts.len() > 1;
+    let header_size = if is_collection { 12 + fonts.len() * 4 } else { 0 };
+    let directories_size: usize = fonts.iter().map(|(_, indices)| 12 + indices.len() * 16).sum();
+
+    // テーブルデータの配置（4バイト境界）
+    let mut table_offsets = Vec::with_capacity(tables.len());
+    let mut offset = header_size + directories_size;
+    for table in tables {
+        table_offsets.push(offset);
+        offset += table.data.len().next_multiple_of(4);
+    }
+
+    let mut out = Vec::with_capacity(offset);
+    if is_collection {
+        out.extend_from_slice(b"ttcf");
+        out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        out.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+        let mut directory_offset = header_size;
+        for (_, indices) in fonts {
+            out.extend_from_slice(&(directory_offset as u32).to_be_bytes());
+            directory_offset += 12 + indices.len() * 16;
+        }
+    }
+
+    for (flavor, indices) in fonts {
+        let num_tables = indices.len() as u16;
+        let entry_selector = if num_tables > 0 { 15 - num_tables.leading_zeros() as u16 } else { 0 };
+        let search_range = (1u16 << entry_selector) * 16;
+        out.extend_from_slice(&flavor.to_be_bytes());
+        out.extend_from_slice(&num_tables.to_be_bytes());
+        out.extend_from_slice(&search_range.to_be_bytes());
+        out.extend_from_slice(&entry_selector.to_be_bytes());
+        out.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+
+        // テーブルレコードはタグ順に並べる
+        let mut sorted = indices.clone();
+        sorted.sort_by_key(|&i| tables[i].tag);
+        for i in sorted {
+            let table = &tables[i];
+            out.extend_from_slice(&table.tag);
+            out.extend_from_slice(&table_checksum(&table.data).to_be_bytes());
+            out.extend_from_slice(&(table_offsets[i] as u32).to_be_bytes());
+            out.extend_from_slice(&(table.data.len() as u32).to_be_bytes());
+        }
+    }
+
+    for table in tables {
+        out.extend_from_slice(&table.data);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+
+    out
+}
+
+/// 単体のsfntでheadテーブルのcheckSumAdjustmentを計算し直す
+pub fn update_checksum_adjustment(font: &mut [u8]) {
+    let num_tables = read_u16(font, 4).unwrap_or(0) as usize;
+    let head_offset = (0..num_tables)
+        .map(|i| 12 + i * 16)
+        .find(|&record| font.get(record..record + 4) == Some(b"head"))
+        .and_then(|record| read_u32(font, record + 8))
+        .map(|offset| offset as usize + 8);
+    let Some(offset) = head_offset.filter(|&offset| offset + 4 <= font.len()) else {
+        return;
+    };
+
+    font[offset..offset + 4].fill(0);
+    let adjustment = 0xB1B0_AFBAu32.wrapping_sub(table_checksum(font));
+    font[offset..offset + 4].copy_from_slice(&adjustment.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font::{sfnt_tables, truetype, TestGlyph};
+
+    fn glyphs() -> Vec<TestGlyph> {
+        vec![
+            TestGlyph::Simple(vec![]),
+            TestGlyph::Simple(vec![vec![(0, 0), (100, 0), (100, 100)]]),
+            TestGlyph::Simple(vec![vec![(0, 0), (50, 0), (50, 70)]]),
+        ]
+    }
+
+    #[test]
+    fn builds_collection_sharing_tables() {
+        let mut tables = sfnt_tables(&truetype(&glyphs(), &[('A', 1)], Vec::new()), 0);
+        let second_cmap = sfnt_tables(&truetype(&glyphs(), &[('A', 2), ('B', 1)], Vec::new()), 0)
+            .into_iter()
+            .find(|table| &table.tag == b"cmap")
+            .unwrap();
+        let first: Vec<usize> = (0..tables.len()).collect();
+        let second: Vec<usize> = first
+            .iter()
+            .map(|&i| if &tables[i].tag == b"cmap" { tables.len() } else { i })
+            .collect();
+        tables.push(second_cmap);
+
+        let data = build_sfnt(&[(0x0001_0000, first), (0x0001_0000, second)], &tables);
+        assert_eq!(ttf_parser::fonts_in_collection(&data), Some(2));
+        let first_face = ttf_parser::Face::parse(&data, 0).unwrap();
+        let second_face = ttf_parser::Face::parse(&data, 1).unwrap();
+        assert_eq!(first_face.glyph_index('A'), Some(ttf_parser::GlyphId(1)));
+        assert_eq!(second_face.glyph_index('A'), Some(ttf_parser::GlyphId(2)));
+        assert_eq!(second_face.glyph_index('B'), Some(ttf_parser::GlyphId(1)));
+
+        // 共有するテーブルは1つだけ置かれる
+        let glyf = |face: &ttf_parser::Face| face.raw_face().table(ttf_parser::Tag::from_bytes(b"glyf")).unwrap().as_ptr();
+        assert_eq!(glyf(&first_face), glyf(&second_face));
+    }
+
+    #[test]
+    fn checksum_adjustment_balances_font_checksum() {
+        let tables = sfnt_tables(&truetype(&glyphs(), &[('A', 1)], Vec::new()), 0);
+        let mut data = build_sfnt(&[(0x0001_0000, (0..tables.len()).collect())], &tables);
+        update_checksum_adjustment(&mut data);
+        assert_eq!(table_checksum(&data), 0xB1B0_AFBA);
+
+        // テーブルのチェックサムはテーブルレコードと一致する
+        for i in 0..tables.len() {
+            let record = 12 + i * 16;
+            let offset = read_u32(&data, record + 8).unwrap() as usize;
+            let length = read_u32(&data, record + 12).unwrap() as usize;
+            let mut table = data[offset..offset + length].to_vec();
+            if &data[record..record + 4] == b"head" {
+                table[8..12].fill(0);
+            }
+            assert_eq!(read_u32(&data, record + 4), Some(table_checksum(&table)));
+        }
+    }
+}
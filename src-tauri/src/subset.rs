@@ -0,0 +1,1631 @@
+//! テキストに必要なグリフだけを残したサブセットフォントを作る
+//! 残すグリフを元の順に詰めてグリフIDを付け替え、cmap・hmtx・GSUB/GPOSなどグリフIDを参照するテーブルも書き直す
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use ttf_parser::gsub::{SingleSubstitution, SubstitutionSubtable};
+use ttf_parser::opentype_layout::{ChainedContextLookup, ContextLookup, LayoutTable};
+use ttf_parser::{Face, GlyphId, Tag};
+
+use crate::cff;
+use crate::sfnt::{build_sfnt, item_variation_store_len, read_u16, read_u32, update_checksum_adjustment, SfntTable};
+use crate::subset_layout;
+
+/// シェイピング時に既定で有効になるGSUBフィーチャー（用字系によって適用されるものを含む）
+const DEFAULT_FEATURES: [&[u8; 4]; 40] = [
+    b"abvf", b"abvs", b"akhn", b"blwf", b"blws", b"calt", b"ccmp", b"cfar", b"cjct", b"clig",
+    b"fin2", b"fin3", b"fina", b"half", b"haln", b"init", b"isol", b"liga", b"ljmo", b"locl",
+    b"ltra", b"ltrm", b"med2", b"medi", b"mset", b"nukt", b"pref", b"pres", b"pstf", b"psts",
+    b"rclt", b"rkrf", b"rlig", b"rphf", b"rtla", b"rtlm", b"rvrn", b"tjmo", b"vatu", b"vjmo",
+];
+/// 縦書きで有効になるフィーチャー
+const VERTICAL_FEATURES: [&[u8; 4]; 2] = [b"vert", b"vrt2"];
+/// 分数の斜線（U+2044）の前後だけに適用されるフィーチャー
+const FRACTION_FEATURES: [&[u8; 4]; 3] = [b"frac", b"numr", b"dnom"];
+/// 書き出さないテーブル
+/// グリフごとの値を持つもの（hdmx・LTSH）と、グリフIDを参照していて付け替えに対応していないもの
+const DROPPED_TABLES: [&[u8; 4]; 22] = [
+    b"DSIG", b"hdmx", b"LTSH", b"SVG ", b"sbix", b"CBDT", b"CBLC", b"EBDT", b"EBLC", b"EBSC", b"MATH",
+    b"JSTF", b"morx", b"mort", b"kerx", b"ankr", b"bsln", b"just", b"lcar", b"opbd", b"prop", b"Zapf",
+];
+/// DROPPED_TABLESのうちカラーグリフのテーブル（除くと絵文字などがアウトラインのみになる）
+const COLOR_GLYPH_TABLES: [&[u8; 4]; 4] = [b"SVG ", b"sbix", b"CBDT", b"CBLC"];
+
+const SFNT_VERSION_TRUETYPE: u32 = 0x0001_0000;
+const SFNT_VERSION_CFF: u32 = 0x4F54_544F; // "OTTO"
+
+pub struct SubsetFont {
+    /// sfntバージョン（TrueTypeアウトラインは0x00010000、CFFは"OTTO"）
+    pub flavor: u32,
+    pub tables: Vec<SfntTable>,
+    /// 残したグリフ数（.notdefを含む）
+    pub glyph_count: usize,
+    /// 書き換えに対応していないため除いたカラーグリフのテーブル（例: "sbix"）
+    pub dropped_color_tables: Vec<String>,
+}
+
+impl SubsetFont {
+    pub fn is_cff(&self) -> bool {
+        self.flavor == SFNT_VERSION_CFF
+    }
+
+    pub fn to_sfnt(&self) -> Vec<u8> {
+        let mut font = build_sfnt(&[(self.flavor, (0..self.tables.len()).collect())], &self.tables);
+        update_checksum_adjustment(&mut font);
+        font
+    }
+}
+
+/// 元のグリフIDと新しいグリフIDの対応（残すグリフを元の順に詰める）
+pub struct GlyphMap {
+    new_ids: Vec<Option<u16>>,
+    /// 新しいグリフIDの順に並べた元のグリフID
+    old_ids: Vec<u16>,
+}
+
+impl GlyphMap {
+    fn new(num_glyphs: u16, glyphs: &BTreeSet<u16>) -> Self {
+        let old_ids: Vec<u16> = glyphs.iter().copied().filter(|&g| g < num_glyphs).collect();
+        let mut new_ids = vec![None; num_glyphs as usize];
+        for (new_id, &old_id) in old_ids.iter().enumerate() {
+            new_ids[old_id as usize] = Some(new_id as u16);
+        }
+        Self { new_ids, old_ids }
+    }
+
+    /// 元のグリフIDに対応する新しいグリフID（残さないグリフはNone）
+    pub fn get(&self, glyph: u16) -> Option<u16> {
+        self.new_ids.get(glyph as usize).copied().flatten()
+    }
+
+    pub fn old_ids(&self) -> &[u16] {
+        &self.old_ids
+    }
+}
+
+/// charsの文字（直後の異体字セレクタによる字形を含む）とGSUBで置換されうるグリフを残す
+/// feature_overridesで既定のフィーチャーを追加・無効化できる（例: palt, ss01, 縦書き以外でのvert）
+pub fn subset_font(
+    face: &Face,
+    chars: &[char],
+    feature_overrides: &[(Tag, bool)],
+    vertical: bool,
+) -> Result<SubsetFont, String> {
+    let mut glyphs = mapped_glyphs(face, chars);
+
+    if let Some(gsub) = face.tables().gsub {
+        let mut features: BTreeSet<Tag> = DEFAULT_FEATURES
+            .iter()
+            .chain(VERTICAL_FEATURES.iter().filter(|_| vertical))
+            .chain(FRACTION_FEATURES.iter().filter(|_| chars.contains(&'\u{2044}')))
+            .map(|tag| Tag::from_bytes(tag))
+            .collect();
+        for &(tag, enabled) in feature_overrides {
+            if enabled {
+                features.insert(tag);
+            } else {
+                features.remove(&tag);
+            }
+        }
+        gsub_closure(&gsub, &features, &mut glyphs);
+    }
+
+    let raw = face.raw_face();
+    let table = |tag: &[u8; 4]| raw.table(Tag::from_bytes(tag));
+    let glyph_ranges = glyf_ranges(face);
+    if let (Some(glyf), Some(ranges)) = (table(b"glyf"), &glyph_ranges) {
+        composite_closure(glyf, ranges, &mut glyphs);
+    }
+    if let Some(colr) = table(b"COLR") {
+        colr_closure(colr, &mut glyphs);
+    }
+    // 範囲外のグリフID（GSUBの不正な値など）は除かれる
+    let map = GlyphMap::new(face.number_of_glyphs(), &glyphs);
+
+    let invalid = |tag: &[u8; 4]| format!("Failed to subset font: invalid {} table", String::from_utf8_lossy(tag).trim());
+    let mapping = retained_mapping(face, &map);
+    let mut tables = Vec::new();
+    let mut dropped_color_tables = Vec::new();
+    let tag_name = |tag: &[u8; 4]| String::from_utf8_lossy(tag).trim().to_string();
+    for record in raw.table_records {
+        let tag = record.tag.to_bytes();
+        if DROPPED_TABLES.contains(&&tag) {
+            if COLOR_GLYPH_TABLES.contains(&&tag) {
+                dropped_color_tables.push(tag_name(&tag));
+            }
+            continue;
+        }
+        let Some(data) = raw.table(record.tag) else {
+            continue;
+        };
+
+        let data = match &tag {
+            b"glyf" | b"loca" | b"head" if glyph_ranges.is_some() => continue,
+            // 送り幅はヘッダーと合わせて後で書く
+            b"hhea" | b"hmtx" | b"vhea" | b"vmtx" => continue,
+            // 書き換えに対応していないCOLRv1（とCPAL）は除く
+            b"COLR" | b"CPAL" if table(b"COLR").and_then(|colr| read_u16(colr, 0)) != Some(0) => {
+                if &tag == b"COLR" {
+                    dropped_color_tables.push(tag_name(&tag));
+                }
+                continue;
+            }
+            b"maxp" => with_num_glyphs(data, map.old_ids().len()),
+            b"CFF " => subset_cff(data, &map, false).ok_or_else(|| invalid(&tag))?,
+            b"CFF2" => subset_cff(data, &map, true).ok_or_else(|| invalid(&tag))?,
+            b"gvar" => subset_gvar(data, &map).ok_or_else(|| invalid(&tag))?,
+            b"HVAR" => subset_metrics_variations(data, &map, 3).ok_or_else(|| invalid(&tag))?,
+            b"VVAR" => subset_metrics_variations(data, &map, 4).ok_or_else(|| invalid(&tag))?,
+            b"GSUB" => subset_layout::subset_gsub(data, &map).ok_or_else(|| invalid(&tag))?,
+            b"GPOS" => subset_layout::subset_gpos(data, &map).ok_or_else(|| invalid(&tag))?,
+            b"GDEF" => subset_layout::subset_gdef(data, &map).ok_or_else(|| invalid(&tag))?,
+            b"COLR" => subset_colr(data, &map).ok_or_else(|| invalid(&tag))?,
+            b"VORG" => subset_vorg(data, &map).ok_or_else(|| invalid(&tag))?,
+            b"cmap" => subset_cmap(data, &mapping, &map),
+            // Apple形式のkernは除く
+            b"kern" => match subset_kern(data, &map) {
+                Some(kern) => kern,
+                None => continue,
+            },
+            b"post" => post_without_glyph_names(data),
+            b"OS/2" => update_os2_char_range(data, &mapping),
+            _ => data.to_vec(),
+        };
+        tables.push(SfntTable { tag, data });
+    }
+
+    for (header_tag, metrics_tag) in [(b"hhea", b"hmtx"), (b"vhea", b"vmtx")] {
+        if let (Some(header), Some(metrics)) = (table(header_tag), table(metrics_tag)) {
+            let (header, metrics) = subset_metrics(header, metrics, &map).ok_or_else(|| invalid(metrics_tag))?;
+            tables.push(SfntTable { tag: *header_tag, data: header });
+            tables.push(SfntTable { tag: *metrics_tag, data: metrics });
+        }
+    }
+
+    if let (Some(glyf), Some(ranges)) = (table(b"glyf"), &glyph_ranges) {
+        let head = table(b"head").ok_or("Failed to subset font: missing head table")?;
+        let (glyf, loca, long_offsets) = subset_glyf(glyf, ranges, &map);
+        let mut head = head.to_vec();
+        if let Some(index_to_loc_format) = head.get_mut(50..52) {
+            index_to_loc_format.copy_from_slice(&(long_offsets as u16).to_be_bytes());
+        }
+        tables.push(SfntTable { tag: *b"head", data: head });
+        tables.push(SfntTable { tag: *b"glyf", data: glyf });
+        tables.push(SfntTable { tag: *b"loca", data: loca });
+    }
+
+    let flavor = if table(b"CFF ").is_some() || table(b"CFF2").is_some() {
+        SFNT_VERSION_CFF
+    } else {
+        SFNT_VERSION_TRUETYPE
+    };
+
+    Ok(SubsetFont {
+        flavor,
+        tables,
+        glyph_count: map.old_ids().len(),
+        dropped_color_tables,
+    })
+}
+
+fn is_variation_selector(c: char) -> bool {
+    matches!(c, '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}')
+}
+
+/// cmapで文字に割り当てられたグリフ（.notdefは常に残す）
+fn mapped_glyphs(face: &Face, chars: &[char]) -> BTreeSet<u16> {
+    let mut glyphs = BTreeSet::from([0]);
+    let mut previous = None;
+
+    for &c in chars {
+        if let Some(glyph) = face.glyph_index(c) {
+            glyphs.insert(glyph.0);
+        }
+        if let (Some(base), true) = (previous, is_variation_selector(c)) {
+            if let Some(glyph) = face.glyph_variation_index(base, c) {
+                glyphs.insert(glyph.0);
+            }
+        }
+        previous = Some(c);
+    }
+
+    glyphs
+}
+
+/// 有効なフィーチャーのルックアップ（文脈置換から呼ばれるものを含む）を、グリフが増えなくなるまで適用する
+fn gsub_closure(gsub: &LayoutTable, features: &BTreeSet<Tag>, glyphs: &mut BTreeSet<u16>) {
+    let mut lookups: BTreeSet<u16> = gsub
+        .features
+        .into_iter()
+        .filter(|feature| features.contains(&feature.tag))
+        .flat_map(|feature| feature.lookup_indices)
+        .collect();
+
+    // 言語システムの必須フィーチャーは常に適用される
+    for script in gsub.scripts {
+        for language in script.default_language.into_iter().chain(script.languages) {
+            if let Some(feature) = language.required_feature.and_then(|i| gsub.features.get(i)) {
+                lookups.extend(feature.lookup_indices);
+            }
+        }
+    }
+
+    let mut pending: Vec<u16> = lookups.iter().copied().collect();
+    while let Some(index) = pending.pop() {
+        let Some(lookup) = gsub.lookups.get(index) else {
+            continue;
+        };
+        for subtable in lookup.subtables.into_iter::<SubstitutionSubtable>() {
+            for nested in nested_lookups(&subtable) {
+                if lookups.insert(nested) {
+                    pending.push(nested);
+                }
+            }
+        }
+    }
+
+    loop {
+        let before = glyphs.len();
+        for lookup in lookups.iter().filter_map(|&i| gsub.lookups.get(i)) {
+            for subtable in lookup.subtables.into_iter::<SubstitutionSubtable>() {
+                let added = substituted_glyphs(&subtable, glyphs);
+                glyphs.extend(added);
+            }
+        }
+        if glyphs.len() == before {
+            break;
+        }
+    }
+}
+
+/// 文脈置換が呼び出すルックアップ
+fn nested_lookups(subtable: &SubstitutionSubtable) -> Vec<u16> {
+    match subtable {
+        SubstitutionSubtable::Context(context) => match context {
+            ContextLookup::Format1 { sets, .. } | ContextLookup::Format2 { sets, .. } => sets
+                .into_iter()
+                .flat_map(|set| set.into_iter())
+                .flat_map(|rule| rule.lookups)
+                .map(|record| record.lookup_list_index)
+                .collect(),
+            ContextLookup::Format3 { lookups, .. } => {
+                lookups.into_iter().map(|record| record.lookup_list_index).collect()
+            }
+        },
+        SubstitutionSubtable::ChainContext(context) => match context {
+            ChainedContextLookup::Format1 { sets, .. } | ChainedContextLookup::Format2 { sets, .. } => sets
+                .into_iter()
+                .flat_map(|set| set.into_iter())
+                .flat_map(|rule| rule.lookups)
+                .map(|record| record.lookup_list_index)
+                .collect(),
+            ChainedContextLookup::Format3 { lookups, .. } => {
+                lookups.into_iter().map(|record| record.lookup_list_index).collect()
+            }
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// 残すグリフに1つのサブテーブルを適用したときの置換先
+/// 文脈置換は呼び出し先のルックアップを直接適用するため、ここでは何も追加しない
+fn substituted_glyphs(subtable: &SubstitutionSubtable, glyphs: &BTreeSet<u16>) -> Vec<u16> {
+    let coverage = subtable.coverage();
+    let covered = glyphs
+        .iter()
+        .filter_map(|&g| coverage.get(GlyphId(g)).map(|index| (g, index)));
+
+    let mut added = Vec::new();
+    for (glyph, index) in covered {
+        match subtable {
+            SubstitutionSubtable::Single(SingleSubstitution::Format1 { delta, .. }) => {
+                added.push((glyph as i32 + *delta as i32).rem_euclid(0x10000) as u16);
+            }
+            SubstitutionSubtable::Single(SingleSubstitution::Format2 { substitutes, .. }) => {
+                added.extend(substitutes.get(index).map(|g| g.0));
+            }
+            SubstitutionSubtable::Multiple(multiple) => {
+                if let Some(sequence) = multiple.sequences.get(index) {
+                    added.extend(sequence.substitutes.into_iter().map(|g| g.0));
+                }
+            }
+            SubstitutionSubtable::Alternate(alternate) => {
+                if let Some(set) = alternate.alternate_sets.get(index) {
+                    added.extend(set.alternates.into_iter().map(|g| g.0));
+                }
+            }
+            SubstitutionSubtable::Ligature(ligature) => {
+                // 構成グリフがすべて残る合字だけを加える
+                for lig in ligature.ligature_sets.get(index).into_iter().flatten() {
+                    if lig.components.into_iter().all(|c| glyphs.contains(&c.0)) {
+                        added.push(lig.glyph.0);
+                    }
+                }
+            }
+            SubstitutionSubtable::ReverseChainSingle(reverse) => {
+                added.extend(reverse.substitutes.get(index).map(|g| g.0));
+            }
+            SubstitutionSubtable::Context(_) | SubstitutionSubtable::ChainContext(_) => {}
+        }
+    }
+
+    added
+}
+
+/// locaから各グリフのglyf内の範囲を求める（glyfが無ければNone）
+fn glyf_ranges(face: &Face) -> Option<Vec<Range<usize>>> {
+    let raw = face.raw_face();
+    let head = raw.table(Tag::from_bytes(b"head"))?;
+    let loca = raw.table(Tag::from_bytes(b"loca"))?;
+    raw.table(Tag::from_bytes(b"glyf"))?;
+
+    let long_offsets = read_u16(head, 50)? != 0;
+    let offset = |i: usize| {
+        if long_offsets {
+            read_u32(loca, i * 4).map(|o| o as usize)
+        } else {
+            read_u16(loca, i * 2).map(|o| o as usize * 2)
+        }
+    };
+
+    (0..face.number_of_glyphs() as usize)
+        .map(|i| Some(offset(i)?..offset(i + 1)?))
+        .collect()
+}
+
+/// 複合グリフの各部品のグリフIDの位置（単純グリフは空）
+fn component_positions(data: &[u8]) -> Vec<usize> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+    let mut positions = Vec::new();
+    if read_u16(data, 0).is_none_or(|n| (n as i16) >= 0) {
+        return positions;
+    }
+
+    let mut offset = 10;
+    while let (Some(flags), Some(_)) = (read_u16(data, offset), read_u16(data, offset + 2)) {
+        positions.push(offset + 2);
+
+        offset += 4;
+        offset += if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            offset += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            offset += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            offset += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    positions
+}
+
+/// 複合グリフが参照するグリフを加える
+fn composite_closure(glyf: &[u8], ranges: &[Range<usize>], glyphs: &mut BTreeSet<u16>) {
+    let mut pending: Vec<u16> = glyphs.iter().copied().collect();
+    while let Some(glyph) = pending.pop() {
+        let Some(data) = ranges.get(glyph as usize).and_then(|r| glyf.get(r.clone())) else {
+            continue;
+        };
+        for position in component_positions(data) {
+            if let Some(component) = read_u16(data, position).filter(|&c| glyphs.insert(c)) {
+                pending.push(component);
+            }
+        }
+    }
+}
+
+/// COLR（バージョン0）でレイヤーに使われるグリフを加える
+fn colr_closure(colr: &[u8], glyphs: &mut BTreeSet<u16>) {
+    let (Some(0), Some(base_count), Some(base_offset), Some(layer_offset)) =
+        (read_u16(colr, 0), read_u16(colr, 2), read_u32(colr, 4), read_u32(colr, 8))
+    else {
+        return;
+    };
+
+    let mut layers = Vec::new();
+    for i in 0..base_count as usize {
+        let record = base_offset as usize + i * 6;
+        let (Some(glyph), Some(first), Some(count)) =
+            (read_u16(colr, record), read_u16(colr, record + 2), read_u16(colr, record + 4))
+        else {
+            break;
+        };
+        if glyphs.contains(&glyph) {
+            let first = layer_offset as usize + first as usize * 4;
+            layers.extend((0..count as usize).filter_map(|j| read_u16(colr, first + j * 4)));
+        }
+    }
+    glyphs.extend(layers);
+}
+
+/// 残すグリフを新しいグリフIDの順に並べたglyf・locaと、locaが長い形式かどうかを返す
+/// 複合グリフの部品のグリフIDも付け替える
+fn subset_glyf(glyf: &[u8], ranges: &[Range<usize>], map: &GlyphMap) -> (Vec<u8>, Vec<u8>, bool) {
+    let mut new_glyf = Vec::new();
+    let mut offsets = Vec::with_capacity(map.old_ids().len() + 1);
+
+    for &glyph in map.old_ids() {
+        offsets.push(new_glyf.len());
+        let start = new_glyf.len();
+        let range = ranges.get(glyph as usize).cloned().unwrap_or_default();
+        new_glyf.extend_from_slice(glyf.get(range).unwrap_or_default());
+        let data = &mut new_glyf[start..];
+        for position in component_positions(data) {
+            // 部品はcomposite_closureで残すグリフに加えてある
+            let component = read_u16(data, position).and_then(|c| map.get(c)).unwrap_or(0);
+            data[position..position + 2].copy_from_slice(&component.to_be_bytes());
+        }
+        // 短い形式のlocaで表せるよう偶数バイトに揃える
+        new_glyf.resize(new_glyf.len().next_multiple_of(2), 0);
+    }
+    offsets.push(new_glyf.len());
+
+    let long_offsets = new_glyf.len() / 2 > u16::MAX as usize;
+    let loca = if long_offsets {
+        offsets.iter().flat_map(|&o| (o as u32).to_be_bytes()).collect()
+    } else {
+        offsets.iter().flat_map(|&o| ((o / 2) as u16).to_be_bytes()).collect()
+    };
+
+    (new_glyf, loca, long_offsets)
+}
+
+/// gvarの変形データを残すグリフの順に並べる
+fn subset_gvar(gvar: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let axis_count = read_u16(gvar, 4)? as usize;
+    let shared_tuple_count = read_u16(gvar, 6)? as usize;
+    let shared_tuples_offset = read_u32(gvar, 8)? as usize;
+    let glyph_count = read_u16(gvar, 12)? as usize;
+    let flags = read_u16(gvar, 14)?;
+    let data_offset = read_u32(gvar, 16)? as usize;
+
+    let long_offsets = flags & 1 != 0;
+    let offset = |i: usize| {
+        if long_offsets {
+            read_u32(gvar, 20 + i * 4).map(|o| o as usize)
+        } else {
+            read_u16(gvar, 20 + i * 2).map(|o| o as usize * 2)
+        }
+    };
+
+    let shared_tuples_size = shared_tuple_count * axis_count * 2;
+    let shared_tuples = gvar.get(shared_tuples_offset..shared_tuples_offset + shared_tuples_size)?;
+
+    let mut data = Vec::new();
+    let mut offsets = Vec::with_capacity(map.old_ids().len() + 1);
+    for &glyph in map.old_ids() {
+        offsets.push(data.len() as u32);
+        let glyph = glyph as usize;
+        if glyph < glyph_count {
+            let start = data_offset + offset(glyph)?;
+            let end = data_offset + offset(glyph + 1)?;
+            data.extend_from_slice(gvar.get(start..end)?);
+        }
+    }
+    offsets.push(data.len() as u32);
+
+    // オフセットは常に長い形式で書く
+    let new_shared_tuples_offset = 20 + offsets.len() * 4;
+    let new_data_offset = new_shared_tuples_offset + shared_tuples_size;
+
+    let mut out = gvar.get(..8)?.to_vec();
+    out.extend_from_slice(&(new_shared_tuples_offset as u32).to_be_bytes());
+    out.extend_from_slice(&(map.old_ids().len() as u16).to_be_bytes());
+    out.extend_from_slice(&(flags | 1).to_be_bytes());
+    out.extend_from_slice(&(new_data_offset as u32).to_be_bytes());
+    for offset in offsets {
+        out.extend_from_slice(&offset.to_be_bytes());
+    }
+    out.extend_from_slice(shared_tuples);
+    out.extend_from_slice(&data);
+    Some(out)
+}
+
+/// グリフ名を持たない形式3のpostテーブル（グリフ名はグリフ数に比例して大きいため）
+fn post_without_glyph_names(post: &[u8]) -> Vec<u8> {
+    let mut out = post.get(..32).unwrap_or(post).to_vec();
+    if let Some(version) = out.get_mut(0..4) {
+        version.copy_from_slice(&0x0003_0000u32.to_be_bytes());
+    }
+    out
+}
+
+/// kernテーブル（OpenType形式）の形式0サブテーブルを残すグリフ同士のペアだけにしてグリフIDを付け替える
+/// グリフIDを付け替えないApple形式（バージョン1）はNone、クラスを使う形式2のサブテーブルは除く
+fn subset_kern(kern: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    if read_u16(kern, 0)? != 0 {
+        return None;
+    }
+    let num_tables = read_u16(kern, 2).unwrap_or(0) as usize;
+
+    let mut subtables = Vec::new();
+    let mut offset = 4;
+    for _ in 0..num_tables {
+        let (Some(length), Some(coverage)) = (read_u16(kern, offset + 2), read_u16(kern, offset + 4)) else {
+            break;
+        };
+
+        if coverage >> 8 != 0 {
+            offset += length as usize;
+            continue;
+        }
+
+        // ペアが多いとlengthが桁あふれするため、ペア数から長さを求める
+        // グリフIDは元の順に詰めるため、付け替えてもペアの並び順は変わらない
+        let num_pairs = read_u16(kern, offset + 6).unwrap_or(0) as usize;
+        let pairs: Vec<[u16; 3]> = (0..num_pairs)
+            .filter_map(|i| {
+                let pair = offset + 14 + i * 6;
+                let left = map.get(read_u16(kern, pair)?)?;
+                let right = map.get(read_u16(kern, pair + 2)?)?;
+                Some([left, right, read_u16(kern, pair + 4)?])
+            })
+            .collect();
+        offset += 14 + num_pairs * 6;
+
+        let entry_selector = if pairs.is_empty() { 0 } else { 15 - (pairs.len() as u16).leading_zeros() as u16 };
+        let search_range = (1u16 << entry_selector) * 6;
+        let new_length = 14 + pairs.len() * 6;
+        let mut out = Vec::with_capacity(new_length);
+        out.extend_from_slice(&0u16.to_be_bytes()); // version
+        out.extend_from_slice(&(new_length.min(u16::MAX as usize) as u16).to_be_bytes());
+        out.extend_from_slice(&coverage.to_be_bytes());
+        out.extend_from_slice(&(pairs.len() as u16).to_be_bytes());
+        out.extend_from_slice(&search_range.to_be_bytes());
+        out.extend_from_slice(&entry_selector.to_be_bytes());
+        out.extend_from_slice(&((pairs.len() * 6) as u16).wrapping_sub(search_range).to_be_bytes());
+        for value in pairs.into_iter().flatten() {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        subtables.push(out);
+    }
+
+    let mut out = 0u16.to_be_bytes().to_vec();
+    out.extend_from_slice(&(subtables.len() as u16).to_be_bytes());
+    out.extend(subtables.concat());
+    Some(out)
+}
+
+/// CFFのオフセットを書き換える項目の位置（書き出し後のCFF先頭からのオフセット）
+#[derive(Default)]
+struct CffLayout {
+    charset: Option<usize>,
+    fd_select: Option<usize>,
+    char_strings: usize,
+    fd_array: Option<usize>,
+    variation_store: Option<usize>,
+    /// (サイズ, オフセット)
+    private: Option<(usize, usize)>,
+}
+
+/// Private DICTのSubrs演算子
+const PRIVATE_SUBRS: u16 = 19;
+
+type ReadIndex = fn(&[u8], usize) -> Option<(Vec<&[u8]>, usize)>;
+type WriteIndex = fn(&[&[u8]]) -> Vec<u8>;
+
+/// CFF・CFF2のCharStringsを残すグリフの順に並べ、charset・FDSelectを書き直す
+/// サブルーチンは番号を変えずに、残すグリフから呼ばれないものの中身を空にする
+fn subset_cff(data: &[u8], map: &GlyphMap, cff2: bool) -> Option<Vec<u8>> {
+    let (read_index, write_index): (ReadIndex, WriteIndex) = if cff2 {
+        (cff::index2, cff::write_index2)
+    } else {
+        (cff::index, cff::write_index)
+    };
+
+    // Top DICTと、その前後でそのまま使う部分
+    // CFFはヘッダー・Name INDEXの後にTop DICT INDEX・String INDEX・Global Subrs INDEXが続き、
+    // CFF2はヘッダーの後にTop DICT・Global Subrs INDEXが続く
+    let header_size = *data.get(2)? as usize;
+    let (top_dict, before_top_dict, string_index, global_subrs) = if cff2 {
+        let top_dict_end = header_size + read_u16(data, 3)? as usize;
+        (
+            data.get(header_size..top_dict_end)?,
+            &data[..header_size],
+            &[][..],
+            cff::index2(data, top_dict_end)?.0,
+        )
+    } else {
+        let (_, top_dict_index) = cff::index(data, header_size)?;
+        let (top_dicts, string_index) = cff::index(data, top_dict_index)?;
+        let (_, global_subrs_index) = cff::index(data, string_index)?;
+        (
+            *top_dicts.first()?,
+            &data[..top_dict_index],
+            &data[string_index..global_subrs_index],
+            cff::index(data, global_subrs_index)?.0,
+        )
+    };
+
+    let entries = cff::dict_entries(top_dict)?;
+    let operands = |operator: u16| {
+        entries
+            .iter()
+            .find(|entry| entry.operator == operator)
+            .map(|entry| entry.operands.as_slice())
+    };
+    let offset_operand = |operator: u16| operands(operator).and_then(|o| o.first().copied()).map(|o| o as usize);
+    let retained = |values: &[u16]| -> Option<Vec<u16>> {
+        map.old_ids().iter().map(|&g| values.get(g as usize).copied()).collect()
+    };
+
+    let (char_strings, _) = read_index(data, offset_operand(17)?)?;
+    let num_glyphs = char_strings.len();
+    let char_strings: Vec<&[u8]> = map
+        .old_ids()
+        .iter()
+        .map(|&g| char_strings.get(g as usize).copied())
+        .collect::<Option<_>>()?;
+
+    // CFF2にはcharsetが無い
+    let charset = match cff2 {
+        true => None,
+        false => {
+            let ids = read_charset(data, offset_operand(15).unwrap_or(0), num_glyphs)?;
+            Some(write_charset(&retained(&ids)?))
+        }
+    };
+    // 各グリフのFont DICTの番号（FDSelectが無ければすべて0）
+    let fds = match offset_operand(0x0C25) {
+        Some(offset) => read_fd_select(data, offset, num_glyphs)?,
+        None => vec![0; num_glyphs],
+    };
+    let fd_select = match offset_operand(0x0C25) {
+        Some(_) => Some(write_fd_select(&retained(&fds)?)),
+        None => None,
+    };
+    let font_dicts = match offset_operand(0x0C24) {
+        Some(offset) => Some(read_index(data, offset)?.0),
+        None => None,
+    };
+    // CFF2の変動データ（長さに続くItemVariationStore）
+    let variation_store = match offset_operand(24) {
+        Some(offset) => Some(data.get(offset..offset + 2 + read_u16(data, offset)? as usize)?),
+        None => None,
+    };
+
+    // Private DICTの(サイズ, オフセット)（Font DICTごと。CIDフォントでなければTop DICTの1つ）
+    let private_location = |operands: Option<&[i32]>| match operands {
+        Some(&[size, offset]) => Some((size as usize, offset as usize)),
+        _ => None,
+    };
+    let private_locations: Vec<_> = match &font_dicts {
+        Some(font_dicts) => font_dicts
+            .iter()
+            .map(|font_dict| private_location(cff::dict_operands(font_dict, 18).as_deref()))
+            .collect(),
+        None => vec![private_location(operands(18))],
+    };
+    let privates = private_locations
+        .iter()
+        .map(|location| match location {
+            Some(location) => private_subrs(data, *location, read_index),
+            None => Some((None, 0)),
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    // 残すグリフから呼ばれるサブルーチン（CharStringを解釈できなければNoneにしてすべて残す）
+    let region_counts = variation_store
+        .and_then(|store| cff::region_counts(&store[2..]))
+        .unwrap_or_default();
+    let used_subrs = {
+        let mut collector = cff::SubrCollector::new(&global_subrs, &region_counts, cff2);
+        let mut local_used = vec![BTreeSet::new(); privates.len()];
+        map.old_ids()
+            .iter()
+            .zip(&char_strings)
+            .try_for_each(|(&g, char_string)| {
+                let fd = *fds.get(g as usize)? as usize;
+                let (subrs, vsindex) = privates.get(fd)?;
+                collector.collect(char_string, subrs.as_deref().unwrap_or(&[]), *vsindex, local_used.get_mut(fd)?)
+            })
+            .map(|_| (collector.global_used, local_used))
+    };
+
+    let global_subrs = prune_subrs(&global_subrs, used_subrs.as_ref().map(|(global, _)| global), write_index, cff2);
+    let encode_head = |top_dict: &[u8]| {
+        let mut out = before_top_dict.to_vec();
+        if cff2 {
+            // ヘッダーのtopDictLength
+            out.get_mut(3..5)?.copy_from_slice(&(top_dict.len() as u16).to_be_bytes());
+            out.extend_from_slice(top_dict);
+        } else {
+            out.extend(cff::write_index(&[top_dict]));
+        }
+        out.extend_from_slice(string_index);
+        out.extend_from_slice(&global_subrs);
+        Some(out)
+    };
+
+    // Private DICTとローカルサブルーチンを続けて配置したデータ
+    let mut private_blocks = private_locations
+        .iter()
+        .zip(&privates)
+        .enumerate()
+        .map(|(fd, (location, (subrs, _)))| match location {
+            Some((size, offset)) => {
+                let used = used_subrs.as_ref().and_then(|(_, local)| local.get(fd));
+                let subrs = subrs.as_ref().map(|subrs| prune_subrs(subrs, used, write_index, cff2));
+                private_block(data, *size, *offset, subrs).map(Some)
+            }
+            None => Some(None),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let top_private = match font_dicts {
+        Some(_) => None,
+        None => private_blocks.pop().flatten(),
+    };
+
+    // Top DICTはオフセットを5バイト形式で書くため、値が決まる前に長さが分かる
+    let placeholder = CffLayout {
+        charset: charset.as_ref().map(|_| 0),
+        fd_select: fd_select.as_ref().map(|_| 0),
+        char_strings: 0,
+        fd_array: font_dicts.as_ref().map(|_| 0),
+        variation_store: variation_store.map(|_| 0),
+        private: top_private.as_ref().map(|_| (0, 0)),
+    };
+    let head_size = encode_head(&encode_top_dict(&entries, &placeholder))?.len();
+
+    let mut body = Vec::new();
+    let mut layout = CffLayout::default();
+    if let Some(charset) = charset {
+        layout.charset = Some(head_size + body.len());
+        body.extend(charset);
+    }
+    if let Some(fd_select) = fd_select {
+        layout.fd_select = Some(head_size + body.len());
+        body.extend(fd_select);
+    }
+    if let Some(variation_store) = variation_store {
+        layout.variation_store = Some(head_size + body.len());
+        body.extend_from_slice(variation_store);
+    }
+    layout.char_strings = head_size + body.len();
+    body.extend(write_index(&char_strings));
+
+    if let Some(font_dicts) = font_dicts {
+        // Font DICTの後ろにそれぞれのPrivate DICTを置く
+        let encode_all = |locations: &[Option<(usize, usize)>]| {
+            let dicts = font_dicts
+                .iter()
+                .zip(locations)
+                .map(|(font_dict, location)| encode_font_dict(font_dict, *location))
+                .collect::<Option<Vec<_>>>()?;
+            let refs: Vec<&[u8]> = dicts.iter().map(|d| d.as_slice()).collect();
+            Some(write_index(&refs))
+        };
+
+        let placeholders: Vec<_> = private_blocks.iter().map(|p| p.as_ref().map(|_| (0, 0))).collect();
+        let fd_array_offset = head_size + body.len();
+        let mut private_offset = fd_array_offset + encode_all(&placeholders)?.len();
+        let locations: Vec<_> = private_blocks
+            .iter()
+            .map(|p| {
+                p.as_ref().map(|(block, dict_size)| {
+                    let location = (*dict_size, private_offset);
+                    private_offset += block.len();
+                    location
+                })
+            })
+            .collect();
+
+        layout.fd_array = Some(fd_array_offset);
+        body.extend(encode_all(&locations)?);
+        for (block, _) in private_blocks.into_iter().flatten() {
+            body.extend(block);
+        }
+    }
+
+    if let Some((block, dict_size)) = top_private {
+        layout.private = Some((dict_size, head_size + body.len()));
+        body.extend(block);
+    }
+
+    let mut out = encode_head(&encode_top_dict(&entries, &layout))?;
+    out.extend(body);
+    Some(out)
+}
+
+fn encode_top_dict(entries: &[cff::DictEntry], layout: &CffLayout) -> Vec<u8> {
+    let mut out = Vec::new();
+    for entry in entries {
+        let offset = match entry.operator {
+            15 => layout.charset,
+            // OpenTypeでは文字の割り当てにcmapを使うため、Encodingは省いて標準エンコーディングにする
+            16 => continue,
+            17 => Some(layout.char_strings),
+            24 => layout.variation_store,
+            0x0C24 => layout.fd_array,
+            0x0C25 => layout.fd_select,
+            18 => {
+                match layout.private {
+                    Some((size, offset)) => cff::write_dict_entry(&mut out, 18, &[size as i32, offset as i32]),
+                    None => cff::write_raw_dict_entry(&mut out, entry),
+                }
+                continue;
+            }
+            _ => None,
+        };
+        match offset {
+            Some(offset) => cff::write_dict_entry(&mut out, entry.operator, &[offset as i32]),
+            None => cff::write_raw_dict_entry(&mut out, entry),
+        }
+    }
+    // charsetを省くと定義済みのISOAdobeになるため、書き直したcharsetは常に指定する
+    if let (Some(charset), false) = (layout.charset, entries.iter().any(|entry| entry.operator == 15)) {
+        cff::write_dict_entry(&mut out, 15, &[charset as i32]);
+    }
+    out
+}
+
+fn encode_font_dict(font_dict: &[u8], private: Option<(usize, usize)>) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    for entry in cff::dict_entries(font_dict)? {
+        match (entry.operator, private) {
+            (18, Some((size, offset))) => cff::write_dict_entry(&mut out, 18, &[size as i32, offset as i32]),
+            _ => cff::write_raw_dict_entry(&mut out, &entry),
+        }
+    }
+    Some(out)
+}
+
+/// Private DICTのローカルサブルーチン（Subrsが無ければNone）と、CFF2の既定のvsindex
+fn private_subrs(
+    data: &[u8],
+    (size, offset): (usize, usize),
+    read_index: ReadIndex,
+) -> Option<(Option<Vec<&[u8]>>, usize)> {
+    let dict = data.get(offset..offset + size)?;
+    // Subrsのオフセットは Private DICTの先頭から
+    let subrs = match cff::dict_operands(dict, PRIVATE_SUBRS).as_deref() {
+        Some(&[relative]) => Some(read_index(data, offset + relative as usize)?.0),
+        _ => None,
+    };
+    let vsindex = cff::dict_operands(dict, 22)
+        .and_then(|operands| operands.first().copied())
+        .unwrap_or(0);
+    Some((subrs, usize::try_from(vsindex).ok()?))
+}
+
+/// 使われないサブルーチンの中身を空にしたINDEX（usedがNoneならすべて残す）
+fn prune_subrs(subrs: &[&[u8]], used: Option<&BTreeSet<usize>>, write_index: WriteIndex, cff2: bool) -> Vec<u8> {
+    // CFFのサブルーチンはreturnで終える
+    let empty: &[u8] = if cff2 { &[] } else { &[11] };
+    let subrs: Vec<&[u8]> = subrs
+        .iter()
+        .enumerate()
+        .map(|(i, &subr)| match used.is_none_or(|used| used.contains(&i)) {
+            true => subr,
+            false => empty,
+        })
+        .collect();
+    write_index(&subrs)
+}
+
+/// Private DICTとローカルサブルーチンのINDEXを続けて配置したデータと、Private DICTの長さ
+fn private_block(data: &[u8], size: usize, offset: usize, subrs: Option<Vec<u8>>) -> Option<(Vec<u8>, usize)> {
+    let entries = cff::dict_entries(data.get(offset..offset + size)?)?;
+    let mut out = Vec::new();
+    for entry in entries.iter().filter(|entry| entry.operator != PRIVATE_SUBRS) {
+        cff::write_raw_dict_entry(&mut out, entry);
+    }
+
+    let Some(subrs) = subrs else {
+        let dict_size = out.len();
+        return Some((out, dict_size));
+    };
+    // 5バイトの整数と演算子の1バイト
+    let dict_size = out.len() + 6;
+    cff::write_dict_entry(&mut out, PRIVATE_SUBRS, &[dict_size as i32]);
+    out.extend(subrs);
+    Some((out, dict_size))
+}
+
+/// charsetの各グリフのSID（CIDフォントではCID）（.notdefの0を含む）
+fn read_charset(data: &[u8], offset: usize, num_glyphs: usize) -> Option<Vec<u16>> {
+    let mut ids = vec![0];
+    match offset {
+        // 定義済みのISOAdobeはグリフIDとSIDが同じ
+        0 => ids.extend(1..num_glyphs as u16),
+        // 定義済みのExpert・ExpertSubsetには対応しない
+        1 | 2 => return None,
+        _ => match *data.get(offset)? {
+            0 => {
+                for i in 1..num_glyphs {
+                    ids.push(read_u16(data, offset - 1 + i * 2)?);
+                }
+            }
+            format @ (1 | 2) => {
+                let mut pos = offset + 1;
+                while ids.len() < num_glyphs {
+                    let first = read_u16(data, pos)?;
+                    let left = if format == 1 {
+                        *data.get(pos + 2)? as u16
+                    } else {
+                        read_u16(data, pos + 2)?
+                    };
+                    ids.extend((0..=left).map(|i| first.wrapping_add(i)));
+                    pos += if format == 1 { 3 } else { 4 };
+                }
+                ids.truncate(num_glyphs);
+            }
+            _ => return None,
+        },
+    }
+    Some(ids)
+}
+
+/// charsetを形式0・2のうち小さい方で書く
+fn write_charset(ids: &[u16]) -> Vec<u8> {
+    // .notdefは含めない
+    let ids = ids.get(1..).unwrap_or_default();
+    // (最初のSID, 続く個数)
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for &id in ids {
+        match ranges.last_mut() {
+            Some((first, left)) if *first as u32 + *left as u32 + 1 == id as u32 => *left += 1,
+            _ => ranges.push((id, 0)),
+        }
+    }
+
+    let mut out = Vec::new();
+    if ranges.len() * 4 < ids.len() * 2 {
+        out.push(2);
+        for (first, left) in ranges {
+            out.extend_from_slice(&first.to_be_bytes());
+            out.extend_from_slice(&left.to_be_bytes());
+        }
+    } else {
+        out.push(0);
+        for id in ids {
+            out.extend_from_slice(&id.to_be_bytes());
+        }
+    }
+    out
+}
+
+/// FDSelectの各グリフのFont DICTの番号
+fn read_fd_select(data: &[u8], offset: usize, num_glyphs: usize) -> Option<Vec<u16>> {
+    match *data.get(offset)? {
+        0 => data
+            .get(offset + 1..offset + 1 + num_glyphs)
+            .map(|fds| fds.iter().map(|&fd| fd as u16).collect()),
+        format @ (3 | 4) => {
+            // 形式4（CFF2）は範囲の数と開始グリフが4バイト、Font DICTの番号が2バイト
+            let (count_size, record_size) = if format == 3 { (2, 3) } else { (4, 6) };
+            let read = |pos: usize| match format {
+                3 => read_u16(data, pos).map(usize::from),
+                _ => read_u32(data, pos).map(|v| v as usize),
+            };
+            let count = read(offset + 1)?;
+            let mut fds = vec![0; num_glyphs];
+            for i in 0..count {
+                let record = offset + 1 + count_size + i * record_size;
+                let fd = match format {
+                    3 => *data.get(record + 2)? as u16,
+                    _ => read_u16(data, record + 4)?,
+                };
+                // 次の範囲の開始（最後は番兵）まで
+                let (first, end) = (read(record)?, read(record + record_size)?);
+                fds.get_mut(first.min(num_glyphs)..end.min(num_glyphs))?.fill(fd);
+            }
+            Some(fds)
+        }
+        _ => None,
+    }
+}
+
+/// FDSelectを形式3で書く
+fn write_fd_select(fds: &[u16]) -> Vec<u8> {
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for (glyph, &fd) in fds.iter().enumerate() {
+        if ranges.last().is_none_or(|&(_, last)| last != fd) {
+            ranges.push((glyph as u16, fd));
+        }
+    }
+
+    let mut out = vec![3];
+    out.extend_from_slice(&(ranges.len() as u16).to_be_bytes());
+    for (first, fd) in ranges {
+        out.extend_from_slice(&first.to_be_bytes());
+        out.push(fd as u8);
+    }
+    out.extend_from_slice(&(fds.len() as u16).to_be_bytes());
+    out
+}
+
+/// 残すグリフに割り当てられた文字と新しいグリフID（.notdef以外）
+fn retained_mapping(face: &Face, map: &GlyphMap) -> BTreeMap<u32, u16> {
+    let mut mapping = BTreeMap::new();
+    if let Some(table) = face.tables().cmap {
+        for subtable in table.subtables.into_iter().filter(|s| s.is_unicode()) {
+            subtable.codepoints(|cp| {
+                if let Some(glyph) = subtable.glyph_index(cp).and_then(|g| map.get(g.0)).filter(|&g| g != 0) {
+                    mapping.entry(cp).or_insert(glyph);
+                }
+            });
+        }
+    }
+    mapping
+}
+
+/// 残す文字だけのcmapを作る（形式4・12と、異体字シーケンスがあれば形式14）
+fn subset_cmap(cmap: &[u8], mapping: &BTreeMap<u32, u16>, map: &GlyphMap) -> Vec<u8> {
+    let format4 = cmap_format4(mapping);
+    let format12 = cmap_format12(mapping);
+    let format14 = cmap_format14(cmap, map);
+
+    // (platformID, encodingID, subtablesの番号) 同じサブテーブルは共有する
+    let mut subtables: Vec<&[u8]> = Vec::new();
+    let mut records: Vec<(u16, u16, usize)> = Vec::new();
+    if let Some(format4) = &format4 {
+        records.extend([(0, 3, subtables.len()), (3, 1, subtables.len())]);
+        subtables.push(format4);
+    }
+    records.extend([(0, 4, subtables.len()), (3, 10, subtables.len())]);
+    subtables.push(&format12);
+    if let Some(format14) = &format14 {
+        records.push((0, 5, subtables.len()));
+        subtables.push(format14);
+    }
+    records.sort();
+
+    let mut offsets = Vec::with_capacity(subtables.len());
+    let mut offset = 4 + records.len() * 8;
+    for subtable in &subtables {
+        offsets.push(offset);
+        offset += subtable.len();
+    }
+
+    let mut out = Vec::with_capacity(offset);
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&(records.len() as u16).to_be_bytes());
+    for (platform, encoding, index) in records {
+        out.extend_from_slice(&platform.to_be_bytes());
+        out.extend_from_slice(&encoding.to_be_bytes());
+        out.extend_from_slice(&(offsets[index] as u32).to_be_bytes());
+    }
+    for subtable in subtables {
+        out.extend_from_slice(subtable);
+    }
+    out
+}
+
+/// BMPの文字の形式4サブテーブル（65535バイトを超える場合はNone）
+fn cmap_format4(mapping: &BTreeMap<u32, u16>) -> Option<Vec<u8>> {
+    // (開始, 終了, 各文字のグリフ) 近い文字は0で埋めて同じセグメントにまとめる
+    let mut segments: Vec<(u16, u16, Vec<u16>)> = Vec::new();
+    for (&cp, &glyph) in mapping.range(..0xFFFF) {
+        let cp = cp as u16;
+        match segments.last_mut() {
+            Some((_, end, glyphs)) if cp - *end <= 4 => {
+                glyphs.extend(std::iter::repeat_n(0, (cp - *end - 1) as usize));
+                glyphs.push(glyph);
+                *end = cp;
+            }
+            _ => segments.push((cp, cp, vec![glyph])),
+        }
+    }
+    segments.push((0xFFFF, 0xFFFF, vec![0]));
+
+    let seg_count = segments.len();
+    // 連続した文字が連続したグリフに対応するセグメントはidDeltaで表し、それ以外はglyphIdArrayを使う
+    let uses_delta = |start: u16, glyphs: &[u16]| {
+        glyphs
+            .iter()
+            .enumerate()
+            .all(|(i, &g)| g != 0 && g.wrapping_sub(start.wrapping_add(i as u16)) == glyphs[0].wrapping_sub(start))
+    };
+
+    let mut end_codes = Vec::new();
+    let mut start_codes = Vec::new();
+    let mut id_deltas = Vec::new();
+    let mut id_range_offsets = Vec::new();
+    let mut glyph_id_array: Vec<u16> = Vec::new();
+    for (i, (start, end, glyphs)) in segments.iter().enumerate() {
+        start_codes.push(*start);
+        end_codes.push(*end);
+        if *start == 0xFFFF {
+            id_deltas.push(1);
+            id_range_offsets.push(0);
+        } else if uses_delta(*start, glyphs) {
+            id_deltas.push(glyphs[0].wrapping_sub(*start));
+            id_range_offsets.push(0);
+        } else {
+            id_deltas.push(0);
+            // idRangeOffset自身の位置からglyphIdArray内の位置までのバイト数
+            id_range_offsets.push(((seg_count - i + glyph_id_array.len()) * 2) as u16);
+            glyph_id_array.extend_from_slice(glyphs);
+        }
+    }
+
+    let length = 16 + seg_count * 8 + glyph_id_array.len() * 2;
+    if length > u16::MAX as usize {
+        return None;
+    }
+
+    let entry_selector = 15 - (seg_count as u16).leading_zeros() as u16;
+    let search_range = 2 * (1u16 << entry_selector);
+    let mut out = Vec::with_capacity(length);
+    for value in [4, length as u16, 0, (seg_count * 2) as u16, search_range, entry_selector] {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    out.extend_from_slice(&((seg_count * 2) as u16 - search_range).to_be_bytes());
+    for value in end_codes {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    out.extend_from_slice(&0u16.to_be_bytes()); // reservedPad
+    for values in [start_codes, id_deltas, id_range_offsets, glyph_id_array] {
+        for value in values {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+    Some(out)
+}
+
+/// 全文字の形式12サブテーブル
+fn cmap_format12(mapping: &BTreeMap<u32, u16>) -> Vec<u8> {
+    // (開始文字, 終了文字, 開始グリフ)
+    let mut groups: Vec<(u32, u32, u32)> = Vec::new();
+    for (&cp, &glyph) in mapping {
+        match groups.last_mut() {
+            Some((start, end, start_glyph)) if cp == *end + 1 && glyph as u32 == *start_glyph + (cp - *start) => {
+                *end = cp;
+            }
+            _ => groups.push((cp, cp, glyph as u32)),
+        }
+    }
+
+    let length = 16 + groups.len() * 12;
+    let mut out = Vec::with_capacity(length);
+    out.extend_from_slice(&12u16.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&(length as u32).to_be_bytes());
+    out.extend_from_slice(&0u32.to_be_bytes()); // language
+    out.extend_from_slice(&(groups.len() as u32).to_be_bytes());
+    for (start, end, glyph) in groups {
+        for value in [start, end, glyph] {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+    out
+}
+
+/// 元のcmapの異体字シーケンス（形式14）サブテーブルのグリフIDを付け替える
+/// 残さないグリフへの割り当ては除き、残るものが無ければNone
+fn cmap_format14(cmap: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let num_tables = read_u16(cmap, 2)? as usize;
+    let subtable = (0..num_tables).find_map(|i| {
+        let offset = read_u32(cmap, 4 + i * 8 + 4)? as usize;
+        if read_u16(cmap, offset)? != 14 {
+            return None;
+        }
+        let length = read_u32(cmap, offset + 2)? as usize;
+        cmap.get(offset..offset + length)
+    })?;
+
+    // (異体字セレクタ, Default UVS, Non-Default UVS)
+    let mut records = Vec::new();
+    for i in 0..read_u32(subtable, 6)? as usize {
+        let record = 10 + i * 11;
+        let selector = subtable.get(record..record + 3)?;
+        // Default UVSは基底の文字のグリフを使うという指定なのでそのまま残す
+        let default_uvs = match read_u32(subtable, record + 3)? as usize {
+            0 => None,
+            offset => {
+                let count = read_u32(subtable, offset)? as usize;
+                Some(subtable.get(offset..offset + 4 + count * 4)?.to_vec())
+            }
+        };
+        let non_default_uvs = match read_u32(subtable, record + 7)? as usize {
+            0 => None,
+            offset => {
+                let count = read_u32(subtable, offset)? as usize;
+                let mappings: Vec<u8> = (0..count)
+                    .filter_map(|j| {
+                        let mapping = subtable.get(offset + 4 + j * 5..offset + 9 + j * 5)?;
+                        let glyph = map.get(u16::from_be_bytes([mapping[3], mapping[4]]))?;
+                        Some([&mapping[..3], &glyph.to_be_bytes()].concat())
+                    })
+                    .flatten()
+                    .collect();
+                let count = (mappings.len() / 5) as u32;
+                (count > 0).then(|| [&count.to_be_bytes()[..], &mappings].concat())
+            }
+        };
+        if default_uvs.is_some() || non_default_uvs.is_some() {
+            records.push((selector, default_uvs, non_default_uvs));
+        }
+    }
+    if records.is_empty() {
+        return None;
+    }
+
+    let header_size = 10 + records.len() * 11;
+    let mut out = Vec::new();
+    let mut body = Vec::new();
+    out.extend_from_slice(&14u16.to_be_bytes());
+    out.extend_from_slice(&0u32.to_be_bytes()); // length
+    out.extend_from_slice(&(records.len() as u32).to_be_bytes());
+    for (selector, default_uvs, non_default_uvs) in records {
+        out.extend_from_slice(selector);
+        for table in [default_uvs, non_default_uvs] {
+            match table {
+                Some(table) => {
+                    out.extend_from_slice(&((header_size + body.len()) as u32).to_be_bytes());
+                    body.extend(table);
+                }
+                None => out.extend_from_slice(&0u32.to_be_bytes()),
+            }
+        }
+    }
+    out.extend(body);
+    let length = out.len() as u32;
+    out[2..6].copy_from_slice(&length.to_be_bytes());
+    Some(out)
+}
+
+/// OS/2のusFirstCharIndex・usLastCharIndexを残した文字に合わせる
+fn update_os2_char_range(os2: &[u8], mapping: &BTreeMap<u32, u16>) -> Vec<u8> {
+    let mut out = os2.to_vec();
+    if let (Some(first), Some(last), Some(range)) = (
+        mapping.keys().next(),
+        mapping.keys().next_back(),
+        out.get_mut(64..68),
+    ) {
+        range[..2].copy_from_slice(&((*first).min(0xFFFF) as u16).to_be_bytes());
+        range[2..].copy_from_slice(&((*last).min(0xFFFF) as u16).to_be_bytes());
+    }
+    out
+}
+
+/// maxpのnumGlyphsを書き換える
+fn with_num_glyphs(maxp: &[u8], num_glyphs: usize) -> Vec<u8> {
+    let mut out = maxp.to_vec();
+    if let Some(field) = out.get_mut(4..6) {
+        field.copy_from_slice(&(num_glyphs as u16).to_be_bytes());
+    }
+    out
+}
+
+/// hhea・hmtx（vhea・vmtx）を残すグリフの順に並べる
+fn subset_metrics(header: &[u8], metrics: &[u8], map: &GlyphMap) -> Option<(Vec<u8>, Vec<u8>)> {
+    // numberOfHMetrics（numOfLongVerMetrics）より後ろのグリフは最後の送り幅を使う
+    let long_count = read_u16(header, 34)? as usize;
+    let last_long = long_count.checked_sub(1)?;
+    let values: Vec<(u16, u16)> = map
+        .old_ids()
+        .iter()
+        .map(|&glyph| {
+            let glyph = glyph as usize;
+            let advance = read_u16(metrics, glyph.min(last_long) * 4)?;
+            let bearing = if glyph < long_count {
+                read_u16(metrics, glyph * 4 + 2)
+            } else {
+                read_u16(metrics, long_count * 4 + (glyph - long_count) * 2)
+            };
+            // 末尾のサイドベアリングを省いたフォントがあるため0とみなす
+            Some((advance, bearing.unwrap_or(0)))
+        })
+        .collect::<Option<_>>()?;
+
+    // 末尾で同じ送り幅が続くグリフは送り幅を省く
+    let last_advance = values.last()?.0;
+    let repeated = values.iter().rev().take_while(|(advance, _)| *advance == last_advance).count();
+    let new_long_count = values.len() - repeated + 1;
+
+    let mut out = Vec::with_capacity(values.len() * 2 + new_long_count * 2);
+    for (i, (advance, bearing)) in values.into_iter().enumerate() {
+        if i < new_long_count {
+            out.extend_from_slice(&advance.to_be_bytes());
+        }
+        out.extend_from_slice(&bearing.to_be_bytes());
+    }
+    let mut header = header.to_vec();
+    header[34..36].copy_from_slice(&(new_long_count as u16).to_be_bytes());
+    Some((header, out))
+}
+
+/// HVAR・VVARの各グリフの変動データの対応（DeltaSetIndexMap）を残すグリフの順にする
+/// mapping_countはヘッダーの対応の数（HVARは3、VVARは4）
+fn subset_metrics_variations(data: &[u8], map: &GlyphMap, mapping_count: usize) -> Option<Vec<u8>> {
+    let store_offset = read_u32(data, 4)? as usize;
+    let store = data.get(store_offset..)?;
+    let store = store.get(..item_variation_store_len(store)?)?;
+
+    // (外側の番号, 内側の番号)を上位・下位16ビットにまとめた値
+    let mut mappings = Vec::with_capacity(mapping_count);
+    for i in 0..mapping_count {
+        let entries = match read_u32(data, 8 + i * 4)? as usize {
+            // 送り幅の対応が無ければグリフIDがそのまま内側の番号になる
+            0 if i == 0 => Some(map.old_ids().iter().map(|&g| g as u32).collect()),
+            0 => None,
+            offset => {
+                let entries = read_delta_set_index_map(data.get(offset..)?)?;
+                // 対応の数より後ろのグリフは最後の値を使う
+                let entry = |glyph: u16| entries.get(glyph as usize).or(entries.last()).copied();
+                Some(map.old_ids().iter().map(|&g| entry(g)).collect::<Option<Vec<u32>>>()?)
+            }
+        };
+        mappings.push(entries.map(|entries| write_delta_set_index_map(&entries)));
+    }
+
+    let header_size = 8 + mapping_count * 4;
+    let mut out = data.get(..4)?.to_vec();
+    out.extend_from_slice(&(header_size as u32).to_be_bytes());
+    let mut offset = header_size + store.len();
+    for mapping in &mappings {
+        let value = mapping.as_ref().map_or(0, |m| {
+            let value = offset;
+            offset += m.len();
+            value
+        });
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+    out.extend_from_slice(store);
+    for mapping in mappings.into_iter().flatten() {
+        out.extend(mapping);
+    }
+    Some(out)
+}
+
+fn read_delta_set_index_map(data: &[u8]) -> Option<Vec<u32>> {
+    let entry_format = *data.get(1)?;
+    let (count, start) = match *data.first()? {
+        0 => (read_u16(data, 2)? as usize, 4),
+        1 => (read_u32(data, 2)? as usize, 6),
+        _ => return None,
+    };
+    let size = ((entry_format >> 4) & 3) as usize + 1;
+    let inner_bits = (entry_format & 0x0F) as u32 + 1;
+
+    (0..count)
+        .map(|i| {
+            let bytes = data.get(start + i * size..start + (i + 1) * size)?;
+            let value = bytes.iter().fold(0u32, |acc, &b| acc << 8 | b as u32);
+            Some((value >> inner_bits) << 16 | (value & ((1 << inner_bits) - 1)))
+        })
+        .collect()
+}
+
+fn write_delta_set_index_map(entries: &[u32]) -> Vec<u8> {
+    let bits = |value: u32| 32 - value.leading_zeros();
+    let inner_bits = entries.iter().map(|e| bits(e & 0xFFFF)).max().unwrap_or(0).max(1);
+    let outer_bits = entries.iter().map(|e| bits(e >> 16)).max().unwrap_or(0);
+    let size = (inner_bits + outer_bits).div_ceil(8).max(1) as usize;
+
+    let mut out = vec![0, ((size - 1) << 4) as u8 | (inner_bits - 1) as u8];
+    out.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+    for entry in entries {
+        let value = (entry >> 16) << inner_bits | (entry & 0xFFFF);
+        out.extend_from_slice(&value.to_be_bytes()[4 - size..]);
+    }
+    out
+}
+
+/// COLR（バージョン0）を残すグリフだけにしてグリフIDを付け替える
+fn subset_colr(colr: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let base_count = read_u16(colr, 2)? as usize;
+    let base_offset = read_u32(colr, 4)? as usize;
+    let layer_offset = read_u32(colr, 8)? as usize;
+
+    let mut bases = Vec::new();
+    let mut layers = Vec::new();
+    for i in 0..base_count {
+        let record = base_offset + i * 6;
+        let Some(glyph) = map.get(read_u16(colr, record)?) else {
+            continue;
+        };
+        let first = layer_offset + read_u16(colr, record + 2)? as usize * 4;
+        let count = read_u16(colr, record + 4)?;
+        bases.push([glyph, (layers.len() / 2) as u16, count]);
+        for j in 0..count as usize {
+            // レイヤーのグリフはcolr_closureで残すグリフに加えてある
+            layers.push(map.get(read_u16(colr, first + j * 4)?)?);
+            layers.push(read_u16(colr, first + j * 4 + 2)?);
+        }
+    }
+
+    let mut out = Vec::with_capacity(14 + bases.len() * 6 + layers.len() * 2);
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&(bases.len() as u16).to_be_bytes());
+    out.extend_from_slice(&14u32.to_be_bytes());
+    out.extend_from_slice(&(14 + bases.len() as u32 * 6).to_be_bytes());
+    out.extend_from_slice(&((layers.len() / 2) as u16).to_be_bytes());
+    for value in bases.into_iter().flatten().chain(layers) {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    Some(out)
+}
+
+/// VORGを残すグリフだけにしてグリフIDを付け替える
+fn subset_vorg(vorg: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let count = read_u16(vorg, 6)? as usize;
+    let records: Vec<[u16; 2]> = (0..count)
+        .filter_map(|i| Some([map.get(read_u16(vorg, 8 + i * 4)?)?, read_u16(vorg, 10 + i * 4)?]))
+        .collect();
+
+    let mut out = vorg.get(..6)?.to_vec();
+    out.extend_from_slice(&(records.len() as u16).to_be_bytes());
+    for value in records.into_iter().flatten() {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font::{cs_int, opentype_cff, outline, push_u16, truetype, TestGlyph};
+
+    fn square(x: i16, y: i16, size: i16) -> TestGlyph {
+        TestGlyph::Simple(vec![vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)]])
+    }
+
+    /// ligaでグリフ1をグリフ5に置き換えるだけのGSUB
+    fn single_substitution_gsub() -> Vec<u8> {
+        let mut out = Vec::new();
+        push_u16s(
+            &mut out,
+            &[
+                // ヘッダー
+                1, 0, 10, 30, 44,
+                // ScriptList（DFLTの既定のLangSysがフィーチャー0を使う）
+                1, 0x4446, 0x4C54, 8, 4, 0, 0, 0xFFFF, 1, 0,
+                // FeatureList（ligaがルックアップ0を使う）
+                1, 0x6C69, 0x6761, 8, 0, 1, 0,
+                // LookupList（単一置換 形式2、Coverage形式1）
+                1, 4, 1, 0, 1, 8, 2, 8, 1, 5, 1, 1, 1,
+            ],
+        );
+        out
+    }
+
+    fn push_u16s(out: &mut Vec<u8>, values: &[u16]) {
+        for &value in values {
+            push_u16(out, value);
+        }
+    }
+
+    fn subset(data: &[u8], text: &str) -> Vec<u8> {
+        let face = Face::parse(data, 0).unwrap();
+        let chars: Vec<char> = text.chars().collect();
+        subset_font(&face, &chars, &[], false).unwrap().to_sfnt()
+    }
+
+    #[test]
+    fn renumbers_truetype_glyphs() {
+        let glyphs = [
+            TestGlyph::Simple(vec![]),
+            square(0, 0, 100),
+            TestGlyph::Simple(vec![vec![(0, 0), (100, 0), (50, 80)]]),
+            // 割り当てのないグリフ4を参照する複合グリフ
+            TestGlyph::Composite(vec![(1, 0, 0), (4, 200, 300)]),
+            square(0, 0, 20),
+            square(10, 10, 50),
+        ];
+        let gsub = SfntTable { tag: *b"GSUB", data: single_substitution_gsub() };
+        let data = truetype(&glyphs, &[('A', 1), ('B', 2), ('C', 3)], vec![gsub]);
+        let original = Face::parse(&data, 0).unwrap();
+
+        let output = subset(&data, "AC");
+        let face = Face::parse(&output, 0).unwrap();
+
+        // .notdef・A・C・Cの部品・Aの置換先が元の順に残る
+        assert_eq!(face.number_of_glyphs(), 5);
+        assert_eq!(face.glyph_index('A'), Some(GlyphId(1)));
+        assert_eq!(face.glyph_index('C'), Some(GlyphId(2)));
+        assert_eq!(face.glyph_index('B'), None);
+        for (old, new) in [(1, 1), (3, 2), (4, 3), (5, 4)] {
+            assert_eq!(outline(&face, new), outline(&original, old));
+            assert_eq!(face.glyph_hor_advance(GlyphId(new)), original.glyph_hor_advance(GlyphId(old)));
+        }
+
+        let gsub = face.tables().gsub.unwrap();
+        let subtable = gsub.lookups.get(0).unwrap().subtables.get::<SubstitutionSubtable>(0).unwrap();
+        // 置換先との差が一定なので形式1で書かれる
+        let SubstitutionSubtable::Single(SingleSubstitution::Format1 { coverage, delta }) = subtable else {
+            panic!("unexpected subtable");
+        };
+        assert_eq!(coverage.get(GlyphId(1)), Some(0));
+        assert_eq!(delta, 3);
+    }
+
+    #[test]
+    fn reports_dropped_color_glyph_tables() {
+        let glyphs = [TestGlyph::Simple(vec![]), square(0, 0, 100)];
+        let extra_tables = [*b"CBDT", *b"CBLC", *b"EBDT", *b"sbix"]
+            .into_iter()
+            .map(|tag| SfntTable { tag, data: vec![0; 8] })
+            .collect();
+        let data = truetype(&glyphs, &[('A', 1)], extra_tables);
+        let face = Face::parse(&data, 0).unwrap();
+
+        let subset = subset_font(&face, &['A'], &[], false).unwrap();
+        // カラーではないビットマップ（EBDT）は警告の対象にしない
+        assert_eq!(subset.dropped_color_tables, ["CBDT", "CBLC", "sbix"]);
+        assert!(subset.tables.iter().all(|table| !DROPPED_TABLES.contains(&&table.tag)));
+    }
+
+    #[test]
+    fn renumbers_cff_glyphs_and_empties_unused_subroutines() {
+        // グリフBだけが呼ぶローカルサブルーチン（50 60 rlineto -50 0 rlineto return）
+        let unused_subr = [cs_int(50), cs_int(60), 5, cs_int(-50), cs_int(0), 5, 11];
+        let local_subrs: [&[u8]; 2] = [&[cs_int(100), cs_int(0), 5, cs_int(0), cs_int(100), 5, 11], &unused_subr];
+        let global_subrs: [&[u8]; 1] = [&[cs_int(30), cs_int(40), 5, cs_int(-30), cs_int(0), 5, 11]];
+        let char_strings: [&[u8]; 4] = [
+            &[14],
+            &[cs_int(0), cs_int(0), 21, cs_int(-107), 10, 14],
+            &[cs_int(0), cs_int(0), 21, cs_int(-106), 10, 14],
+            // hstemとhintmaskの後にグローバルサブルーチンを呼ぶ
+            &[cs_int(0), cs_int(10), 1, 19, 0x80, cs_int(0), cs_int(0), 21, cs_int(-107), 29, 14],
+        ];
+        let data = opentype_cff(&char_strings, &global_subrs, &local_subrs, &[('A', 1), ('B', 2), ('C', 3)]);
+        let original = Face::parse(&data, 0).unwrap();
+
+        let output = subset(&data, "AC");
+        let face = Face::parse(&output, 0).unwrap();
+
+        assert_eq!(face.number_of_glyphs(), 3);
+        assert_eq!(face.glyph_index('C'), Some(GlyphId(2)));
+        assert_eq!(face.glyph_index('B'), None);
+        for (old, new) in [(1, 1), (3, 2)] {
+            assert!(!outline(&face, new).is_empty());
+            assert_eq!(outline(&face, new), outline(&original, old));
+            assert_eq!(face.glyph_hor_advance(GlyphId(new)), original.glyph_hor_advance(GlyphId(old)));
+        }
+        assert!(data.windows(unused_subr.len()).any(|w| w == unused_subr));
+        assert!(!output.windows(unused_subr.len()).any(|w| w == unused_subr));
+    }
+
+    /// サブテーブル1つのcmapにしてttf-parserで引く
+    fn lookup(subtable: &[u8], encoding: u16, cp: u32) -> Option<u16> {
+        let mut cmap = Vec::new();
+        push_u16s(&mut cmap, &[0, 1, 3, encoding, 0, 12]);
+        cmap.extend_from_slice(subtable);
+        let table = ttf_parser::cmap::Table::parse(&cmap).unwrap();
+        let subtable = table.subtables.get(0).unwrap();
+        subtable.glyph_index(cp).map(|g| g.0).filter(|&g| g != 0)
+    }
+
+    fn test_mapping() -> BTreeMap<u32, u16> {
+        let mut mapping = BTreeMap::new();
+        // idDeltaで表せる連続した割り当て
+        mapping.extend((0x41..=0x5A).map(|cp| (cp, cp as u16 - 0x40)));
+        // 近い文字を0で埋めてまとめるセグメント（グリフは不連続）
+        mapping.extend([(0x3042, 40), (0x3044, 30), (0x3048, 50)]);
+        // グリフIDが文字より小さい（idDeltaが負）割り当てとBMPの末尾
+        mapping.extend([(0xFF01, 3), (0xFFFD, 60)]);
+        // 形式12だけに入る文字
+        mapping.extend([(0x1F600, 70), (0x1F601, 71), (0x20000, 80)]);
+        mapping
+    }
+
+    #[test]
+    fn cmap_format4_round_trips_bmp_mapping() {
+        let mapping = test_mapping();
+        let subtable = cmap_format4(&mapping).unwrap();
+        for cp in 0..0x10000 {
+            let expected = mapping.get(&cp).copied().filter(|_| cp < 0xFFFF);
+            assert_eq!(lookup(&subtable, 1, cp), expected, "U+{:04X}", cp);
+        }
+    }
+
+    #[test]
+    fn cmap_format12_round_trips_mapping() {
+        let mapping = test_mapping();
+        let subtable = cmap_format12(&mapping);
+        for cp in (0..0x10000).chain(0x1F5F0..0x1F610).chain([0x20000, 0x20001]) {
+            assert_eq!(lookup(&subtable, 10, cp), mapping.get(&cp).copied(), "U+{:04X}", cp);
+        }
+    }
+}
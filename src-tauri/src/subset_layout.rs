@@ -0,0 +1,1070 @@
+//! サブセットでグリフIDを付け替えたフォントのGSUB・GPOS・GDEFを作る
+//! 残さないグリフの項目を除いてグリフIDを書き換える（ScriptList・FeatureList・ルックアップの番号はそのまま）
+
+use std::collections::HashMap;
+
+use crate::sfnt::{item_variation_store_len, read_u16, read_u32};
+use crate::subset::GlyphMap;
+
+/// 拡張サブテーブルのルックアップの種類
+const GSUB_EXTENSION: u16 = 7;
+const GPOS_EXTENSION: u16 = 9;
+/// LookupFlagのUSE_MARK_FILTERING_SET
+const USE_MARK_FILTERING_SET: u16 = 0x0010;
+
+/// 子テーブルへのオフセットを持つテーブルを書く
+/// 子テーブルは本体の後ろに並べ、同じ内容のものは1つにまとめる
+#[derive(Default)]
+struct Writer {
+    data: Vec<u8>,
+    /// (オフセットを書く位置, 4バイトのオフセットか, 子テーブル)
+    links: Vec<(usize, bool, Vec<u8>)>,
+}
+
+impl Writer {
+    fn u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u16s(&mut self, values: &[u16]) {
+        for &value in values {
+            self.u16(value);
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// 16ビットのオフセット（子テーブルが無ければNULL）
+    fn offset16(&mut self, child: Option<Vec<u8>>) {
+        if let Some(child) = child {
+            self.links.push((self.data.len(), false, child));
+        }
+        self.u16(0);
+    }
+
+    /// 32ビットのオフセット（子テーブルが無ければNULL）
+    fn offset32(&mut self, child: Option<Vec<u8>>) {
+        if let Some(child) = child {
+            self.links.push((self.data.len(), true, child));
+        }
+        self.bytes(&[0; 4]);
+    }
+
+    /// 子テーブルを並べてオフセットを埋める（16ビットのオフセットに収まらなければNone）
+    fn finish(self) -> Option<Vec<u8>> {
+        let mut out = self.data;
+        let mut placed: HashMap<Vec<u8>, usize> = HashMap::new();
+        for (position, long, child) in self.links {
+            let offset = match placed.get(&child) {
+                Some(&offset) => offset,
+                None => {
+                    let offset = out.len();
+                    out.extend_from_slice(&child);
+                    placed.insert(child, offset);
+                    offset
+                }
+            };
+            if long {
+                out[position..position + 4].copy_from_slice(&(offset as u32).to_be_bytes());
+            } else {
+                out[position..position + 2].copy_from_slice(&u16::try_from(offset).ok()?.to_be_bytes());
+            }
+        }
+        Some(out)
+    }
+}
+
+fn push_u16s(out: &mut Vec<u8>, values: &[u16]) {
+    for value in values {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// posの16ビットオフセットが指すデータ（NULLならNone）
+fn linked(parent: &[u8], pos: usize) -> Option<&[u8]> {
+    match read_u16(parent, pos)? {
+        0 => None,
+        offset => parent.get(offset as usize..),
+    }
+}
+
+/// posの32ビットオフセットが指すデータ（NULLならNone）
+fn linked32(parent: &[u8], pos: usize) -> Option<&[u8]> {
+    match read_u32(parent, pos)? {
+        0 => None,
+        offset => parent.get(offset as usize..),
+    }
+}
+
+/// GSUBのグリフIDを付け替える
+pub fn subset_gsub(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    subset_layout(data, map, GSUB_EXTENSION, gsub_subtable)
+}
+
+/// GPOSのグリフIDを付け替える
+pub fn subset_gpos(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    subset_layout(data, map, GPOS_EXTENSION, gpos_subtable)
+}
+
+/// GDEFのグリフIDを付け替える（読めない項目は省く）
+pub fn subset_gdef(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let version = read_u32(data, 0)?;
+
+    let mut w = Writer::default();
+    w.bytes(&version.to_be_bytes());
+    w.offset16(linked(data, 4).and_then(|class_def| retained_class_def(class_def, map)));
+    w.offset16(linked(data, 6).and_then(|attach_list| subset_attach_list(attach_list, map)));
+    w.offset16(linked(data, 8).and_then(|lig_caret_list| subset_lig_caret_list(lig_caret_list, map)));
+    w.offset16(linked(data, 10).and_then(|class_def| retained_class_def(class_def, map)));
+    if version >= 0x0001_0002 {
+        w.offset16(linked(data, 12).and_then(|mark_glyph_sets| subset_mark_glyph_sets(mark_glyph_sets, map)));
+    }
+    if version >= 0x0001_0003 {
+        let store = linked32(data, 14).and_then(|store| store.get(..item_variation_store_len(store)?));
+        w.offset32(store.map(<[u8]>::to_vec));
+    }
+    w.finish()
+}
+
+type SubtableFn = fn(u16, &[u8], &GlyphMap) -> Option<Vec<u8>>;
+
+/// GSUB・GPOS共通の構造（ヘッダー・ScriptList・FeatureList・FeatureVariations）を書き直す
+fn subset_layout(data: &[u8], map: &GlyphMap, extension: u16, subtable: SubtableFn) -> Option<Vec<u8>> {
+    let minor_version = read_u16(data, 2)?;
+    let lookups = match linked(data, 8) {
+        Some(lookup_list) => read_lookups(lookup_list, map, extension, subtable)?,
+        None => Vec::new(),
+    };
+
+    let mut w = Writer::default();
+    w.u16s(&[1, minor_version.min(1)]);
+    w.offset16(Some(copy_script_list(linked(data, 4)?)?));
+    w.offset16(Some(copy_feature_list(linked(data, 6)?)?));
+    w.offset16(Some(write_lookup_list(&lookups, extension)?));
+    if minor_version >= 1 {
+        w.offset32(linked32(data, 10).and_then(copy_feature_variations));
+    }
+    w.finish()
+}
+
+fn copy_script_list(data: &[u8]) -> Option<Vec<u8>> {
+    let count = read_u16(data, 0)?;
+    let mut w = Writer::default();
+    w.u16(count);
+    for i in 0..count as usize {
+        w.bytes(data.get(2 + i * 6..6 + i * 6)?);
+        w.offset16(Some(copy_script(linked(data, 6 + i * 6)?)?));
+    }
+    w.finish()
+}
+
+fn copy_script(data: &[u8]) -> Option<Vec<u8>> {
+    let count = read_u16(data, 2)?;
+    let mut w = Writer::default();
+    w.offset16(linked(data, 0).and_then(copy_lang_sys));
+    w.u16(count);
+    for i in 0..count as usize {
+        w.bytes(data.get(4 + i * 6..8 + i * 6)?);
+        w.offset16(Some(copy_lang_sys(linked(data, 8 + i * 6)?)?));
+    }
+    w.finish()
+}
+
+fn copy_lang_sys(data: &[u8]) -> Option<Vec<u8>> {
+    let count = read_u16(data, 4)? as usize;
+    data.get(..6 + count * 2).map(<[u8]>::to_vec)
+}
+
+fn copy_feature_list(data: &[u8]) -> Option<Vec<u8>> {
+    let count = read_u16(data, 0)?;
+    let mut w = Writer::default();
+    w.u16(count);
+    for i in 0..count as usize {
+        let tag = data.get(2 + i * 6..6 + i * 6)?;
+        w.bytes(tag);
+        w.offset16(Some(copy_feature(linked(data, 6 + i * 6)?, tag)?));
+    }
+    w.finish()
+}
+
+fn copy_feature(data: &[u8], tag: &[u8]) -> Option<Vec<u8>> {
+    let count = read_u16(data, 2)? as usize;
+    let mut w = Writer::default();
+    w.offset16(linked(data, 0).and_then(|params| feature_params(params, tag)));
+    w.bytes(data.get(2..4 + count * 2)?);
+    w.finish()
+}
+
+/// FeatureParams（長さはフィーチャーの種類で決まる）
+fn feature_params(data: &[u8], tag: &[u8]) -> Option<Vec<u8>> {
+    let length = match tag {
+        b"size" => 10,
+        [b's', b's', ..] => 4,
+        [b'c', b'v', ..] => 14 + read_u16(data, 12)? as usize * 3,
+        _ => return None,
+    };
+    data.get(..length).map(<[u8]>::to_vec)
+}
+
+fn copy_feature_variations(data: &[u8]) -> Option<Vec<u8>> {
+    let count = read_u32(data, 4)? as usize;
+    let mut w = Writer::default();
+    w.bytes(data.get(..8)?);
+    for i in 0..count {
+        w.offset32(linked32(data, 8 + i * 8).and_then(copy_condition_set));
+        w.offset32(linked32(data, 12 + i * 8).and_then(copy_feature_substitution));
+    }
+    w.finish()
+}
+
+fn copy_condition_set(data: &[u8]) -> Option<Vec<u8>> {
+    let count = read_u16(data, 0)?;
+    let mut w = Writer::default();
+    w.u16(count);
+    for i in 0..count as usize {
+        let condition = linked32(data, 2 + i * 4)?;
+        // 形式1（軸の範囲）だけが定義されている
+        if read_u16(condition, 0)? != 1 {
+            return None;
+        }
+        w.offset32(Some(condition.get(..8)?.to_vec()));
+    }
+    w.finish()
+}
+
+fn copy_feature_substitution(data: &[u8]) -> Option<Vec<u8>> {
+    let count = read_u16(data, 4)?;
+    let mut w = Writer::default();
+    w.bytes(data.get(..6)?);
+    for i in 0..count as usize {
+        w.u16(read_u16(data, 6 + i * 6)?);
+        w.offset32(Some(copy_feature(linked32(data, 8 + i * 6)?, &[])?));
+    }
+    w.finish()
+}
+
+struct Lookup {
+    /// 拡張サブテーブルを外した種類
+    kind: u16,
+    flag: u16,
+    subtables: Vec<Vec<u8>>,
+    mark_filtering_set: Option<u16>,
+}
+
+fn read_lookups(lookup_list: &[u8], map: &GlyphMap, extension: u16, subtable: SubtableFn) -> Option<Vec<Lookup>> {
+    let count = read_u16(lookup_list, 0)? as usize;
+    (0..count)
+        .map(|i| {
+            let lookup = linked(lookup_list, 2 + i * 2)?;
+            let mut kind = read_u16(lookup, 0)?;
+            let flag = read_u16(lookup, 2)?;
+            let subtable_count = read_u16(lookup, 4)? as usize;
+
+            let mut subtables = Vec::with_capacity(subtable_count);
+            for j in 0..subtable_count {
+                let mut data = linked(lookup, 6 + j * 2)?;
+                if read_u16(lookup, 0)? == extension {
+                    kind = read_u16(data, 2)?;
+                    data = data.get(read_u32(data, 4)? as usize..)?;
+                }
+                subtables.push(subtable(kind, data, map)?);
+            }
+
+            let mark_filtering_set = match flag & USE_MARK_FILTERING_SET {
+                0 => None,
+                _ => Some(read_u16(lookup, 6 + subtable_count * 2)?),
+            };
+            Some(Lookup {
+                kind,
+                flag,
+                subtables,
+                mark_filtering_set,
+            })
+        })
+        .collect()
+}
+
+fn write_lookup_list(lookups: &[Lookup], extension: u16) -> Option<Vec<u8>> {
+    let direct = || {
+        let mut list = Writer::default();
+        list.u16(lookups.len() as u16);
+        for lookup in lookups {
+            let mut w = Writer::default();
+            w.u16s(&[lookup.kind, lookup.flag, lookup.subtables.len() as u16]);
+            for subtable in &lookup.subtables {
+                w.offset16(Some(subtable.clone()));
+            }
+            if let Some(set) = lookup.mark_filtering_set {
+                w.u16(set);
+            }
+            list.offset16(Some(w.finish()?));
+        }
+        list.finish()
+    };
+    direct().or_else(|| write_extension_lookup_list(lookups, extension))
+}
+
+/// 16ビットのオフセットに収まらないときは、すべてのサブテーブルを拡張サブテーブル経由で参照する
+/// LookupListの後ろに各ルックアップと拡張サブテーブルを置き、サブテーブルの本体は末尾にまとめる
+fn write_extension_lookup_list(lookups: &[Lookup], extension: u16) -> Option<Vec<u8>> {
+    let header_size = |lookup: &Lookup| {
+        6 + lookup.subtables.len() * 2 + if lookup.mark_filtering_set.is_some() { 2 } else { 0 }
+    };
+
+    let mut out = Vec::new();
+    push_u16s(&mut out, &[lookups.len() as u16]);
+    let mut offset = 2 + lookups.len() * 2;
+    for lookup in lookups {
+        push_u16s(&mut out, &[u16::try_from(offset).ok()?]);
+        offset += header_size(lookup) + lookup.subtables.len() * 8;
+    }
+
+    // offsetはサブテーブルの本体の開始位置になっている
+    for lookup in lookups {
+        let header_size = header_size(lookup);
+        push_u16s(&mut out, &[extension, lookup.flag, lookup.subtables.len() as u16]);
+        for j in 0..lookup.subtables.len() {
+            push_u16s(&mut out, &[u16::try_from(header_size + j * 8).ok()?]);
+        }
+        if let Some(set) = lookup.mark_filtering_set {
+            push_u16s(&mut out, &[set]);
+        }
+        for subtable in &lookup.subtables {
+            let relative = offset - out.len();
+            push_u16s(&mut out, &[1, lookup.kind]);
+            out.extend_from_slice(&u32::try_from(relative).ok()?.to_be_bytes());
+            offset += subtable.len();
+        }
+    }
+    for subtable in lookups.iter().flat_map(|lookup| &lookup.subtables) {
+        out.extend_from_slice(subtable);
+    }
+    Some(out)
+}
+
+fn gsub_subtable(kind: u16, data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    match kind {
+        1 => single_substitution(data, map),
+        2 => sequence_substitution(data, map, true),
+        3 => sequence_substitution(data, map, false),
+        4 => ligature_substitution(data, map),
+        5 => sequence_context(data, map),
+        6 => chained_sequence_context(data, map),
+        8 => reverse_chain_substitution(data, map),
+        _ => None,
+    }
+}
+
+fn gpos_subtable(kind: u16, data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    match kind {
+        1 => single_positioning(data, map),
+        2 => pair_positioning(data, map),
+        3 => cursive_positioning(data, map),
+        4 | 6 => mark_positioning(data, map),
+        5 => mark_to_ligature_positioning(data, map),
+        7 => sequence_context(data, map),
+        8 => chained_sequence_context(data, map),
+        _ => None,
+    }
+}
+
+/// Coverageのグリフ（Coverage番号の順）
+fn read_coverage(data: &[u8]) -> Option<Vec<u16>> {
+    let count = read_u16(data, 2)? as usize;
+    match read_u16(data, 0)? {
+        1 => (0..count).map(|i| read_u16(data, 4 + i * 2)).collect(),
+        2 => {
+            let mut glyphs = Vec::new();
+            for i in 0..count {
+                glyphs.extend(read_u16(data, 4 + i * 6)?..=read_u16(data, 6 + i * 6)?);
+            }
+            Some(glyphs)
+        }
+        _ => None,
+    }
+}
+
+/// 残すグリフのCoverageを(新しいグリフID, 元のCoverage番号)で返す
+fn retained_coverage(data: &[u8], map: &GlyphMap) -> Option<Vec<(u16, usize)>> {
+    let glyphs = read_coverage(data)?;
+    Some(
+        glyphs
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, glyph)| Some((map.get(glyph)?, index)))
+            .collect(),
+    )
+}
+
+/// 残すグリフだけのCoverage
+fn subset_coverage(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let glyphs: Vec<u16> = read_coverage(data)?.into_iter().filter_map(|g| map.get(g)).collect();
+    Some(write_coverage(&glyphs))
+}
+
+/// 連続するグリフで値が同じものを(開始, 終了, 値)の範囲にまとめる
+fn runs(items: impl IntoIterator<Item = (u16, u16)>) -> Vec<(u16, u16, u16)> {
+    let mut runs: Vec<(u16, u16, u16)> = Vec::new();
+    for (glyph, value) in items {
+        match runs.last_mut() {
+            Some((_, end, last)) if glyph.checked_sub(1) == Some(*end) && *last == value => *end = glyph,
+            _ => runs.push((glyph, glyph, value)),
+        }
+    }
+    runs
+}
+
+/// Coverageを形式1・2のうち小さい方で書く
+fn write_coverage(glyphs: &[u16]) -> Vec<u8> {
+    let ranges = runs(glyphs.iter().map(|&glyph| (glyph, 0)));
+    let mut out = Vec::new();
+    if ranges.len() * 3 < glyphs.len() {
+        push_u16s(&mut out, &[2, ranges.len() as u16]);
+        let mut index = 0;
+        for (start, end, _) in ranges {
+            push_u16s(&mut out, &[start, end, index]);
+            index += end - start + 1;
+        }
+    } else {
+        push_u16s(&mut out, &[1, glyphs.len() as u16]);
+        push_u16s(&mut out, glyphs);
+    }
+    out
+}
+
+fn coverage_glyphs(coverage: &[(u16, usize)]) -> Vec<u8> {
+    let glyphs: Vec<u16> = coverage.iter().map(|&(glyph, _)| glyph).collect();
+    write_coverage(&glyphs)
+}
+
+/// ClassDefの(グリフID, クラス)（クラス0は含まない）
+fn read_class_def(data: &[u8]) -> Option<Vec<(u16, u16)>> {
+    match read_u16(data, 0)? {
+        1 => {
+            let start = read_u16(data, 2)?;
+            let count = read_u16(data, 4)? as usize;
+            (0..count)
+                .map(|i| Some((start.checked_add(i as u16)?, read_u16(data, 6 + i * 2)?)))
+                .collect()
+        }
+        2 => {
+            let count = read_u16(data, 2)? as usize;
+            let mut classes = Vec::new();
+            for i in 0..count {
+                let class = read_u16(data, 8 + i * 6)?;
+                classes.extend((read_u16(data, 4 + i * 6)?..=read_u16(data, 6 + i * 6)?).map(|g| (g, class)));
+            }
+            Some(classes)
+        }
+        _ => None,
+    }
+}
+
+/// 残すグリフだけのClassDef（クラスの番号は変えない）
+fn retained_class_def(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let mut classes: Vec<(u16, u16)> = read_class_def(data)?
+        .into_iter()
+        .filter(|&(_, class)| class != 0)
+        .filter_map(|(glyph, class)| Some((map.get(glyph)?, class)))
+        .collect();
+    classes.sort_unstable();
+    Some(write_class_def(&classes))
+}
+
+/// ClassDefを形式1・2のうち小さい方で書く
+fn write_class_def(classes: &[(u16, u16)]) -> Vec<u8> {
+    let ranges = runs(classes.iter().copied());
+    let span = match (classes.first(), classes.last()) {
+        (Some(first), Some(last)) => (last.0 - first.0) as usize + 1,
+        _ => 0,
+    };
+
+    let mut out = Vec::new();
+    if span > 0 && 6 + span * 2 <= 4 + ranges.len() * 6 {
+        let first = classes[0].0;
+        let mut values = vec![0; span];
+        for &(glyph, class) in classes {
+            values[(glyph - first) as usize] = class;
+        }
+        push_u16s(&mut out, &[1, first, span as u16]);
+        push_u16s(&mut out, &values);
+    } else {
+        push_u16s(&mut out, &[2, ranges.len() as u16]);
+        for (start, end, class) in ranges {
+            push_u16s(&mut out, &[start, end, class]);
+        }
+    }
+    out
+}
+
+/// posのオフセットが指すDevice（VariationIndex）テーブル（NULLや読めない場合はNone）
+fn device(parent: &[u8], pos: usize) -> Option<Vec<u8>> {
+    let data = linked(parent, pos)?;
+    let length = match read_u16(data, 4)? {
+        format @ 1..=3 => {
+            let count = read_u16(data, 2)?.checked_sub(read_u16(data, 0)?)? as usize + 1;
+            // 値1つが2・4・8ビット
+            6 + (count << format).div_ceil(16) * 2
+        }
+        0x8000 => 6,
+        _ => return None,
+    };
+    data.get(..length).map(<[u8]>::to_vec)
+}
+
+/// posのオフセットが指すAnchor（NULLや読めない場合はNone）
+fn anchor(parent: &[u8], pos: usize) -> Option<Vec<u8>> {
+    let data = linked(parent, pos)?;
+    match read_u16(data, 0)? {
+        1 => data.get(..6).map(<[u8]>::to_vec),
+        2 => data.get(..8).map(<[u8]>::to_vec),
+        3 => {
+            let mut w = Writer::default();
+            w.bytes(data.get(..6)?);
+            w.offset16(device(data, 6));
+            w.offset16(device(data, 8));
+            w.finish()
+        }
+        _ => None,
+    }
+}
+
+fn value_record_size(format: u16) -> usize {
+    (format & 0xFF).count_ones() as usize * 2
+}
+
+/// parentのposにあるValueRecordを写す（Deviceのオフセットはparentの先頭から）
+fn copy_value_record(w: &mut Writer, parent: &[u8], pos: usize, format: u16) -> Option<()> {
+    let mut pos = pos;
+    for bit in 0..8 {
+        if format & (1 << bit) == 0 {
+            continue;
+        }
+        // 下位4ビットは値、上位4ビットはDeviceへのオフセット
+        if bit < 4 {
+            w.u16(read_u16(parent, pos)?);
+        } else {
+            w.offset16(device(parent, pos));
+        }
+        pos += 2;
+    }
+    Some(())
+}
+
+fn single_substitution(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let coverage = read_coverage(linked(data, 2)?)?;
+    let substitutions: Vec<(u16, u16)> = match read_u16(data, 0)? {
+        1 => {
+            let delta = read_u16(data, 4)?;
+            coverage.iter().map(|&glyph| (glyph, glyph.wrapping_add(delta))).collect()
+        }
+        2 => coverage
+            .iter()
+            .enumerate()
+            .map(|(i, &glyph)| Some((glyph, read_u16(data, 6 + i * 2)?)))
+            .collect::<Option<_>>()?,
+        _ => return None,
+    };
+    let substitutions: Vec<(u16, u16)> = substitutions
+        .into_iter()
+        .filter_map(|(glyph, substitute)| Some((map.get(glyph)?, map.get(substitute)?)))
+        .collect();
+
+    let glyphs: Vec<u16> = substitutions.iter().map(|&(glyph, _)| glyph).collect();
+    let delta = substitutions.first().map(|&(glyph, substitute)| substitute.wrapping_sub(glyph));
+    let mut w = Writer::default();
+    match delta.filter(|&delta| substitutions.iter().all(|&(g, s)| s.wrapping_sub(g) == delta)) {
+        Some(delta) => {
+            w.u16(1);
+            w.offset16(Some(write_coverage(&glyphs)));
+            w.u16(delta);
+        }
+        None => {
+            w.u16(2);
+            w.offset16(Some(write_coverage(&glyphs)));
+            w.u16(substitutions.len() as u16);
+            for (_, substitute) in substitutions {
+                w.u16(substitute);
+            }
+        }
+    }
+    w.finish()
+}
+
+/// 複数置換・選択置換（置換先のグリフ列を持つもの）
+/// 複数置換は置換先がすべて残るものだけ、選択置換は残る候補だけにする
+fn sequence_substitution(data: &[u8], map: &GlyphMap, all_required: bool) -> Option<Vec<u8>> {
+    let coverage = read_coverage(linked(data, 2)?)?;
+    let mut glyphs = Vec::new();
+    let mut sequences = Vec::new();
+    for (i, glyph) in coverage.into_iter().enumerate() {
+        let Some(glyph) = map.get(glyph) else {
+            continue;
+        };
+        let sequence = linked(data, 6 + i * 2)?;
+        let count = read_u16(sequence, 0)? as usize;
+        let substitutes = (0..count)
+            .map(|j| read_u16(sequence, 2 + j * 2))
+            .collect::<Option<Vec<u16>>>()?;
+        let retained: Vec<u16> = substitutes.into_iter().filter_map(|g| map.get(g)).collect();
+        if (all_required && retained.len() < count) || (!all_required && retained.is_empty()) {
+            continue;
+        }
+
+        let mut out = Vec::new();
+        push_u16s(&mut out, &[retained.len() as u16]);
+        push_u16s(&mut out, &retained);
+        glyphs.push(glyph);
+        sequences.push(out);
+    }
+
+    let mut w = Writer::default();
+    w.u16(1);
+    w.offset16(Some(write_coverage(&glyphs)));
+    w.u16(sequences.len() as u16);
+    for sequence in sequences {
+        w.offset16(Some(sequence));
+    }
+    w.finish()
+}
+
+/// 合字置換（合字と構成グリフがすべて残るものだけ）
+fn ligature_substitution(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let coverage = read_coverage(linked(data, 2)?)?;
+    let mut glyphs = Vec::new();
+    let mut sets = Vec::new();
+    for (i, glyph) in coverage.into_iter().enumerate() {
+        let Some(glyph) = map.get(glyph) else {
+            continue;
+        };
+        let set = linked(data, 6 + i * 2)?;
+        let count = read_u16(set, 0)? as usize;
+
+        let mut ligatures = Vec::new();
+        for j in 0..count {
+            let ligature = linked(set, 2 + j * 2)?;
+            let component_count = read_u16(ligature, 2)? as usize;
+            // 合字のグリフと、2番目以降の構成グリフ
+            let ids = std::iter::once(0)
+                .chain((1..component_count).map(|k| 2 + k * 2))
+                .map(|pos| read_u16(ligature, pos))
+                .collect::<Option<Vec<u16>>>()?;
+            let Some(ids) = ids.into_iter().map(|g| map.get(g)).collect::<Option<Vec<u16>>>() else {
+                continue;
+            };
+            let mut out = Vec::new();
+            push_u16s(&mut out, &[ids[0], component_count as u16]);
+            push_u16s(&mut out, &ids[1..]);
+            ligatures.push(out);
+        }
+        if ligatures.is_empty() {
+            continue;
+        }
+
+        let mut w = Writer::default();
+        w.u16(ligatures.len() as u16);
+        for ligature in ligatures {
+            w.offset16(Some(ligature));
+        }
+        glyphs.push(glyph);
+        sets.push(w.finish()?);
+    }
+
+    let mut w = Writer::default();
+    w.u16(1);
+    w.offset16(Some(write_coverage(&glyphs)));
+    w.u16(sets.len() as u16);
+    for set in sets {
+        w.offset16(Some(set));
+    }
+    w.finish()
+}
+
+fn reverse_chain_substitution(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let coverage = read_coverage(linked(data, 2)?)?;
+    let backtrack_count = read_u16(data, 4)? as usize;
+    let lookahead_pos = 6 + backtrack_count * 2;
+    let lookahead_count = read_u16(data, lookahead_pos)? as usize;
+    let substitutes_pos = lookahead_pos + 4 + lookahead_count * 2;
+
+    let mut substitutions = Vec::new();
+    for (i, glyph) in coverage.into_iter().enumerate() {
+        let substitute = read_u16(data, substitutes_pos + i * 2)?;
+        if let (Some(glyph), Some(substitute)) = (map.get(glyph), map.get(substitute)) {
+            substitutions.push((glyph, substitute));
+        }
+    }
+    let glyphs: Vec<u16> = substitutions.iter().map(|&(glyph, _)| glyph).collect();
+
+    let mut w = Writer::default();
+    w.u16(1);
+    w.offset16(Some(write_coverage(&glyphs)));
+    w.u16(backtrack_count as u16);
+    for i in 0..backtrack_count {
+        w.offset16(Some(subset_coverage(linked(data, 6 + i * 2)?, map)?));
+    }
+    w.u16(lookahead_count as u16);
+    for i in 0..lookahead_count {
+        w.offset16(Some(subset_coverage(linked(data, lookahead_pos + 2 + i * 2)?, map)?));
+    }
+    w.u16(substitutions.len() as u16);
+    for (_, substitute) in substitutions {
+        w.u16(substitute);
+    }
+    w.finish()
+}
+
+/// count個のグリフ（またはクラス）の列を写す（mapがあればグリフIDを付け替え、残さないグリフがあればNone）
+fn copy_sequence(out: &mut Vec<u8>, data: &[u8], pos: usize, count: usize, map: Option<&GlyphMap>) -> Option<()> {
+    for i in 0..count {
+        let value = read_u16(data, pos + i * 2)?;
+        let value = match map {
+            Some(map) => map.get(value)?,
+            None => value,
+        };
+        push_u16s(out, &[value]);
+    }
+    Some(())
+}
+
+/// SequenceRule（形式1はグリフ、形式2はクラスの列）
+fn sequence_rule(data: &[u8], map: Option<&GlyphMap>) -> Option<Vec<u8>> {
+    let glyph_count = read_u16(data, 0)? as usize;
+    let lookup_count = read_u16(data, 2)? as usize;
+    let input_count = glyph_count.checked_sub(1)?;
+
+    let mut out = data.get(..4)?.to_vec();
+    copy_sequence(&mut out, data, 4, input_count, map)?;
+    let records = 4 + input_count * 2;
+    out.extend_from_slice(data.get(records..records + lookup_count * 4)?);
+    Some(out)
+}
+
+/// ChainedSequenceRule（形式1はグリフ、形式2はクラスの列）
+fn chained_sequence_rule(data: &[u8], map: Option<&GlyphMap>) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    // 前方・入力（先頭のグリフを除く）・後方の列
+    for part in 0..3 {
+        let count = read_u16(data, pos)? as usize;
+        let length = if part == 1 { count.checked_sub(1)? } else { count };
+        push_u16s(&mut out, &[count as u16]);
+        copy_sequence(&mut out, data, pos + 2, length, map)?;
+        pos += 2 + length * 2;
+    }
+    let lookup_count = read_u16(data, pos)? as usize;
+    out.extend_from_slice(data.get(pos..pos + 2 + lookup_count * 4)?);
+    Some(out)
+}
+
+/// 規則の集合（読めない規則や残さないグリフを含む規則は除き、残る規則が無ければNone）
+fn rule_set(data: &[u8], rule: impl Fn(&[u8]) -> Option<Vec<u8>>) -> Option<Vec<u8>> {
+    let count = read_u16(data, 0)? as usize;
+    let rules: Vec<Vec<u8>> = (0..count).filter_map(|i| rule(linked(data, 2 + i * 2)?)).collect();
+    if rules.is_empty() {
+        return None;
+    }
+
+    let mut w = Writer::default();
+    w.u16(rules.len() as u16);
+    for rule in rules {
+        w.offset16(Some(rule));
+    }
+    w.finish()
+}
+
+/// 文脈置換・文脈位置調整（GSUBの5、GPOSの7）
+fn sequence_context(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let mut w = Writer::default();
+    match read_u16(data, 0)? {
+        1 => {
+            let coverage = retained_coverage(linked(data, 2)?, map)?;
+            w.u16(1);
+            w.offset16(Some(coverage_glyphs(&coverage)));
+            w.u16(coverage.len() as u16);
+            for &(_, index) in &coverage {
+                let set = linked(data, 6 + index * 2);
+                w.offset16(set.and_then(|set| rule_set(set, |rule| sequence_rule(rule, Some(map)))));
+            }
+        }
+        2 => {
+            let coverage = retained_coverage(linked(data, 2)?, map)?;
+            let set_count = read_u16(data, 6)?;
+            w.u16(2);
+            w.offset16(Some(coverage_glyphs(&coverage)));
+            w.offset16(Some(retained_class_def(linked(data, 4)?, map)?));
+            w.u16(set_count);
+            for i in 0..set_count as usize {
+                let set = linked(data, 8 + i * 2);
+                w.offset16(set.and_then(|set| rule_set(set, |rule| sequence_rule(rule, None))));
+            }
+        }
+        3 => {
+            let glyph_count = read_u16(data, 2)? as usize;
+            let lookup_count = read_u16(data, 4)? as usize;
+            w.u16s(&[3, glyph_count as u16, lookup_count as u16]);
+            for i in 0..glyph_count {
+                w.offset16(Some(subset_coverage(linked(data, 6 + i * 2)?, map)?));
+            }
+            let records = 6 + glyph_count * 2;
+            w.bytes(data.get(records..records + lookup_count * 4)?);
+        }
+        _ => return None,
+    }
+    w.finish()
+}
+
+/// 前後の文脈付きの置換・位置調整（GSUBの6、GPOSの8）
+fn chained_sequence_context(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let mut w = Writer::default();
+    match read_u16(data, 0)? {
+        1 => {
+            let coverage = retained_coverage(linked(data, 2)?, map)?;
+            w.u16(1);
+            w.offset16(Some(coverage_glyphs(&coverage)));
+            w.u16(coverage.len() as u16);
+            for &(_, index) in &coverage {
+                let set = linked(data, 6 + index * 2);
+                w.offset16(set.and_then(|set| rule_set(set, |rule| chained_sequence_rule(rule, Some(map)))));
+            }
+        }
+        2 => {
+            let coverage = retained_coverage(linked(data, 2)?, map)?;
+            let set_count = read_u16(data, 10)?;
+            w.u16(2);
+            w.offset16(Some(coverage_glyphs(&coverage)));
+            // 前方・後方のClassDefは無いことがある
+            w.offset16(linked(data, 4).and_then(|class_def| retained_class_def(class_def, map)));
+            w.offset16(Some(retained_class_def(linked(data, 6)?, map)?));
+            w.offset16(linked(data, 8).and_then(|class_def| retained_class_def(class_def, map)));
+            w.u16(set_count);
+            for i in 0..set_count as usize {
+                let set = linked(data, 12 + i * 2);
+                w.offset16(set.and_then(|set| rule_set(set, |rule| chained_sequence_rule(rule, None))));
+            }
+        }
+        3 => {
+            w.u16(3);
+            let mut pos = 2;
+            for _ in 0..3 {
+                let count = read_u16(data, pos)? as usize;
+                w.u16(count as u16);
+                for i in 0..count {
+                    w.offset16(Some(subset_coverage(linked(data, pos + 2 + i * 2)?, map)?));
+                }
+                pos += 2 + count * 2;
+            }
+            let lookup_count = read_u16(data, pos)? as usize;
+            w.bytes(data.get(pos..pos + 2 + lookup_count * 4)?);
+        }
+        _ => return None,
+    }
+    w.finish()
+}
+
+fn single_positioning(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let format = read_u16(data, 0)?;
+    let coverage = retained_coverage(linked(data, 2)?, map)?;
+    let value_format = read_u16(data, 4)?;
+
+    let mut w = Writer::default();
+    w.u16(format);
+    w.offset16(Some(coverage_glyphs(&coverage)));
+    w.u16(value_format);
+    match format {
+        1 => copy_value_record(&mut w, data, 6, value_format)?,
+        2 => {
+            let size = value_record_size(value_format);
+            w.u16(coverage.len() as u16);
+            for &(_, index) in &coverage {
+                copy_value_record(&mut w, data, 8 + index * size, value_format)?;
+            }
+        }
+        _ => return None,
+    }
+    w.finish()
+}
+
+fn pair_positioning(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let format = read_u16(data, 0)?;
+    let coverage = retained_coverage(linked(data, 2)?, map)?;
+    let value_format1 = read_u16(data, 4)?;
+    let value_format2 = read_u16(data, 6)?;
+    let size1 = value_record_size(value_format1);
+    let size2 = value_record_size(value_format2);
+
+    let mut w = Writer::default();
+    w.u16(format);
+    w.offset16(Some(coverage_glyphs(&coverage)));
+    w.u16s(&[value_format1, value_format2]);
+    match format {
+        1 => {
+            w.u16(coverage.len() as u16);
+            for &(_, index) in &coverage {
+                let set = linked(data, 10 + index * 2)?;
+                let count = read_u16(set, 0)? as usize;
+                let records: Vec<(u16, usize)> = (0..count)
+                    .map(|j| 2 + j * (2 + size1 + size2))
+                    .filter_map(|pos| Some((map.get(read_u16(set, pos)?)?, pos)))
+                    .collect();
+
+                // PairSetのDeviceのオフセットはPairSetの先頭から
+                let mut set_writer = Writer::default();
+                set_writer.u16(records.len() as u16);
+                for (second_glyph, pos) in records {
+                    set_writer.u16(second_glyph);
+                    copy_value_record(&mut set_writer, set, pos + 2, value_format1)?;
+                    copy_value_record(&mut set_writer, set, pos + 2 + size1, value_format2)?;
+                }
+                w.offset16(Some(set_writer.finish()?));
+            }
+        }
+        2 => {
+            let class1_count = read_u16(data, 12)? as usize;
+            let class2_count = read_u16(data, 14)? as usize;
+            w.offset16(Some(retained_class_def(linked(data, 8)?, map)?));
+            w.offset16(Some(retained_class_def(linked(data, 10)?, map)?));
+            w.u16s(&[class1_count as u16, class2_count as u16]);
+            let mut pos = 16;
+            for _ in 0..class1_count * class2_count {
+                copy_value_record(&mut w, data, pos, value_format1)?;
+                copy_value_record(&mut w, data, pos + size1, value_format2)?;
+                pos += size1 + size2;
+            }
+        }
+        _ => return None,
+    }
+    w.finish()
+}
+
+fn cursive_positioning(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let coverage = retained_coverage(linked(data, 2)?, map)?;
+    let mut w = Writer::default();
+    w.u16(1);
+    w.offset16(Some(coverage_glyphs(&coverage)));
+    w.u16(coverage.len() as u16);
+    for &(_, index) in &coverage {
+        w.offset16(anchor(data, 6 + index * 4));
+        w.offset16(anchor(data, 8 + index * 4));
+    }
+    w.finish()
+}
+
+/// マークの基底字・マークへの結合（GPOSの4・6）
+fn mark_positioning(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let marks = retained_coverage(linked(data, 2)?, map)?;
+    let bases = retained_coverage(linked(data, 4)?, map)?;
+    let class_count = read_u16(data, 6)? as usize;
+    let base_rows: Vec<usize> = bases.iter().map(|&(_, index)| index).collect();
+
+    let mut w = Writer::default();
+    w.u16(1);
+    w.offset16(Some(coverage_glyphs(&marks)));
+    w.offset16(Some(coverage_glyphs(&bases)));
+    w.u16(class_count as u16);
+    w.offset16(Some(subset_mark_array(linked(data, 8)?, &marks)?));
+    w.offset16(Some(anchor_matrix(linked(data, 10)?, &base_rows, class_count)?));
+    w.finish()
+}
+
+fn mark_to_ligature_positioning(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let marks = retained_coverage(linked(data, 2)?, map)?;
+    let ligatures = retained_coverage(linked(data, 4)?, map)?;
+    let class_count = read_u16(data, 6)? as usize;
+    let ligature_array = linked(data, 10)?;
+
+    let mut array_writer = Writer::default();
+    array_writer.u16(ligatures.len() as u16);
+    for &(_, index) in &ligatures {
+        let attach = linked(ligature_array, 2 + index * 2)?;
+        let components: Vec<usize> = (0..read_u16(attach, 0)? as usize).collect();
+        array_writer.offset16(Some(anchor_matrix(attach, &components, class_count)?));
+    }
+
+    let mut w = Writer::default();
+    w.u16(1);
+    w.offset16(Some(coverage_glyphs(&marks)));
+    w.offset16(Some(coverage_glyphs(&ligatures)));
+    w.u16(class_count as u16);
+    w.offset16(Some(subset_mark_array(linked(data, 8)?, &marks)?));
+    w.offset16(Some(array_writer.finish()?));
+    w.finish()
+}
+
+fn subset_mark_array(data: &[u8], marks: &[(u16, usize)]) -> Option<Vec<u8>> {
+    let mut w = Writer::default();
+    w.u16(marks.len() as u16);
+    for &(_, index) in marks {
+        w.u16(read_u16(data, 2 + index * 4)?);
+        w.offset16(anchor(data, 4 + index * 4));
+    }
+    w.finish()
+}
+
+/// BaseArray・Mark2Array・LigatureAttach（各行にマーククラスごとのAnchor）のrowsの行だけを残す
+fn anchor_matrix(data: &[u8], rows: &[usize], class_count: usize) -> Option<Vec<u8>> {
+    let mut w = Writer::default();
+    w.u16(rows.len() as u16);
+    for &row in rows {
+        for class in 0..class_count {
+            w.offset16(anchor(data, 2 + (row * class_count + class) * 2));
+        }
+    }
+    w.finish()
+}
+
+fn subset_attach_list(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let coverage = retained_coverage(linked(data, 0)?, map)?;
+    let mut w = Writer::default();
+    w.offset16(Some(coverage_glyphs(&coverage)));
+    w.u16(coverage.len() as u16);
+    for &(_, index) in &coverage {
+        let point = linked(data, 4 + index * 2)?;
+        let count = read_u16(point, 0)? as usize;
+        w.offset16(Some(point.get(..2 + count * 2)?.to_vec()));
+    }
+    w.finish()
+}
+
+fn subset_lig_caret_list(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let coverage = retained_coverage(linked(data, 0)?, map)?;
+    let mut w = Writer::default();
+    w.offset16(Some(coverage_glyphs(&coverage)));
+    w.u16(coverage.len() as u16);
+    for &(_, index) in &coverage {
+        let lig_glyph = linked(data, 4 + index * 2)?;
+        let count = read_u16(lig_glyph, 0)? as usize;
+        let mut glyph_writer = Writer::default();
+        glyph_writer.u16(count as u16);
+        for i in 0..count {
+            glyph_writer.offset16(Some(caret_value(linked(lig_glyph, 2 + i * 2)?)?));
+        }
+        w.offset16(Some(glyph_writer.finish()?));
+    }
+    w.finish()
+}
+
+fn caret_value(data: &[u8]) -> Option<Vec<u8>> {
+    match read_u16(data, 0)? {
+        1 | 2 => data.get(..4).map(<[u8]>::to_vec),
+        3 => {
+            let mut w = Writer::default();
+            w.bytes(data.get(..4)?);
+            w.offset16(device(data, 4));
+            w.finish()
+        }
+        _ => None,
+    }
+}
+
+fn subset_mark_glyph_sets(data: &[u8], map: &GlyphMap) -> Option<Vec<u8>> {
+    let count = read_u16(data, 2)?;
+    let mut w = Writer::default();
+    w.u16s(&[1, count]);
+    for i in 0..count as usize {
+        w.offset32(Some(subset_coverage(linked32(data, 4 + i * 4)?, map)?));
+    }
+    w.finish()
+}
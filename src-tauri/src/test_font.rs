@@ -0,0 +1,279 @@
+//! テスト用の小さなフォントを組み立てる
+
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+use crate::cff;
+use crate::sfnt::{build_sfnt, SfntTable};
+
+/// TrueTypeのグリフ
+pub enum TestGlyph {
+    /// 直線だけの輪郭（すべてオンカーブ点）
+    Simple(Vec<Vec<(i16, i16)>>),
+    /// 参照するグリフと、その(dx, dy)
+    Composite(Vec<(u16, i16, i16)>),
+}
+
+/// グリフ（0番は.notdef）と文字の割り当てからTrueTypeフォントを作る
+pub fn truetype(glyphs: &[TestGlyph], cmap: &[(char, u16)], extra_tables: Vec<SfntTable>) -> Vec<u8> {
+    let mut glyf = Vec::new();
+    let mut loca = Vec::new();
+    for glyph in glyphs {
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+        glyf.extend(encode_glyph(glyph));
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+    }
+    loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+
+    let mut maxp = Vec::new();
+    push_u32(&mut maxp, 0x0001_0000);
+    push_u16(&mut maxp, glyphs.len() as u16);
+    maxp.resize(32, 0);
+
+    let mut tables = vec![
+        SfntTable { tag: *b"head", data: head(1) },
+        SfntTable { tag: *b"hhea", data: hhea(glyphs.len()) },
+        SfntTable { tag: *b"maxp", data: maxp },
+        SfntTable { tag: *b"hmtx", data: hmtx(glyphs.len()) },
+        SfntTable { tag: *b"cmap", data: cmap_format12(cmap) },
+        SfntTable { tag: *b"post", data: post() },
+        SfntTable { tag: *b"loca", data: loca },
+        SfntTable { tag: *b"glyf", data: glyf },
+    ];
+    tables.extend(extra_tables);
+    build_sfnt(&[(0x0001_0000, (0..tables.len()).collect())], &tables)
+}
+
+/// CharString（0番は.notdef）とサブルーチン、文字の割り当てからCFFのOpenTypeフォントを作る
+pub fn opentype_cff(
+    char_strings: &[&[u8]],
+    global_subrs: &[&[u8]],
+    local_subrs: &[&[u8]],
+    cmap: &[(char, u16)],
+) -> Vec<u8> {
+    let mut maxp = Vec::new();
+    push_u32(&mut maxp, 0x0000_5000);
+    push_u16(&mut maxp, char_strings.len() as u16);
+
+    let tables = vec![
+        SfntTable { tag: *b"CFF ", data: cff_table(char_strings, global_subrs, local_subrs) },
+        SfntTable { tag: *b"head", data: head(0) },
+        SfntTable { tag: *b"hhea", data: hhea(char_strings.len()) },
+        SfntTable { tag: *b"maxp", data: maxp },
+        SfntTable { tag: *b"hmtx", data: hmtx(char_strings.len()) },
+        SfntTable { tag: *b"cmap", data: cmap_format12(cmap) },
+        SfntTable { tag: *b"post", data: post() },
+    ];
+    build_sfnt(&[(0x4F54_544F, (0..tables.len()).collect())], &tables)
+}
+
+/// sfnt（TTCならindex番目のフォント）のテーブルをタグ順に取り出す
+pub fn sfnt_tables(data: &[u8], index: u32) -> Vec<SfntTable> {
+    let raw = ttf_parser::RawFace::parse(data, index).unwrap();
+    let mut tables: Vec<SfntTable> = raw
+        .table_records
+        .into_iter()
+        .map(|record| SfntTable {
+            tag: record.tag.to_bytes(),
+            data: raw.table(record.tag).unwrap().to_vec(),
+        })
+        .collect();
+    tables.sort_by_key(|table| table.tag);
+    tables
+}
+
+/// CharStringの整数（-107〜107）
+pub fn cs_int(value: i32) -> u8 {
+    (value + 139) as u8
+}
+
+/// グリフの輪郭をパス文字列にする（比較用）
+pub fn outline(face: &Face, glyph: u16) -> String {
+    struct Recorder(String);
+    impl OutlineBuilder for Recorder {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.0 += &format!("M{} {} ", x, y);
+        }
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.0 += &format!("L{} {} ", x, y);
+        }
+        fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+            self.0 += &format!("Q{} {} {} {} ", x1, y1, x, y);
+        }
+        fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+            self.0 += &format!("C{} {} {} {} {} {} ", x1, y1, x2, y2, x, y);
+        }
+        fn close(&mut self) {
+            self.0 += "Z ";
+        }
+    }
+
+    let mut recorder = Recorder(String::new());
+    face.outline_glyph(GlyphId(glyph), &mut recorder);
+    recorder.0
+}
+
+pub fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+pub fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+fn encode_glyph(glyph: &TestGlyph) -> Vec<u8> {
+    let mut out = Vec::new();
+    match glyph {
+        TestGlyph::Simple(contours) if contours.is_empty() => {}
+        TestGlyph::Simple(contours) => {
+            let points: Vec<(i16, i16)> = contours.concat();
+            push_u16(&mut out, contours.len() as u16);
+            for value in bbox(&points) {
+                push_u16(&mut out, value as u16);
+            }
+            let mut end = 0;
+            for contour in contours {
+                end += contour.len();
+                push_u16(&mut out, end as u16 - 1);
+            }
+            // 命令なし、すべてオンカーブ点で座標は2バイトの差分
+            push_u16(&mut out, 0);
+            out.extend(std::iter::repeat_n(1, points.len()));
+            for axis in [0, 1] {
+                let mut previous = 0;
+                for point in &points {
+                    let value = if axis == 0 { point.0 } else { point.1 };
+                    push_u16(&mut out, (value - previous) as u16);
+                    previous = value;
+                }
+            }
+        }
+        TestGlyph::Composite(components) => {
+            push_u16(&mut out, 0xFFFF);
+            // 外接矩形はフォントの読み込みに使われないため0にする
+            out.extend([0; 8]);
+            for (i, &(glyph, dx, dy)) in components.iter().enumerate() {
+                // ARG_1_AND_2_ARE_WORDS | ARGS_ARE_XY_VALUES（| MORE_COMPONENTS）
+                let more = if i + 1 < components.len() { 0x0020 } else { 0 };
+                push_u16(&mut out, 0x0003 | more);
+                push_u16(&mut out, glyph);
+                push_u16(&mut out, dx as u16);
+                push_u16(&mut out, dy as u16);
+            }
+        }
+    }
+    out
+}
+
+fn bbox(points: &[(i16, i16)]) -> [i16; 4] {
+    let x = points.iter().map(|p| p.0);
+    let y = points.iter().map(|p| p.1);
+    [
+        x.clone().min().unwrap_or(0),
+        y.clone().min().unwrap_or(0),
+        x.max().unwrap_or(0),
+        y.max().unwrap_or(0),
+    ]
+}
+
+fn head(index_to_loc_format: u16) -> Vec<u8> {
+    let mut out = Vec::new();
+    push_u32(&mut out, 0x0001_0000);
+    push_u32(&mut out, 0x0001_0000);
+    push_u32(&mut out, 0);
+    push_u32(&mut out, 0x5F0F_3CF5);
+    push_u16(&mut out, 0);
+    // unitsPerEm
+    push_u16(&mut out, 1000);
+    out.resize(50, 0);
+    push_u16(&mut out, index_to_loc_format);
+    push_u16(&mut out, 0);
+    out
+}
+
+fn hhea(num_glyphs: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    push_u32(&mut out, 0x0001_0000);
+    push_u16(&mut out, 800);
+    push_u16(&mut out, (-200i16) as u16);
+    out.resize(18, 0);
+    // caretSlopeRise
+    push_u16(&mut out, 1);
+    out.resize(34, 0);
+    push_u16(&mut out, num_glyphs as u16);
+    out
+}
+
+/// グリフごとに異なる送り幅（500 + 10 × グリフID）
+fn hmtx(num_glyphs: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    for glyph in 0..num_glyphs {
+        push_u16(&mut out, 500 + 10 * glyph as u16);
+        push_u16(&mut out, 0);
+    }
+    out
+}
+
+fn cmap_format12(cmap: &[(char, u16)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 1);
+    push_u16(&mut out, 3);
+    push_u16(&mut out, 10);
+    push_u32(&mut out, 12);
+    push_u16(&mut out, 12);
+    push_u16(&mut out, 0);
+    push_u32(&mut out, 16 + 12 * cmap.len() as u32);
+    push_u32(&mut out, 0);
+    push_u32(&mut out, cmap.len() as u32);
+    let mut sorted = cmap.to_vec();
+    sorted.sort();
+    for (c, glyph) in sorted {
+        push_u32(&mut out, c as u32);
+        push_u32(&mut out, c as u32);
+        push_u32(&mut out, glyph as u32);
+    }
+    out
+}
+
+/// グリフ名を持たないpost（バージョン3）
+fn post() -> Vec<u8> {
+    let mut out = Vec::new();
+    push_u32(&mut out, 0x0003_0000);
+    out.resize(32, 0);
+    out
+}
+
+fn cff_table(char_strings: &[&[u8]], global_subrs: &[&[u8]], local_subrs: &[&[u8]]) -> Vec<u8> {
+    // Private DICTはSubrsだけ（ローカルサブルーチンは直後に置く）
+    let mut private = Vec::new();
+    if !local_subrs.is_empty() {
+        cff::write_dict_entry(&mut private, 19, &[6]);
+    }
+
+    let head = |top_dict: &[u8]| {
+        let mut out = vec![1, 0, 4, 1];
+        out.extend(cff::write_index(&[b"Test"]));
+        out.extend(cff::write_index(&[top_dict]));
+        out.extend(cff::write_index(&[]));
+        out.extend(cff::write_index(global_subrs));
+        out
+    };
+    let top_dict = |char_strings_offset: usize, private_offset: usize| {
+        let mut out = Vec::new();
+        cff::write_dict_entry(&mut out, 17, &[char_strings_offset as i32]);
+        cff::write_dict_entry(&mut out, 18, &[private.len() as i32, private_offset as i32]);
+        out
+    };
+
+    let char_strings_offset = head(&top_dict(0, 0)).len();
+    let char_strings = cff::write_index(char_strings);
+    let private_offset = char_strings_offset + char_strings.len();
+
+    let mut out = head(&top_dict(char_strings_offset, private_offset));
+    out.extend(char_strings);
+    out.extend(&private);
+    if !local_subrs.is_empty() {
+        out.extend(cff::write_index(local_subrs));
+    }
+    out
+}
@@ -0,0 +1,231 @@
+//! バリアブルフォントの軸・名前付きインスタンス情報（fvar/avar/STAT）を取得
+
+use ttf_parser::{Face, Tag};
+
+use crate::face_name;
+use crate::sfnt::{read_fixed, read_tag, read_u16, read_u32};
+
+/// 軸の座標（例: wght=700）
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct AxisCoordinate {
+    pub tag: String,
+    pub value: f32,
+}
+
+#[derive(serde::Serialize)]
+pub struct VariationAxisInfo {
+    pub tag: String,
+    pub name: Option<String>,
+    pub min_value: f32,
+    pub default_value: f32,
+    pub max_value: f32,
+    /// UIに表示しないことが推奨される軸
+    pub hidden: bool,
+    /// avarによる正規化座標の対応（[変換前, 変換後]、-1.0〜1.0）、avarが無ければ空
+    pub avar_mapping: Vec<[f32; 2]>,
+}
+
+#[derive(serde::Serialize)]
+pub struct NamedInstanceInfo {
+    pub name: Option<String>,
+    pub postscript_name: Option<String>,
+    pub coordinates: Vec<AxisCoordinate>,
+}
+
+#[derive(serde::Serialize)]
+pub struct StatAxisValueInfo {
+    pub name: Option<String>,
+    /// 名前の組み立て時に省略できる値（例: "Regular"）
+    pub elidable: bool,
+    pub coordinates: Vec<AxisCoordinate>,
+    /// フォーマット2の範囲指定（[最小, 最大]）
+    pub range: Option<[f32; 2]>,
+    /// フォーマット3のリンク先の値（例: Regular → Bold）
+    pub linked_value: Option<f32>,
+}
+
+#[derive(serde::Serialize)]
+pub struct FontVariations {
+    pub axes: Vec<VariationAxisInfo>,
+    pub named_instances: Vec<NamedInstanceInfo>,
+    /// STATテーブルの軸の値（STATが無ければ空）
+    pub stat_values: Vec<StatAxisValueInfo>,
+}
+
+pub fn collect_font_variations(face: &Face) -> FontVariations {
+    let axes: Vec<_> = face.variation_axes().into_iter().collect();
+
+    // avarのセグメントマップは軸の順に並ぶ
+    let avar_maps: Vec<Vec<[f32; 2]>> = face
+        .tables()
+        .avar
+        .map(|avar| {
+            avar.segment_maps
+                .into_iter()
+                .map(|maps| {
+                    maps.into_iter()
+                        .map(|m| [f2dot14(m.from_coordinate), f2dot14(m.to_coordinate)])
+                        .collect()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let axis_infos = axes
+        .iter()
+        .enumerate()
+        .map(|(i, axis)| VariationAxisInfo {
+            tag: axis.tag.to_string(),
+            name: face_name(face, axis.name_id),
+            min_value: axis.min_value,
+            default_value: axis.def_value,
+            max_value: axis.max_value,
+            hidden: axis.hidden,
+            avar_mapping: avar_maps.get(i).cloned().unwrap_or_default(),
+        })
+        .collect();
+
+    let axis_tags: Vec<Tag> = axes.iter().map(|a| a.tag).collect();
+
+    FontVariations {
+        axes: axis_infos,
+        named_instances: named_instances(face).unwrap_or_default(),
+        stat_values: stat_axis_values(face, &axis_tags).unwrap_or_default(),
+    }
+}
+
+/// 2.14固定小数点
+fn f2dot14(value: i16) -> f32 {
+    value as f32 / 16384.0
+}
+
+/// fvarの名前付きインスタンス（ttf-parserは軸しか公開していないため直接読む）
+fn named_instances(face: &Face) -> Option<Vec<NamedInstanceInfo>> {
+    let data = face.raw_face().table(Tag::from_bytes(b"fvar"))?;
+
+    let axes_offset = read_u16(data, 4)? as usize;
+    let axis_count = read_u16(data, 8)? as usize;
+    let axis_size = read_u16(data, 10)? as usize;
+    let instance_count = read_u16(data, 12)? as usize;
+    let instance_size = read_u16(data, 14)? as usize;
+
+    let axis_tags: Vec<Tag> = (0..axis_count)
+        .map(|i| read_tag(data, axes_offset + i * axis_size))
+        .collect::<Option<_>>()?;
+
+    // PostScript名IDはinstanceSizeが座標の後に2バイト余分にある場合のみ存在
+    let has_postscript_name = instance_size >= axis_count * 4 + 6;
+    let instances_offset = axes_offset + axis_count * axis_size;
+
+    let mut instances = Vec::new();
+    for i in 0..instance_count {
+        let offset = instances_offset + i * instance_size;
+        let subfamily_name_id = read_u16(data, offset)?;
+
+        let coordinates = axis_tags
+            .iter()
+            .enumerate()
+            .map(|(j, tag)| {
+                Some(AxisCoordinate {
+                    tag: tag.to_string(),
+                    value: read_fixed(data, offset + 4 + j * 4)?,
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        let postscript_name = if has_postscript_name {
+            read_u16(data, offset + 4 + axis_count * 4)
+                .filter(|&id| id != 0xFFFF)
+                .and_then(|id| face_name(face, id))
+        } else {
+            None
+        };
+
+        instances.push(NamedInstanceInfo {
+            name: face_name(face, subfamily_name_id),
+            postscript_name,
+            coordinates,
+        });
+    }
+
+    Some(instances)
+}
+
+/// STATの軸の値テーブル（フォーマット1〜4）
+fn stat_axis_values(face: &Face, fvar_axes: &[Tag]) -> Option<Vec<StatAxisValueInfo>> {
+    let data = face.raw_face().table(Tag::from_bytes(b"STAT"))?;
+
+    let design_axis_size = read_u16(data, 4)? as usize;
+    let design_axis_count = read_u16(data, 6)? as usize;
+    let design_axes_offset = read_u32(data, 8)? as usize;
+    let axis_value_count = read_u16(data, 12)? as usize;
+    let axis_value_offsets = read_u32(data, 14)? as usize;
+
+    // STATの軸にはfvarに無い軸（例: ital）も含まれる
+    let design_axes: Vec<Tag> = (0..design_axis_count)
+        .map(|i| read_tag(data, design_axes_offset + i * design_axis_size))
+        .collect::<Option<_>>()?;
+    let axis_tag = |index: u16| {
+        design_axes
+            .get(index as usize)
+            .or_else(|| fvar_axes.get(index as usize))
+            .map(|t| t.to_string())
+            .unwrap_or_default()
+    };
+
+    let mut values = Vec::new();
+    for i in 0..axis_value_count {
+        let offset = axis_value_offsets + read_u16(data, axis_value_offsets + i * 2)? as usize;
+        let format = read_u16(data, offset)?;
+
+        let (flags, name_id, coordinates, range, linked_value) = match format {
+            1..=3 => {
+                let axis_index = read_u16(data, offset + 2)?;
+                let flags = read_u16(data, offset + 4)?;
+                let name_id = read_u16(data, offset + 6)?;
+                let value = read_fixed(data, offset + 8)?;
+                let coordinate = AxisCoordinate {
+                    tag: axis_tag(axis_index),
+                    value,
+                };
+                let range = if format == 2 {
+                    Some([read_fixed(data, offset + 12)?, read_fixed(data, offset + 16)?])
+                } else {
+                    None
+                };
+                let linked_value = if format == 3 {
+                    Some(read_fixed(data, offset + 12)?)
+                } else {
+                    None
+                };
+                (flags, name_id, vec![coordinate], range, linked_value)
+            }
+            4 => {
+                let axis_count = read_u16(data, offset + 2)? as usize;
+                let flags = read_u16(data, offset + 4)?;
+                let name_id = read_u16(data, offset + 6)?;
+                let coordinates = (0..axis_count)
+                    .map(|j| {
+                        let record = offset + 8 + j * 6;
+                        Some(AxisCoordinate {
+                            tag: axis_tag(read_u16(data, record)?),
+                            value: read_fixed(data, record + 2)?,
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                (flags, name_id, coordinates, None, None)
+            }
+            _ => continue,
+        };
+
+        values.push(StatAxisValueInfo {
+            name: face_name(face, name_id),
+            elidable: flags & 0x0002 != 0,
+            coordinates,
+            range,
+            linked_value,
+        });
+    }
+
+    Some(values)
+}
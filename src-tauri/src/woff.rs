@@ -0,0 +1,908 @@
+//! WOFF/WOFF2をsfnt（TrueType/OpenType、コレクションの場合はTTC）に展開する
+//! WOFF2のglyf/loca・hmtx変換も復元する
+
+use std::io::Read;
+
+use crate::sfnt::{build_sfnt, read_u16, read_u32, SfntTable};
+
+const WOFF_SIGNATURE: &[u8; 4] = b"wOFF";
+const WOFF2_SIGNATURE: &[u8; 4] = b"wOF2";
+const TTC_FLAVOR: u32 = 0x7474_6366; // "ttcf"
+
+/// WOFF2のテーブルディレクトリで番号により省略されるタグ（0〜62）
+const WOFF2_KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+/// WOFF2の圧縮品質（Brotliの最大値。Webフォントは一度作って何度も配信するため圧縮率を優先）
+const WOFF2_BROTLI_QUALITY: i32 = 11;
+
+/// WOFF/WOFF2であればsfntに展開し、それ以外はそのまま返す
+pub fn decode_if_needed(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    match data.get(0..4) {
+        Some(signature) if signature == WOFF_SIGNATURE => {
+            decode_woff(&data).ok_or_else(|| "Failed to decode WOFF: invalid data".to_string())
+        }
+        Some(signature) if signature == WOFF2_SIGNATURE => {
+            decode_woff2(&data).ok_or_else(|| "Failed to decode WOFF2: invalid data".to_string())
+        }
+        _ => Ok(data),
+    }
+}
+
+fn read_tag_bytes(data: &[u8], offset: usize) -> Option<[u8; 4]> {
+    data.get(offset..offset + 4)?.try_into().ok()
+}
+
+fn decode_woff(data: &[u8]) -> Option<Vec<u8>> {
+    let flavor = read_u32(data, 4)?;
+    let num_tables = read_u16(data, 12)? as usize;
+
+    let mut tables = Vec::with_capacity(num_tables);
+    for i in 0..num_tables {
+        let entry = 44 + i * 20;
+        let tag = read_tag_bytes(data, entry)?;
+        let offset = read_u32(data, entry + 4)? as usize;
+        let comp_length = read_u32(data, entry + 8)? as usize;
+        let orig_length = read_u32(data, entry + 12)? as usize;
+        let compressed = data.get(offset..offset.checked_add(comp_length)?)?;
+
+        // 圧縮後のほうが大きくなるテーブルは無圧縮で格納される
+        let table_data = if comp_length < orig_length {
+            let mut decoded = Vec::with_capacity(orig_length);
+            flate2::read::ZlibDecoder::new(compressed)
+                .read_to_end(&mut decoded)
+                .ok()?;
+            decoded
+        } else {
+            compressed.to_vec()
+        };
+        if table_data.len() != orig_length {
+            return None;
+        }
+
+        tables.push(SfntTable { tag, data: table_data });
+    }
+
+    let indices: Vec<usize> = (0..tables.len()).collect();
+    Some(build_sfnt(&[(flavor, indices)], &tables))
+}
+
+/// UIntBase128（WOFF2の可変長整数）
+fn read_base128(data: &[u8], offset: &mut usize) -> Option<u32> {
+    let mut value: u32 = 0;
+    for i in 0..5 {
+        let byte = *data.get(*offset)?;
+        *offset += 1;
+        // 先頭の0は不正
+        if i == 0 && byte == 0x80 {
+            return None;
+        }
+        if value & 0xFE00_0000 != 0 {
+            return None;
+        }
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// 255UInt16（WOFF2の可変長整数）
+fn read_255_u16(data: &[u8], offset: &mut usize) -> Option<u16> {
+    let code = *data.get(*offset)?;
+    *offset += 1;
+    match code {
+        253 => {
+            let value = read_u16(data, *offset)?;
+            *offset += 2;
+            Some(value)
+        }
+        254 => {
+            let value = *data.get(*offset)? as u16 + 253 * 2;
+            *offset += 1;
+            Some(value)
+        }
+        255 => {
+            let value = *data.get(*offset)? as u16 + 253;
+            *offset += 1;
+            Some(value)
+        }
+        _ => Some(code as u16),
+    }
+}
+
+/// UIntBase128で書き込む
+fn write_base128(out: &mut Vec<u8>, value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        bytes.push((rest & 0x7F) as u8 | 0x80);
+        rest >>= 7;
+    }
+    out.extend(bytes.iter().rev());
+}
+
+/// 単体のフォントのテーブルをWOFF2にする
+/// glyf/loca・hmtxは変換せずに（null transform）Brotliで圧縮する
+pub fn encode_woff2(flavor: u32, tables: &[SfntTable]) -> Result<Vec<u8>, String> {
+    let mut sorted: Vec<&SfntTable> = tables.iter().collect();
+    sorted.sort_by_key(|table| table.tag);
+
+    let mut directory = Vec::new();
+    let mut stream = Vec::new();
+    for table in &sorted {
+        // glyf/locaはバージョン3、それ以外はバージョン0が変換なし
+        let transform_version: u8 = if &table.tag == b"glyf" || &table.tag == b"loca" { 3 } else { 0 };
+        match WOFF2_KNOWN_TAGS.iter().position(|tag| **tag == table.tag) {
+            Some(index) => directory.push(index as u8 | transform_version << 6),
+            None => {
+                directory.push(63 | transform_version << 6);
+                directory.extend_from_slice(&table.tag);
+            }
+        }
+        write_base128(&mut directory, table.data.len() as u32);
+        stream.extend_from_slice(&table.data);
+    }
+
+    let params = brotli::enc::BrotliEncoderParams {
+        quality: WOFF2_BROTLI_QUALITY,
+        mode: brotli::enc::backward_references::BrotliEncoderMode::BROTLI_MODE_FONT,
+        size_hint: stream.len(),
+        ..Default::default()
+    };
+    let mut compressed = Vec::new();
+    brotli::BrotliCompress(&mut stream.as_slice(), &mut compressed, &params)
+        .map_err(|e| format!("Failed to compress WOFF2: {}", e))?;
+
+    let total_sfnt_size: usize =
+        12 + tables.len() * 16 + tables.iter().map(|t| t.data.len().next_multiple_of(4)).sum::<usize>();
+    // 圧縮データの後ろは4バイト境界まで埋める（lengthにも含める）
+    let length = (48 + directory.len() + compressed.len()).next_multiple_of(4);
+
+    let mut out = Vec::with_capacity(length);
+    out.extend_from_slice(WOFF2_SIGNATURE);
+    out.extend_from_slice(&flavor.to_be_bytes());
+    out.extend_from_slice(&(length as u32).to_be_bytes());
+    out.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes()); // reserved
+    out.extend_from_slice(&(total_sfnt_size as u32).to_be_bytes());
+    out.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+    out.extend_from_slice(&1u16.to_be_bytes()); // majorVersion
+    out.extend_from_slice(&0u16.to_be_bytes()); // minorVersion
+    // メタデータ・プライベートデータは無し
+    out.extend_from_slice(&[0u8; 20]);
+    out.extend_from_slice(&directory);
+    out.extend_from_slice(&compressed);
+    out.resize(length, 0);
+
+    Ok(out)
+}
+
+struct Woff2TableEntry {
+    tag: [u8; 4],
+    transformed: bool,
+    /// 展開後のデータ内の範囲
+    start: usize,
+    length: usize,
+}
+
+fn decode_woff2(data: &[u8]) -> Option<Vec<u8>> {
+    let flavor = read_u32(data, 4)?;
+    let num_tables = read_u16(data, 12)? as usize;
+    let total_compressed_size = read_u32(data, 20)? as usize;
+
+    let mut offset = 48;
+    let mut entries = Vec::with_capacity(num_tables);
+    let mut stream_offset = 0;
+    for _ in 0..num_tables {
+        let flags = *data.get(offset)?;
+        offset += 1;
+        let tag = match flags & 0x3F {
+            63 => {
+                let tag = read_tag_bytes(data, offset)?;
+                offset += 4;
+                tag
+            }
+            index => *WOFF2_KNOWN_TAGS[index as usize],
+        };
+        let transform_version = flags >> 6;
+        let orig_length = read_base128(data, &mut offset)? as usize;
+
+        // glyf/locaはバージョン0が変換あり、それ以外はバージョン0が変換なし
+        let is_glyf_loca = &tag == b"glyf" || &tag == b"loca";
+        let transformed = if is_glyf_loca {
+            transform_version == 0
+        } else {
+            transform_version != 0
+        };
+        let length = if transformed {
+            read_base128(data, &mut offset)? as usize
+        } else {
+            orig_length
+        };
+
+        entries.push(Woff2TableEntry {
+            tag,
+            transformed,
+            start: stream_offset,
+            length,
+        });
+        stream_offset += length;
+    }
+
+    // コレクションの各フォントが使うテーブル
+    let fonts: Vec<(u32, Vec<usize>)> = if flavor == TTC_FLAVOR {
+        offset += 4; // version
+        let num_fonts = read_255_u16(data, &mut offset)?;
+        let mut fonts = Vec::new();
+        for _ in 0..num_fonts {
+            let font_num_tables = read_255_u16(data, &mut offset)?;
+            let font_flavor = read_u32(data, offset)?;
+            offset += 4;
+            let indices = (0..font_num_tables)
+                .map(|_| read_255_u16(data, &mut offset).map(|i| i as usize))
+                .collect::<Option<Vec<_>>>()?;
+            if indices.iter().any(|&i| i >= entries.len()) {
+                return None;
+            }
+            fonts.push((font_flavor, indices));
+        }
+        fonts
+    } else {
+        vec![(flavor, (0..entries.len()).collect())]
+    };
+
+    let compressed = data.get(offset..offset.checked_add(total_compressed_size)?)?;
+    let mut stream = Vec::new();
+    brotli::Decompressor::new(compressed, 4096)
+        .read_to_end(&mut stream)
+        .ok()?;
+    if stream.len() < stream_offset {
+        return None;
+    }
+
+    let mut tables: Vec<Option<SfntTable>> = entries
+        .iter()
+        .map(|entry| {
+            (!entry.transformed).then(|| SfntTable {
+                tag: entry.tag,
+                data: stream[entry.start..entry.start + entry.length].to_vec(),
+            })
+        })
+        .collect();
+
+    // 変換されたテーブルをフォントごとに復元（glyf → loca → hmtxの順に依存する）
+    for (_, indices) in &fonts {
+        let find = |tag: &[u8; 4]| indices.iter().copied().find(|&i| &entries[i].tag == tag);
+
+        let mut x_mins = None;
+        if let (Some(glyf), Some(loca)) = (find(b"glyf"), find(b"loca")) {
+            if entries[glyf].transformed && tables[glyf].is_none() {
+                let entry = &entries[glyf];
+                let glyphs = reconstruct_glyf(&stream[entry.start..entry.start + entry.length])?;
+                tables[glyf] = Some(SfntTable { tag: *b"glyf", data: glyphs.glyf });
+                tables[loca] = Some(SfntTable { tag: *b"loca", data: glyphs.loca });
+                x_mins = Some(glyphs.x_mins);
+
+                // locaを長い形式にした場合はheadのindexToLocFormatも合わせる
+                let head = find(b"head").and_then(|i| tables[i].as_mut())?;
+                head.data.get_mut(50..52)?.copy_from_slice(&glyphs.index_format.to_be_bytes());
+            }
+        }
+
+        if let Some(hmtx) = find(b"hmtx").filter(|&i| entries[i].transformed && tables[i].is_none()) {
+            let hhea = tables[find(b"hhea")?].as_ref()?;
+            let x_mins = x_mins.as_ref()?;
+            let entry = &entries[hmtx];
+            let data = reconstruct_hmtx(
+                &stream[entry.start..entry.start + entry.length],
+                read_u16(&hhea.data, 34)? as usize,
+                x_mins,
+            )?;
+            tables[hmtx] = Some(SfntTable { tag: *b"hmtx", data });
+        }
+    }
+
+    let tables = tables.into_iter().collect::<Option<Vec<_>>>()?;
+    Some(build_sfnt(&fonts, &tables))
+}
+
+struct ReconstructedGlyphs {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    /// locaの形式（headのindexToLocFormat、0なら短い形式）
+    index_format: u16,
+    /// hmtxの復元に使う各グリフのxMin（空のグリフは0）
+    x_mins: Vec<i16>,
+}
+
+/// 変換されたglyfテーブル（WOFF2仕様 5.1）からglyf/locaを復元
+fn reconstruct_glyf(data: &[u8]) -> Option<ReconstructedGlyphs> {
+    let option_flags = read_u16(data, 2)?;
+    let num_glyphs = read_u16(data, 4)? as usize;
+    let index_format = read_u16(data, 6)?;
+
+    // nContour, nPoints, flag, glyph, composite, bbox, instructionの各ストリーム
+    let mut streams = Vec::with_capacity(7);
+    let mut offset: usize = 36;
+    for i in 0..7 {
+        let size = read_u32(data, 8 + i * 4)? as usize;
+        streams.push(data.get(offset..offset.checked_add(size)?)?);
+        offset += size;
+    }
+    let [n_contour_stream, n_points_stream, flag_stream, glyph_stream, composite_stream, bbox_stream, instruction_stream] =
+        streams[..]
+    else {
+        return None;
+    };
+    let overlap_bitmap = if option_flags & 1 != 0 {
+        data.get(offset..offset + num_glyphs.div_ceil(8))
+    } else {
+        None
+    };
+
+    let bbox_bitmap_size = num_glyphs.div_ceil(32) * 4;
+    let bbox_bitmap = bbox_stream.get(..bbox_bitmap_size)?;
+    let mut bbox_offset = bbox_bitmap_size;
+
+    let (mut n_points_offset, mut flag_offset, mut glyph_offset) = (0, 0, 0);
+    let (mut composite_offset, mut instruction_offset) = (0, 0);
+
+    let mut glyf = Vec::new();
+    let mut loca_offsets = Vec::with_capacity(num_glyphs + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs);
+
+    for glyph_index in 0..num_glyphs {
+        loca_offsets.push(glyf.len());
+        let n_contours = read_u16(n_contour_stream, glyph_index * 2)? as i16;
+        let has_bbox = bbox_bitmap[glyph_index / 8] & (0x80 >> (glyph_index % 8)) != 0;
+        let mut read_bbox = || {
+            let bbox: Vec<u8> = bbox_stream.get(bbox_offset..bbox_offset + 8)?.to_vec();
+            bbox_offset += 8;
+            Some(bbox)
+        };
+
+        if n_contours == 0 {
+            x_mins.push(0);
+            continue;
+        }
+
+        let glyph_start = glyf.len();
+        glyf.extend_from_slice(&n_contours.to_be_bytes());
+
+        if n_contours > 0 {
+            // 単純グリフ
+            let mut end_points = Vec::with_capacity(n_contours as usize);
+            let mut total_points: usize = 0;
+            for _ in 0..n_contours {
+                total_points += read_255_u16(n_points_stream, &mut n_points_offset)? as usize;
+                end_points.push(u16::try_from(total_points.checked_sub(1)?).ok()?);
+            }
+
+            let flags_in = flag_stream.get(flag_offset..flag_offset + total_points)?;
+            flag_offset += total_points;
+
+            let mut points = Vec::with_capacity(total_points);
+            let (mut x, mut y) = (0i32, 0i32);
+            for &flag in flags_in {
+                let on_curve = flag & 0x80 == 0;
+                let (dx, dy) = decode_triplet(flag & 0x7F, glyph_stream, &mut glyph_offset)?;
+                x += dx;
+                y += dy;
+                points.push((x, y, on_curve));
+            }
+
+            let instruction_length = read_255_u16(glyph_stream, &mut glyph_offset)? as usize;
+            let instructions =
+                instruction_stream.get(instruction_offset..instruction_offset + instruction_length)?;
+            instruction_offset += instruction_length;
+
+            let bbox = if has_bbox {
+                read_bbox()?
+            } else {
+                let x_min = points.iter().map(|p| p.0).min()?;
+                let y_min = points.iter().map(|p| p.1).min()?;
+                let x_max = points.iter().map(|p| p.0).max()?;
+                let y_max = points.iter().map(|p| p.1).max()?;
+                [x_min, y_min, x_max, y_max]
+                    .iter()
+                    .flat_map(|v| (*v as i16).to_be_bytes())
+                    .collect()
+            };
+            x_mins.push(i16::from_be_bytes([bbox[0], bbox[1]]));
+            glyf.extend_from_slice(&bbox);
+
+            for end_point in end_points {
+                glyf.extend_from_slice(&end_point.to_be_bytes());
+            }
+            glyf.extend_from_slice(&(instruction_length as u16).to_be_bytes());
+            glyf.extend_from_slice(instructions);
+
+            let overlap = overlap_bitmap
+                .is_some_and(|bitmap| bitmap[glyph_index / 8] & (0x80 >> (glyph_index % 8)) != 0);
+            write_simple_glyph_points(&mut glyf, &points, overlap);
+        } else {
+            // 複合グリフ（bboxは必ずbboxストリームにある）
+            let bbox = read_bbox()?;
+            x_mins.push(i16::from_be_bytes([bbox[0], bbox[1]]));
+            glyf.extend_from_slice(&bbox);
+
+            let (component_data, have_instructions) =
+                composite_glyph_data(composite_stream, &mut composite_offset)?;
+            glyf.extend_from_slice(component_data);
+
+            if have_instructions {
+                let instruction_length = read_255_u16(glyph_stream, &mut glyph_offset)? as usize;
+                let instructions = instruction_stream
+                    .get(instruction_offset..instruction_offset + instruction_length)?;
+                instruction_offset += instruction_length;
+                glyf.extend_from_slice(&(instruction_length as u16).to_be_bytes());
+                glyf.extend_from_slice(instructions);
+            }
+        }
+
+        // locaの形式に合わせてパディング（短い形式は2バイト、長い形式は4バイト境界）
+        let align = if index_format == 0 { 2 } else { 4 };
+        while (glyf.len() - glyph_start) % align != 0 {
+            glyf.push(0);
+        }
+    }
+    loca_offsets.push(glyf.len());
+
+    // 短い形式（オフセット/2をu16）で表せない大きさになった場合は長い形式にする
+    let index_format = if index_format == 0 && glyf.len() > 0x1FFFE { 1 } else { index_format };
+    let loca = if index_format == 0 {
+        loca_offsets
+            .iter()
+            .flat_map(|&o| ((o / 2) as u16).to_be_bytes())
+            .collect()
+    } else {
+        loca_offsets
+            .iter()
+            .flat_map(|&o| (o as u32).to_be_bytes())
+            .collect()
+    };
+
+    Some(ReconstructedGlyphs {
+        glyf,
+        loca,
+        index_format,
+        x_mins,
+    })
+}
+
+/// 座標のトリプレット符号化（WOFF2仕様 5.2）を復号し、(dx, dy)を返す
+fn decode_triplet(flag: u8, data: &[u8], offset: &mut usize) -> Option<(i32, i32)> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let flag_i = flag as i32;
+
+    let byte_count = match flag {
+        0..=83 => 1,
+        84..=119 => 2,
+        120..=123 => 3,
+        _ => 4,
+    };
+    let bytes: Vec<i32> = data
+        .get(*offset..*offset + byte_count)?
+        .iter()
+        .map(|&b| b as i32)
+        .collect();
+    *offset += byte_count;
+
+    let delta = match flag {
+        0..=9 => (0, with_sign(flag, ((flag_i & 14) << 7) + bytes[0])),
+        10..=19 => (with_sign(flag, (((flag_i - 10) & 14) << 7) + bytes[0]), 0),
+        20..=83 => {
+            let b0 = flag_i - 20;
+            (
+                with_sign(flag, 1 + (b0 & 0x30) + (bytes[0] >> 4)),
+                with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (bytes[0] & 0x0F)),
+            )
+        }
+        84..=119 => {
+            let b0 = flag_i - 84;
+            (
+                with_sign(flag, 1 + ((b0 / 12) << 8) + bytes[0]),
+                with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + bytes[1]),
+            )
+        }
+        120..=123 => (
+            with_sign(flag, (bytes[0] << 4) + (bytes[1] >> 4)),
+            with_sign(flag >> 1, ((bytes[1] & 0x0F) << 8) + bytes[2]),
+        ),
+        _ => (
+            with_sign(flag, (bytes[0] << 8) + bytes[1]),
+            with_sign(flag >> 1, (bytes[2] << 8) + bytes[3]),
+        ),
+    };
+    Some(delta)
+}
+
+/// 単純グリフのフラグと座標をglyf形式で書き出す
+fn write_simple_glyph_points(glyf: &mut Vec<u8>, points: &[(i32, i32, bool)], overlap: bool) {
+    const ON_CURVE: u8 = 0x01;
+    const X_SHORT: u8 = 0x02;
+    const Y_SHORT: u8 = 0x04;
+    const X_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_SAME_OR_POSITIVE: u8 = 0x20;
+    const OVERLAP_SIMPLE: u8 = 0x40;
+    const REPEAT_FLAG: u8 = 0x08;
+
+    let mut flags = Vec::with_capacity(points.len());
+    let mut xs = Vec::new();
+    let mut ys = Vec::new();
+    let (mut last_x, mut last_y) = (0, 0);
+
+    for (i, &(x, y, on_curve)) in points.iter().enumerate() {
+        let mut flag = if on_curve { ON_CURVE } else { 0 };
+        if i == 0 && overlap {
+            flag |= OVERLAP_SIMPLE;
+        }
+
+        let dx = x - last_x;
+        let dy = y - last_y;
+        if dx == 0 {
+            flag |= X_SAME_OR_POSITIVE;
+        } else if (-255..=255).contains(&dx) {
+            flag |= X_SHORT;
+            if dx > 0 {
+                flag |= X_SAME_OR_POSITIVE;
+            }
+            xs.push(dx.unsigned_abs() as u8);
+        } else {
+            xs.extend_from_slice(&(dx as i16).to_be_bytes());
+        }
+        if dy == 0 {
+            flag |= Y_SAME_OR_POSITIVE;
+        } else if (-255..=255).contains(&dy) {
+            flag |= Y_SHORT;
+            if dy > 0 {
+                flag |= Y_SAME_OR_POSITIVE;
+            }
+            ys.push(dy.unsigned_abs() as u8);
+        } else {
+            ys.extend_from_slice(&(dy as i16).to_be_bytes());
+        }
+
+        flags.push(flag);
+        last_x = x;
+        last_y = y;
+    }
+
+    // 同じフラグが続く場合はREPEAT_FLAGと繰り返し回数（最大255）にまとめる
+    let mut i = 0;
+    while i < flags.len() {
+        let flag = flags[i];
+        let repeat = flags[i + 1..]
+            .iter()
+            .take(255)
+            .take_while(|&&f| f == flag)
+            .count();
+        if repeat > 0 {
+            glyf.push(flag | REPEAT_FLAG);
+            glyf.push(repeat as u8);
+        } else {
+            glyf.push(flag);
+        }
+        i += 1 + repeat;
+    }
+    glyf.extend_from_slice(&xs);
+    glyf.extend_from_slice(&ys);
+}
+
+/// 複合グリフのコンポーネント列を取り出し、命令の有無を返す
+fn composite_glyph_data<'a>(data: &'a [u8], offset: &mut usize) -> Option<(&'a [u8], bool)> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+    const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+    let start = *offset;
+    let mut have_instructions = false;
+    loop {
+        let flags = read_u16(data, *offset)?;
+        have_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+
+        // flags + glyphIndex + 引数 + 変換行列
+        let mut size = 4;
+        size += if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            size += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            size += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            size += 8;
+        }
+        *offset += size;
+
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+
+    Some((data.get(start..*offset)?, have_instructions))
+}
+
+/// 変換されたhmtxテーブル（WOFF2仕様 5.4）を復元
+/// 省略されたleft side bearingはグリフのxMinから求める
+fn reconstruct_hmtx(data: &[u8], number_of_h_metrics: usize, x_mins: &[i16]) -> Option<Vec<u8>> {
+    let flags = *data.first()?;
+    let num_glyphs = x_mins.len();
+    if number_of_h_metrics == 0 || number_of_h_metrics > num_glyphs {
+        return None;
+    }
+
+    let mut offset = 1;
+    let advances: Vec<u16> = (0..number_of_h_metrics)
+        .map(|i| read_u16(data, offset + i * 2))
+        .collect::<Option<_>>()?;
+    offset += number_of_h_metrics * 2;
+
+    let mut lsbs: Vec<i16> = Vec::with_capacity(num_glyphs);
+    if flags & 0x01 == 0 {
+        for i in 0..number_of_h_metrics {
+            lsbs.push(read_u16(data, offset + i * 2)? as i16);
+        }
+        offset += number_of_h_metrics * 2;
+    } else {
+        lsbs.extend_from_slice(&x_mins[..number_of_h_metrics]);
+    }
+    if flags & 0x02 == 0 {
+        for i in 0..num_glyphs - number_of_h_metrics {
+            lsbs.push(read_u16(data, offset + i * 2)? as i16);
+        }
+    } else {
+        lsbs.extend_from_slice(&x_mins[number_of_h_metrics..]);
+    }
+
+    let mut hmtx = Vec::with_capacity(number_of_h_metrics * 4 + (num_glyphs - number_of_h_metrics) * 2);
+    for (i, lsb) in lsbs.iter().enumerate() {
+        if let Some(advance) = advances.get(i) {
+            hmtx.extend_from_slice(&advance.to_be_bytes());
+        }
+        hmtx.extend_from_slice(&lsb.to_be_bytes());
+    }
+    Some(hmtx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font::{outline, push_u16, push_u32, sfnt_tables, truetype, TestGlyph};
+    use ttf_parser::{Face, GlyphId};
+
+    fn write_255_u16(out: &mut Vec<u8>, value: u16) {
+        if value < 253 {
+            out.push(value as u8);
+        } else {
+            out.push(253);
+            push_u16(out, value);
+        }
+    }
+
+    /// 変換したglyf（WOFF2仕様 5.1）を作る
+    /// 単純グリフの座標はすべて16ビットの差分（トリプレットのフラグ124〜127）にする
+    fn transformed_glyf(glyphs: &[TestGlyph], index_format: u16) -> Vec<u8> {
+        let mut n_contours = Vec::new();
+        let mut n_points = Vec::new();
+        let mut flags = Vec::new();
+        let mut glyph_stream = Vec::new();
+        let mut composites = Vec::new();
+        let mut bbox_bitmap = vec![0u8; glyphs.len().div_ceil(32) * 4];
+        let mut bboxes = Vec::new();
+
+        for (i, glyph) in glyphs.iter().enumerate() {
+            match glyph {
+                TestGlyph::Simple(contours) => {
+                    push_u16(&mut n_contours, contours.len() as u16);
+                    let (mut x, mut y) = (0i16, 0i16);
+                    for contour in contours {
+                        write_255_u16(&mut n_points, contour.len() as u16);
+                        for &(px, py) in contour {
+                            let (dx, dy) = (px - x, py - y);
+                            flags.push(124 | (dx >= 0) as u8 | ((dy >= 0) as u8) << 1);
+                            push_u16(&mut glyph_stream, dx.unsigned_abs());
+                            push_u16(&mut glyph_stream, dy.unsigned_abs());
+                            (x, y) = (px, py);
+                        }
+                    }
+                    if !contours.is_empty() {
+                        // 命令の長さ
+                        write_255_u16(&mut glyph_stream, 0);
+                    }
+                }
+                TestGlyph::Composite(components) => {
+                    push_u16(&mut n_contours, 0xFFFF);
+                    bbox_bitmap[i / 8] |= 0x80 >> (i % 8);
+                    bboxes.extend([0; 8]);
+                    for (j, &(component, dx, dy)) in components.iter().enumerate() {
+                        let more = if j + 1 < components.len() { 0x0020 } else { 0 };
+                        push_u16(&mut composites, 0x0003 | more);
+                        push_u16(&mut composites, component);
+                        push_u16(&mut composites, dx as u16);
+                        push_u16(&mut composites, dy as u16);
+                    }
+                }
+            }
+        }
+        bbox_bitmap.extend(bboxes);
+
+        let streams = [n_contours, n_points, flags, glyph_stream, composites, bbox_bitmap, Vec::new()];
+        let mut out = Vec::new();
+        push_u16(&mut out, 0);
+        push_u16(&mut out, 0);
+        push_u16(&mut out, glyphs.len() as u16);
+        push_u16(&mut out, index_format);
+        for stream in &streams {
+            push_u32(&mut out, stream.len() as u32);
+        }
+        out.extend(streams.concat());
+        out
+    }
+
+    /// テーブルをWOFF2にする（glyf/locaはtransformed_glyfで変換したものに置き換える）
+    /// fontsを指定するとコレクションにする
+    fn woff2(tables: &[SfntTable], glyf: Option<&[u8]>, fonts: Option<&[(u32, Vec<usize>)]>) -> Vec<u8> {
+        let mut directory = Vec::new();
+        let mut stream = Vec::new();
+        for table in tables {
+            let index = WOFF2_KNOWN_TAGS.iter().position(|tag| **tag == table.tag).unwrap();
+            let transformed = match &table.tag {
+                b"glyf" => glyf,
+                b"loca" => glyf.map(|_| &[][..]),
+                _ => None,
+            };
+            match transformed {
+                Some(data) => {
+                    directory.push(index as u8);
+                    write_base128(&mut directory, table.data.len() as u32);
+                    write_base128(&mut directory, data.len() as u32);
+                    stream.extend_from_slice(data);
+                }
+                None => {
+                    let version = if &table.tag == b"glyf" || &table.tag == b"loca" { 3 } else { 0 };
+                    directory.push(index as u8 | version << 6);
+                    write_base128(&mut directory, table.data.len() as u32);
+                    stream.extend_from_slice(&table.data);
+                }
+            }
+        }
+        if let Some(fonts) = fonts {
+            push_u32(&mut directory, 0x0001_0000);
+            write_255_u16(&mut directory, fonts.len() as u16);
+            for (flavor, indices) in fonts {
+                write_255_u16(&mut directory, indices.len() as u16);
+                push_u32(&mut directory, *flavor);
+                for &index in indices {
+                    write_255_u16(&mut directory, index as u16);
+                }
+            }
+        }
+
+        let mut compressed = Vec::new();
+        brotli::BrotliCompress(&mut stream.as_slice(), &mut compressed, &Default::default()).unwrap();
+
+        let mut out = WOFF2_SIGNATURE.to_vec();
+        push_u32(&mut out, if fonts.is_some() { TTC_FLAVOR } else { 0x0001_0000 });
+        push_u32(&mut out, (48 + directory.len() + compressed.len()) as u32);
+        push_u16(&mut out, tables.len() as u16);
+        push_u16(&mut out, 0);
+        push_u32(&mut out, 0);
+        push_u32(&mut out, compressed.len() as u32);
+        out.resize(48, 0);
+        out.extend(directory);
+        out.extend(compressed);
+        out
+    }
+
+    fn test_glyphs() -> Vec<TestGlyph> {
+        vec![
+            TestGlyph::Simple(vec![]),
+            TestGlyph::Simple(vec![vec![(0, 0), (100, 0), (100, 100), (0, 100)]]),
+            TestGlyph::Simple(vec![
+                vec![(-50, -20), (300, -20), (120, 700)],
+                vec![(10, 10), (20, 10), (20, 20)],
+            ]),
+            TestGlyph::Composite(vec![(1, 0, 0), (2, 200, -300)]),
+        ]
+    }
+
+    fn assert_same_glyphs(decoded: &Face, original: &Face) {
+        assert_eq!(decoded.number_of_glyphs(), original.number_of_glyphs());
+        for glyph in 0..original.number_of_glyphs() {
+            assert_eq!(outline(decoded, glyph), outline(original, glyph));
+            assert_eq!(
+                decoded.glyph_hor_advance(GlyphId(glyph)),
+                original.glyph_hor_advance(GlyphId(glyph))
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips_encoded_woff2() {
+        let font = truetype(&test_glyphs(), &[('A', 1), ('B', 2), ('C', 3)], Vec::new());
+        let tables = sfnt_tables(&font, 0);
+
+        let decoded = decode_if_needed(encode_woff2(0x0001_0000, &tables).unwrap()).unwrap();
+        let decoded_tables = sfnt_tables(&decoded, 0);
+        assert_eq!(decoded_tables.len(), tables.len());
+        for (decoded, original) in decoded_tables.iter().zip(&tables) {
+            assert_eq!(decoded.tag, original.tag);
+            assert_eq!(decoded.data, original.data);
+        }
+    }
+
+    #[test]
+    fn reconstructs_transformed_glyf() {
+        let glyphs = test_glyphs();
+        let font = truetype(&glyphs, &[('A', 1), ('B', 2), ('C', 3)], Vec::new());
+        let glyf = transformed_glyf(&glyphs, 0);
+
+        let decoded = decode_if_needed(woff2(&sfnt_tables(&font, 0), Some(&glyf), None)).unwrap();
+        let face = Face::parse(&decoded, 0).unwrap();
+        assert_same_glyphs(&face, &Face::parse(&font, 0).unwrap());
+        // 外接矩形は点から求め直される
+        let bbox = face.glyph_bounding_box(GlyphId(2)).unwrap();
+        assert_eq!((bbox.x_min, bbox.y_min, bbox.x_max, bbox.y_max), (-50, -20, 300, 700));
+    }
+
+    #[test]
+    fn switches_to_long_loca_when_glyf_outgrows_short_offsets() {
+        // 1点あたり5バイト前後になる大きな差分の点で、glyfを短い形式の上限（128KB）より大きくする
+        let contour: Vec<(i16, i16)> = (0..600)
+            .map(|i| ((i % 2) * 1000, ((i / 2) % 2) * 1000 + i % 7))
+            .collect();
+        let mut glyphs = vec![TestGlyph::Simple(vec![])];
+        glyphs.extend((0..60).map(|_| TestGlyph::Simple(vec![contour.clone()])));
+        let font = truetype(&glyphs, &[('A', 60)], Vec::new());
+        let glyf = transformed_glyf(&glyphs, 0);
+
+        let decoded = decode_if_needed(woff2(&sfnt_tables(&font, 0), Some(&glyf), None)).unwrap();
+        let face = Face::parse(&decoded, 0).unwrap();
+        assert_eq!(
+            face.tables().head.index_to_location_format,
+            ttf_parser::head::IndexToLocationFormat::Long
+        );
+        assert!(face.raw_face().table(ttf_parser::Tag::from_bytes(b"glyf")).unwrap().len() > 0x1FFFE);
+        assert_same_glyphs(&face, &Face::parse(&font, 0).unwrap());
+    }
+
+    #[test]
+    fn decodes_collection_sharing_tables() {
+        let glyphs = test_glyphs();
+        let first = truetype(&glyphs, &[('A', 1), ('B', 2)], Vec::new());
+        let second = truetype(&glyphs, &[('A', 3)], Vec::new());
+
+        // 2つ目のフォントはcmapだけが異なる
+        let mut tables = sfnt_tables(&first, 0);
+        let cmap = sfnt_tables(&second, 0).into_iter().find(|table| &table.tag == b"cmap").unwrap();
+        let first_indices: Vec<usize> = (0..tables.len()).collect();
+        let second_indices: Vec<usize> = tables
+            .iter()
+            .enumerate()
+            .map(|(i, table)| if &table.tag == b"cmap" { tables.len() } else { i })
+            .collect();
+        tables.push(cmap);
+        let fonts = [(0x0001_0000, first_indices), (0x0001_0000, second_indices)];
+        let glyf = transformed_glyf(&glyphs, 0);
+
+        let decoded = decode_if_needed(woff2(&tables, Some(&glyf), Some(&fonts))).unwrap();
+        assert_eq!(ttf_parser::fonts_in_collection(&decoded), Some(2));
+        let first_face = Face::parse(&decoded, 0).unwrap();
+        let second_face = Face::parse(&decoded, 1).unwrap();
+        assert_eq!(first_face.glyph_index('B'), Some(GlyphId(2)));
+        assert_eq!(second_face.glyph_index('A'), Some(GlyphId(3)));
+        assert_eq!(second_face.glyph_index('B'), None);
+        assert_same_glyphs(&second_face, &Face::parse(&second, 0).unwrap());
+    }
+}
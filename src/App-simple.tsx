@@ -1,6 +1,7 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import "./App.css";
+import { FontFamilyInfo } from "./types";
 
 function App() {
   const [fonts, setFonts] = useState<string[]>([]);
@@ -10,7 +11,8 @@ function App() {
     async function loadFonts() {
       try {
         console.log("Fetching fonts...");
-        const systemFonts = await invoke<string[]>("get_system_fonts");
+        const fontFamilies = await invoke<FontFamilyInfo[]>("get_system_fonts");
+        const systemFonts = fontFamilies.map((f) => f.family);
         console.log("Fonts loaded:", systemFonts.length);
         setFonts(systemFonts);
         setMessage(`Loaded ${systemFonts.length} fonts`);
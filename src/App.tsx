@@ -1,7 +1,7 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import "./App.css";
-import { AppState, FavoritesData, StrokeLayer } from "./types";
+import { AppState, FavoritesData, FontFamilyInfo, FontLocation, StrokeLayer } from "./types";
 import { loadFavorites, saveFavorites, loadAppState, saveAppState } from "./storage";
 import FontList from "./components/FontList";
 import PreviewArea from "./components/PreviewArea";
@@ -9,6 +9,7 @@ import ControlPanel from "./components/ControlPanel";
 
 function App() {
   const [fonts, setFonts] = useState<string[]>([]);
+  const [userFonts, setUserFonts] = useState<Record<string, FontLocation>>({});
   const [selectedFont, setSelectedFont] = useState<string | null>(null);
   const [textInput, setTextInput] = useState("サンプルテキスト");
   const [fontSize, setFontSize] = useState(48);
@@ -30,13 +31,36 @@ function App() {
   const [filterText, setFilterText] = useState("");
   const [showFavoritesOnly, setShowFavoritesOnly] = useState(false);
 
+  // 追加したフォントファイルはOSにインストールされていないため、プレビュー用にファイルの場所を覚えておく
+  const updateUserFonts = (fontFamilies: FontFamilyInfo[]) => {
+    const locations: Record<string, FontLocation> = {};
+    for (const family of fontFamilies) {
+      const face = family.faces.find((f) => f.user_font);
+      if (face) locations[family.family] = face.location;
+    }
+    setUserFonts(locations);
+  };
+
+  // フォントリストを再取得（フォントファイル・フォルダの追加後）
+  const reloadFonts = async () => {
+    try {
+      const fontFamilies = await invoke<FontFamilyInfo[]>("get_system_fonts");
+      setFonts(fontFamilies.map((f) => f.family));
+      updateUserFonts(fontFamilies);
+    } catch (error) {
+      console.error("Failed to reload fonts:", error);
+    }
+  };
+
   // 初期化：フォントリストと保存された設定を読み込み
   useEffect(() => {
     async function initialize() {
       try {
         // フォントリストを取得
-        const systemFonts = await invoke<string[]>("get_system_fonts");
+        const fontFamilies = await invoke<FontFamilyInfo[]>("get_system_fonts");
+        const systemFonts = fontFamilies.map((f) => f.family);
         setFonts(systemFonts);
+        updateUserFonts(fontFamilies);
 
         // お気に入りを読み込み
         const savedFavorites = await loadFavorites();
@@ -100,11 +124,13 @@ function App() {
         onFilterTextChange={setFilterText}
         showFavoritesOnly={showFavoritesOnly}
         onShowFavoritesOnlyChange={setShowFavoritesOnly}
+        onFontSourcesChanged={reloadFonts}
       />
 
       {/* 中央 - プレビューエリア */}
       <PreviewArea
         selectedFont={selectedFont}
+        userFontLocation={selectedFont ? userFonts[selectedFont] ?? null : null}
         textInput={textInput}
         fontSize={fontSize}
         textColor={textColor}
@@ -1,5 +1,7 @@
-import { useState } from "react";
-import { FavoritesData } from "../types";
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { open } from "@tauri-apps/plugin-dialog";
+import { FavoritesData, FontFamilyInfo, FontFilter } from "../types";
 
 interface FontListProps {
   fonts: string[];
@@ -11,6 +13,7 @@ interface FontListProps {
   onFilterTextChange: (text: string) => void;
   showFavoritesOnly: boolean;
   onShowFavoritesOnlyChange: (show: boolean) => void;
+  onFontSourcesChanged: () => void;
 }
 
 export default function FontList({
@@ -23,11 +26,74 @@ export default function FontList({
   onFilterTextChange,
   showFavoritesOnly,
   onShowFavoritesOnlyChange,
+  onFontSourcesChanged,
 }: FontListProps) {
   const [selectedCategory, setSelectedCategory] = useState<string>("デフォルト");
   const [newCategoryName, setNewCategoryName] = useState("");
   const [showCategoryManagement, setShowCategoryManagement] = useState(false);
   const [filterByCategory, setFilterByCategory] = useState<string | null>(null);
+  const [showFeatureFilter, setShowFeatureFilter] = useState(false);
+  const [fontFilter, setFontFilter] = useState<FontFilter>({ scripts: [] });
+  // 機能フィルターに合うファミリー（条件が無い場合はnull）
+  const [matchingFamilies, setMatchingFamilies] = useState<Set<string> | null>(null);
+  const [isSearching, setIsSearching] = useState(false);
+
+  const hasFeatureFilter = !!(
+    fontFilter.characters?.trim() ||
+    fontFilter.scripts?.length ||
+    fontFilter.has_vertical_metrics ||
+    fontFilter.monospaced ||
+    fontFilter.variable
+  );
+
+  useEffect(() => {
+    if (!hasFeatureFilter) {
+      setMatchingFamilies(null);
+      return;
+    }
+
+    let cancelled = false;
+    setIsSearching(true);
+    invoke<FontFamilyInfo[]>("search_system_fonts", { filter: fontFilter })
+      .then((families) => {
+        if (!cancelled) setMatchingFamilies(new Set(families.map((f) => f.family)));
+      })
+      .catch((error) => console.error("Failed to search fonts:", error))
+      .finally(() => {
+        if (!cancelled) setIsSearching(false);
+      });
+
+    return () => {
+      cancelled = true;
+    };
+  }, [fontFilter, hasFeatureFilter]);
+
+  // インストールされていないフォントファイル・フォルダを登録
+  const addFontSources = async (directory: boolean) => {
+    const selected = await open({
+      directory,
+      multiple: true,
+      filters: directory
+        ? undefined
+        : [{ name: "フォント", extensions: ["ttf", "otf", "ttc", "otc", "woff", "woff2"] }],
+    });
+    if (!selected) return;
+
+    try {
+      await invoke("add_font_sources", { paths: Array.isArray(selected) ? selected : [selected] });
+      onFontSourcesChanged();
+    } catch (error) {
+      console.error("Failed to add font sources:", error);
+    }
+  };
+
+  const toggleScript = (script: string) => {
+    const scripts = fontFilter.scripts ?? [];
+    setFontFilter({
+      ...fontFilter,
+      scripts: scripts.includes(script) ? scripts.filter((s) => s !== script) : [...scripts, script],
+    });
+  };
 
   const isFavorite = (fontName: string): boolean => {
     return Object.values(favorites.categories).some((fonts) => fonts.includes(fontName));
@@ -101,7 +167,9 @@ export default function FontList({
       favorites.categories[filterByCategory]?.includes(font)
     );
 
-    return matchesSearch && matchesFavorites && matchesCategory && matchesCategoryFilter;
+    const matchesFeatures = !matchingFamilies || matchingFamilies.has(font);
+
+    return matchesSearch && matchesFavorites && matchesCategory && matchesCategoryFilter && matchesFeatures;
   });
 
   return (
@@ -129,6 +197,89 @@ export default function FontList({
           <span>お気に入りのみ表示</span>
         </label>
 
+        {/* フォントファイル・フォルダの追加 */}
+        <div className="flex gap-2 mb-2">
+          <button
+            onClick={() => addFontSources(false)}
+            className="flex-1 px-2 py-1 bg-gray-100 hover:bg-gray-200 rounded-md text-xs"
+          >
+            ファイルを追加
+          </button>
+          <button
+            onClick={() => addFontSources(true)}
+            className="flex-1 px-2 py-1 bg-gray-100 hover:bg-gray-200 rounded-md text-xs"
+          >
+            フォルダを追加
+          </button>
+        </div>
+
+        {/* 対応文字・機能フィルター */}
+        <button
+          onClick={() => setShowFeatureFilter(!showFeatureFilter)}
+          className="w-full px-3 py-2 bg-gray-100 hover:bg-gray-200 rounded-md text-sm mb-2"
+        >
+          {showFeatureFilter ? "▼ 対応文字・機能で絞り込み" : "▶ 対応文字・機能で絞り込み"}
+          {isSearching && " （検索中...）"}
+        </button>
+
+        {showFeatureFilter && (
+          <div className="mb-2 p-2 bg-gray-50 rounded-md text-sm space-y-1">
+            <input
+              type="text"
+              placeholder="収録が必要な文字"
+              value={fontFilter.characters ?? ""}
+              onChange={(e) => setFontFilter({ ...fontFilter, characters: e.target.value })}
+              className="w-full px-2 py-1 border border-gray-300 rounded text-sm"
+            />
+            <div className="flex flex-wrap gap-x-3">
+              {[
+                ["Hira", "ひらがな"],
+                ["Kana", "カタカナ"],
+                ["Hani", "常用漢字"],
+              ].map(([script, label]) => (
+                <label key={script} className="flex items-center">
+                  <input
+                    type="checkbox"
+                    checked={fontFilter.scripts?.includes(script) ?? false}
+                    onChange={() => toggleScript(script)}
+                    className="mr-1"
+                  />
+                  <span>{label}</span>
+                </label>
+              ))}
+            </div>
+            <div className="flex flex-wrap gap-x-3">
+              <label className="flex items-center">
+                <input
+                  type="checkbox"
+                  checked={fontFilter.has_vertical_metrics ?? false}
+                  onChange={(e) => setFontFilter({ ...fontFilter, has_vertical_metrics: e.target.checked })}
+                  className="mr-1"
+                />
+                <span>縦書きメトリクス</span>
+              </label>
+              <label className="flex items-center">
+                <input
+                  type="checkbox"
+                  checked={fontFilter.monospaced ?? false}
+                  onChange={(e) => setFontFilter({ ...fontFilter, monospaced: e.target.checked })}
+                  className="mr-1"
+                />
+                <span>等幅</span>
+              </label>
+              <label className="flex items-center">
+                <input
+                  type="checkbox"
+                  checked={fontFilter.variable ?? false}
+                  onChange={(e) => setFontFilter({ ...fontFilter, variable: e.target.checked })}
+                  className="mr-1"
+                />
+                <span>可変</span>
+              </label>
+            </div>
+          </div>
+        )}
+
         {/* カテゴリ管理 */}
         <button
           onClick={() => setShowCategoryManagement(!showCategoryManagement)}
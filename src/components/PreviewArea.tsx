@@ -1,13 +1,40 @@
 import { useRef, useEffect, useState } from "react";
-import { StrokeLayer } from "../types";
+import { DxfExportResult, EpsExportResult, FileExportResult, FontLocation, MissingGlyph, PlotterExportResult, StrokeLayer, SubsetExportResult, SvgExportResult } from "../types";
 import { convertFileSrc } from "@tauri-apps/api/core";
 import { invoke } from "@tauri-apps/api/core";
 import { save } from "@tauri-apps/plugin-dialog";
-import { writeTextFile } from "@tauri-apps/plugin-fs";
+import { writeFile, writeTextFile } from "@tauri-apps/plugin-fs";
 import jsxContent from "../assets/load-svg-as-shape.jsx?raw";
 
+// サブセットに追加できる文字集合（get_font_coverageのIDと同じ）
+const SUBSET_CHARSETS = [
+  { id: "latin1", name: "Latin-1（英数字・記号を含む）" },
+  { id: "joyo", name: "常用漢字" },
+  { id: "jisx0208_level1", name: "JIS第1水準" },
+  { id: "jisx0208_level2", name: "JIS第2水準" },
+];
+
+const EXPORT_FORMATS = ["svg", "png", "pdf", "eps", "dxf", "gcode", "hpgl", "stl", "obj", "glb"] as const;
+type ExportFormat = typeof EXPORT_FORMATS[number];
+
+// 保存するファイルの拡張子（形式名と異なるもの）
+const EXPORT_EXTENSIONS: Partial<Record<ExportFormat, string>> = { hpgl: "plt" };
+
+// PNG出力の解像度の選択肢（SVGの1pxを1/96インチとする）
+const PNG_DPI_OPTIONS = [72, 96, 144, 192, 300];
+
+// DXF・G-code・HPGL出力で曲線を折れ線にするときの許容誤差の選択肢（mm）
+const DXF_TOLERANCE_OPTIONS = [0.01, 0.05, 0.1, 0.2];
+
+const formatMissingGlyphs = (missingGlyphs: MissingGlyph[]) =>
+  missingGlyphs
+    .map((m) => `${m.character}（U+${m.codepoint.toString(16).toUpperCase().padStart(4, "0")}）`)
+    .join("、");
+
 interface PreviewAreaProps {
   selectedFont: string | null;
+  /** 追加したフォントファイルの場合の場所（WOFF/WOFF2も含む） */
+  userFontLocation: FontLocation | null;
   textInput: string;
   fontSize: number;
   textColor: string;
@@ -20,6 +47,7 @@ interface PreviewAreaProps {
 
 export default function PreviewArea({
   selectedFont,
+  userFontLocation,
   textInput,
   fontSize,
   textColor,
@@ -31,9 +59,56 @@ export default function PreviewArea({
 }: PreviewAreaProps) {
   const canvasRef = useRef<HTMLCanvasElement>(null);
   const [isExporting, setIsExporting] = useState(false);
+  const [exportFormat, setExportFormat] = useState<ExportFormat>("svg");
   const [exportMode, setExportMode] = useState<"path_only" | "fill" | "fill_and_stroke">("fill");
+  const [pngDpi, setPngDpi] = useState(144);
+  const [pngTransparent, setPngTransparent] = useState(false);
+  const [cmykOutput, setCmykOutput] = useState(false);
+  const [strokeJoin, setStrokeJoin] = useState<"round" | "miter" | "bevel">("round");
+  const [outlineStrokes, setOutlineStrokes] = useState(false);
+  const [unionOutlines, setUnionOutlines] = useState(false);
+  const [layerGroups, setLayerGroups] = useState(false);
+  const [dxfTextHeight, setDxfTextHeight] = useState(10);
+  const [dxfCurveMode, setDxfCurveMode] = useState<"polyline" | "spline">("polyline");
+  const [dxfTolerance, setDxfTolerance] = useState(0.05);
+  const [gcodeFeedRate, setGcodeFeedRate] = useState(1000);
+  const [gcodePenUp, setGcodePenUp] = useState("G0 Z5");
+  const [gcodePenDown, setGcodePenDown] = useState("G1 Z0");
+  const [optimizeTravel, setOptimizeTravel] = useState(true);
+  const [meshDepth, setMeshDepth] = useState(3);
+  const [meshBevel, setMeshBevel] = useState(0);
   const [showExportPanel, setShowExportPanel] = useState(false);
   const [exportError, setExportError] = useState<string | null>(null);
+  const [exportWarnings, setExportWarnings] = useState<string[]>([]);
+  const [allowRestrictedLicense, setAllowRestrictedLicense] = useState(false);
+  const [failOnMissingGlyphs, setFailOnMissingGlyphs] = useState(false);
+  const [loadedUserFont, setLoadedUserFont] = useState<string | null>(null);
+  const [showSubsetPanel, setShowSubsetPanel] = useState(false);
+  const [subsetFormat, setSubsetFormat] = useState<"sfnt" | "woff2">("woff2");
+  const [subsetCharsets, setSubsetCharsets] = useState<string[]>([]);
+  const [subsetError, setSubsetError] = useState<string | null>(null);
+  const [subsetMessages, setSubsetMessages] = useState<string[]>([]);
+
+  // 追加したフォントファイルはWebViewに読み込んでプレビューに使う
+  useEffect(() => {
+    if (!selectedFont || !userFontLocation) return;
+
+    let cancelled = false;
+    const face = new FontFace(selectedFont, `url("${convertFileSrc(userFontLocation.path)}")`);
+    face.load()
+      .then((loaded) => {
+        if (cancelled) return;
+        document.fonts.add(loaded);
+        setLoadedUserFont(selectedFont);
+      })
+      .catch((error) => {
+        console.error("Failed to load font file for preview:", error);
+      });
+
+    return () => {
+      cancelled = true;
+    };
+  }, [selectedFont, userFontLocation]);
 
   // 横書きプレビュー用のCanvas描画
   useEffect(() => {
@@ -127,7 +202,7 @@ export default function PreviewArea({
     return () => {
       window.removeEventListener("resize", resizeCanvas);
     };
-  }, [selectedFont, textInput, fontSize, textColor, bgColor, useBgImage, bgImagePath, strokeLayers, isVertical]);
+  }, [selectedFont, loadedUserFont, textInput, fontSize, textColor, bgColor, useBgImage, bgImagePath, strokeLayers, isVertical]);
 
   const exportToSvg = async () => {
     if (!selectedFont || !textInput) {
@@ -137,19 +212,31 @@ export default function PreviewArea({
 
     setIsExporting(true);
     setExportError(null);
+    setExportWarnings([]);
 
     try {
-      const svgContent = await invoke<string>("generate_svg", {
+      const result = await invoke<SvgExportResult>("generate_svg", {
         request: {
           font_name: selectedFont,
+          font_location: userFontLocation,
           text: textInput,
           font_size: fontSize,
           text_color: textColor,
           stroke_layers: strokeLayers,
           export_mode: exportMode,
           vertical: isVertical,
+          allow_restricted_license: allowRestrictedLicense,
+          fail_on_missing_glyphs: failOnMissingGlyphs,
+          union_outlines: unionOutlines,
+          stroke_join: strokeJoin,
+          outline_strokes: outlineStrokes,
+          layer_groups: layerGroups,
         }
       });
+      const missingWarnings = result.missing_glyphs.length > 0
+        ? [`フォントに無い文字は出力されません: ${formatMissingGlyphs(result.missing_glyphs)}`]
+        : [];
+      setExportWarnings([...result.warnings.map((w) => w.message), ...missingWarnings]);
 
       const savePath = await save({
         defaultPath: "text-export.svg",
@@ -160,8 +247,11 @@ export default function PreviewArea({
       });
 
       if (savePath) {
-        await writeTextFile(savePath, svgContent);
-        setShowExportPanel(false);
+        await writeTextFile(savePath, result.svg);
+        // 警告がある場合はパネルを開いたままにして内容を見せる
+        if (result.warnings.length === 0 && result.missing_glyphs.length === 0) {
+          setShowExportPanel(false);
+        }
       }
 
     } catch (error) {
@@ -172,6 +262,175 @@ export default function PreviewArea({
     }
   };
 
+  // バックエンドで生成したPNG・PDF・EPS・DXF・G-code・HPGL・3Dモデルを保存
+  const exportToFile = async (format: Exclude<ExportFormat, "svg">) => {
+    if (!selectedFont || !textInput) {
+      setExportError("フォントとテキストを選択してください");
+      return;
+    }
+
+    setIsExporting(true);
+    setExportError(null);
+    setExportWarnings([]);
+
+    // 形式ごとの追加設定
+    const formatOptions = (() => {
+      switch (format) {
+        case "png":
+          return {
+            dpi: pngDpi,
+            background_color: bgColor,
+            background_image_path: useBgImage ? bgImagePath : null,
+            transparent: pngTransparent,
+          };
+        case "dxf":
+          return {
+            text_height_mm: dxfTextHeight,
+            curve_mode: dxfCurveMode,
+            tolerance_mm: dxfTolerance,
+          };
+        case "gcode":
+        case "hpgl":
+          return {
+            format,
+            text_height_mm: dxfTextHeight,
+            tolerance_mm: dxfTolerance,
+            feed_rate: gcodeFeedRate,
+            pen_up: gcodePenUp,
+            pen_down: gcodePenDown,
+            keep_contour_order: !optimizeTravel,
+          };
+        case "stl":
+        case "obj":
+        case "glb":
+          return {
+            format,
+            text_height_mm: dxfTextHeight,
+            tolerance_mm: dxfTolerance,
+            depth_mm: meshDepth,
+            bevel_mm: meshBevel,
+          };
+        default:
+          return { cmyk: cmykOutput };
+      }
+    })();
+
+    try {
+      const command = format === "gcode" || format === "hpgl"
+        ? "generate_plotter"
+        : format === "stl" || format === "obj" || format === "glb"
+          ? "generate_mesh"
+          : `generate_${format}`;
+      const result = await invoke<FileExportResult | EpsExportResult | DxfExportResult | PlotterExportResult>(command, {
+        request: {
+          font_name: selectedFont,
+          font_location: userFontLocation,
+          text: textInput,
+          font_size: fontSize,
+          text_color: textColor,
+          stroke_layers: strokeLayers,
+          export_mode: exportMode,
+          vertical: isVertical,
+          allow_restricted_license: allowRestrictedLicense,
+          fail_on_missing_glyphs: failOnMissingGlyphs,
+          union_outlines: unionOutlines,
+          stroke_join: strokeJoin,
+          ...formatOptions,
+        }
+      });
+      const missingWarnings = result.missing_glyphs.length > 0
+        ? [`フォントに無い文字は出力されません: ${formatMissingGlyphs(result.missing_glyphs)}`]
+        : [];
+      setExportWarnings([...result.warnings.map((w) => w.message), ...missingWarnings]);
+
+      const extension = EXPORT_EXTENSIONS[format] ?? format;
+      const savePath = await save({
+        defaultPath: `text-export.${extension}`,
+        filters: [{
+          name: format.toUpperCase(),
+          extensions: [extension]
+        }]
+      });
+
+      if (savePath) {
+        if ("eps" in result) {
+          await writeTextFile(savePath, result.eps);
+        } else if ("dxf" in result) {
+          await writeTextFile(savePath, result.dxf);
+        } else if ("commands" in result) {
+          await writeTextFile(savePath, result.commands);
+        } else {
+          await writeFile(savePath, new Uint8Array(result.data));
+        }
+        if (result.warnings.length === 0 && result.missing_glyphs.length === 0) {
+          setShowExportPanel(false);
+        }
+      }
+    } catch (error) {
+      console.error(`${format.toUpperCase()} export failed:`, error);
+      setExportError(`エクスポート失敗: ${error}`);
+    } finally {
+      setIsExporting(false);
+    }
+  };
+
+  // テキストに必要なグリフだけを残したフォントを保存
+  const exportSubsetFont = async () => {
+    if (!selectedFont || (!textInput && subsetCharsets.length === 0)) {
+      setSubsetError("フォントとテキストを選択してください");
+      return;
+    }
+
+    setIsExporting(true);
+    setSubsetError(null);
+    setSubsetMessages([]);
+
+    try {
+      const result = await invoke<SubsetExportResult>("export_font_subset", {
+        request: {
+          font_name: selectedFont,
+          font_location: userFontLocation,
+          text: textInput,
+          charsets: subsetCharsets,
+          vertical: isVertical,
+          format: subsetFormat,
+          allow_restricted_license: allowRestrictedLicense,
+        }
+      });
+      const missingWarnings = result.missing_glyphs.length > 0
+        ? [`フォントに無い文字は含まれません: ${formatMissingGlyphs(result.missing_glyphs)}`]
+        : [];
+      setSubsetMessages([
+        `${result.total_glyphs}グリフ中${result.glyph_count}グリフを出力します（${(result.size / 1024).toFixed(1)} KB）`,
+        ...result.warnings.map((w) => w.message),
+        ...missingWarnings,
+      ]);
+
+      const savePath = await save({
+        defaultPath: `${selectedFont}-subset.${result.extension}`,
+        filters: [{
+          name: result.extension.toUpperCase(),
+          extensions: [result.extension]
+        }]
+      });
+
+      if (savePath) {
+        await writeFile(savePath, new Uint8Array(result.data));
+      }
+    } catch (error) {
+      console.error("Font subset export failed:", error);
+      setSubsetError(`エクスポート失敗: ${error}`);
+    } finally {
+      setIsExporting(false);
+    }
+  };
+
+  const toggleSubsetCharset = (id: string) => {
+    setSubsetCharsets((current) =>
+      current.includes(id) ? current.filter((c) => c !== id) : [...current, id]
+    );
+  };
+
   // Photoshop用JSXスクリプトを保存
   const saveJsxScript = async () => {
     try {
@@ -229,15 +488,132 @@ export default function PreviewArea({
           PS用JSX
         </button>
         <button
-          onClick={() => setShowExportPanel(!showExportPanel)}
+          onClick={() => {
+            setShowSubsetPanel(!showSubsetPanel);
+            setShowExportPanel(false);
+          }}
+          className="px-3 py-1.5 bg-teal-500 text-white text-sm rounded hover:bg-teal-600 shadow"
+          title="テキストに使う文字だけを含むフォントを保存"
+        >
+          フォント出力
+        </button>
+        <button
+          onClick={() => {
+            setShowExportPanel(!showExportPanel);
+            setShowSubsetPanel(false);
+          }}
           className="px-3 py-1.5 bg-blue-500 text-white text-sm rounded hover:bg-blue-600 shadow"
         >
-          SVG出力
+          画像出力
         </button>
 
+        {showSubsetPanel && (
+          <div className="absolute top-10 right-0 bg-white rounded-lg shadow-lg p-4 min-w-[240px]">
+            <h3 className="font-bold text-sm mb-3">サブセットフォント出力</h3>
+
+            <div className="mb-3">
+              <label className="block text-xs text-gray-600 mb-1">形式</label>
+              <div className="space-y-1">
+                <label className="flex items-center cursor-pointer">
+                  <input
+                    type="radio"
+                    name="subsetFormat"
+                    value="woff2"
+                    checked={subsetFormat === "woff2"}
+                    onChange={() => setSubsetFormat("woff2")}
+                    className="mr-2"
+                  />
+                  <span className="text-sm">WOFF2（Web用）</span>
+                </label>
+                <label className="flex items-center cursor-pointer">
+                  <input
+                    type="radio"
+                    name="subsetFormat"
+                    value="sfnt"
+                    checked={subsetFormat === "sfnt"}
+                    onChange={() => setSubsetFormat("sfnt")}
+                    className="mr-2"
+                  />
+                  <span className="text-sm">TTF / OTF</span>
+                </label>
+              </div>
+            </div>
+
+            <div className="mb-3">
+              <label className="block text-xs text-gray-600 mb-1">テキストに加えて含める文字</label>
+              <div className="space-y-1">
+                {SUBSET_CHARSETS.map((charset) => (
+                  <label key={charset.id} className="flex items-center cursor-pointer">
+                    <input
+                      type="checkbox"
+                      checked={subsetCharsets.includes(charset.id)}
+                      onChange={() => toggleSubsetCharset(charset.id)}
+                      className="mr-2"
+                    />
+                    <span className="text-sm">{charset.name}</span>
+                  </label>
+                ))}
+              </div>
+            </div>
+
+            <label className="flex items-center cursor-pointer mb-3">
+              <input
+                type="checkbox"
+                checked={allowRestrictedLicense}
+                onChange={(e) => setAllowRestrictedLicense(e.target.checked)}
+                className="mr-2"
+              />
+              <span className="text-xs text-gray-600">埋め込み制限のあるフォントも出力する</span>
+            </label>
+
+            <button
+              onClick={exportSubsetFont}
+              disabled={isExporting || !selectedFont}
+              className={`w-full px-3 py-2 rounded text-white text-sm ${
+                isExporting || !selectedFont
+                  ? "bg-gray-400 cursor-not-allowed"
+                  : "bg-green-500 hover:bg-green-600"
+              }`}
+            >
+              {isExporting ? "エクスポート中..." : "フォントとして保存"}
+            </button>
+
+            {subsetError && (
+              <p className="text-xs text-red-500 mt-2">
+                {subsetError}
+              </p>
+            )}
+
+            {subsetMessages.map((message, i) => (
+              <p key={i} className="text-xs text-gray-600 mt-2">
+                {message}
+              </p>
+            ))}
+          </div>
+        )}
+
         {showExportPanel && (
           <div className="absolute top-10 right-0 bg-white rounded-lg shadow-lg p-4 min-w-[240px]">
-            <h3 className="font-bold text-sm mb-3">SVGエクスポート設定</h3>
+            <h3 className="font-bold text-sm mb-3">エクスポート設定</h3>
+
+            <div className="mb-3">
+              <label className="block text-xs text-gray-600 mb-1">形式</label>
+              <div className="flex flex-wrap gap-3">
+                {EXPORT_FORMATS.map((format) => (
+                  <label key={format} className="flex items-center cursor-pointer">
+                    <input
+                      type="radio"
+                      name="exportFormat"
+                      value={format}
+                      checked={exportFormat === format}
+                      onChange={() => setExportFormat(format)}
+                      className="mr-2"
+                    />
+                    <span className="text-sm">{format.toUpperCase()}</span>
+                  </label>
+                ))}
+              </div>
+            </div>
 
             <div className="mb-3">
               <label className="block text-xs text-gray-600 mb-1">出力モード</label>
@@ -278,8 +654,211 @@ export default function PreviewArea({
               </div>
             </div>
 
+            {["svg", "png", "pdf", "eps"].includes(exportFormat) && (
+              <div className="mb-3">
+                <label className="block text-xs text-gray-600 mb-1">ストロークの角</label>
+                <select
+                  value={strokeJoin}
+                  onChange={(e) => setStrokeJoin(e.target.value as "round" | "miter" | "bevel")}
+                  className="w-full px-2 py-1 border rounded text-sm"
+                >
+                  <option value="round">ラウンド</option>
+                  <option value="miter">マイター</option>
+                  <option value="bevel">ベベル</option>
+                </select>
+              </div>
+            )}
+
+            {exportFormat === "svg" && (
+              <div className="mb-3">
+                <label className="flex items-center cursor-pointer">
+                  <input
+                    type="checkbox"
+                    checked={outlineStrokes}
+                    onChange={(e) => setOutlineStrokes(e.target.checked)}
+                    className="mr-2"
+                  />
+                  <span className="text-xs text-gray-600">ストロークをアウトライン化する</span>
+                </label>
+                <label className="flex items-center cursor-pointer mt-1">
+                  <input
+                    type="checkbox"
+                    checked={layerGroups}
+                    onChange={(e) => setLayerGroups(e.target.checked)}
+                    className="mr-2"
+                  />
+                  <span className="text-xs text-gray-600">ストロークと塗りをレイヤーに分ける</span>
+                </label>
+              </div>
+            )}
+
+            {exportFormat === "png" && (
+              <div className="mb-3">
+                <label className="block text-xs text-gray-600 mb-1">解像度</label>
+                <select
+                  value={pngDpi}
+                  onChange={(e) => setPngDpi(Number(e.target.value))}
+                  className="w-full px-2 py-1 border rounded text-sm mb-2"
+                >
+                  {PNG_DPI_OPTIONS.map((dpi) => (
+                    <option key={dpi} value={dpi}>{dpi} dpi</option>
+                  ))}
+                </select>
+                <label className="flex items-center cursor-pointer">
+                  <input
+                    type="checkbox"
+                    checked={pngTransparent}
+                    onChange={(e) => setPngTransparent(e.target.checked)}
+                    className="mr-2"
+                  />
+                  <span className="text-xs text-gray-600">背景を透明にする</span>
+                </label>
+              </div>
+            )}
+
+            {(exportFormat === "pdf" || exportFormat === "eps") && (
+              <label className="flex items-center cursor-pointer mb-3">
+                <input
+                  type="checkbox"
+                  checked={cmykOutput}
+                  onChange={(e) => setCmykOutput(e.target.checked)}
+                  className="mr-2"
+                />
+                <span className="text-xs text-gray-600">CMYKで出力する（黒はK100）</span>
+              </label>
+            )}
+
+            {["dxf", "gcode", "hpgl", "stl", "obj", "glb"].includes(exportFormat) && (
+              <div className="mb-3">
+                <label className="block text-xs text-gray-600 mb-1">文字の高さ（1em、mm）</label>
+                <input
+                  type="number"
+                  min={0.1}
+                  step={0.1}
+                  value={dxfTextHeight}
+                  onChange={(e) => setDxfTextHeight(Number(e.target.value))}
+                  className="w-full px-2 py-1 border rounded text-sm mb-2"
+                />
+                {exportFormat === "dxf" && (
+                  <>
+                    <label className="block text-xs text-gray-600 mb-1">曲線</label>
+                    <select
+                      value={dxfCurveMode}
+                      onChange={(e) => setDxfCurveMode(e.target.value as "polyline" | "spline")}
+                      className="w-full px-2 py-1 border rounded text-sm mb-2"
+                    >
+                      <option value="polyline">折れ線（LWPOLYLINE）</option>
+                      <option value="spline">スプライン（SPLINE）</option>
+                    </select>
+                  </>
+                )}
+                {(exportFormat !== "dxf" || dxfCurveMode === "polyline") && (
+                  <select
+                    value={dxfTolerance}
+                    onChange={(e) => setDxfTolerance(Number(e.target.value))}
+                    className="w-full px-2 py-1 border rounded text-sm"
+                  >
+                    {DXF_TOLERANCE_OPTIONS.map((tolerance) => (
+                      <option key={tolerance} value={tolerance}>許容誤差 {tolerance} mm</option>
+                    ))}
+                  </select>
+                )}
+                {exportFormat === "gcode" && (
+                  <>
+                    <label className="block text-xs text-gray-600 mt-2 mb-1">送り速度（mm/min）</label>
+                    <input
+                      type="number"
+                      min={1}
+                      value={gcodeFeedRate}
+                      onChange={(e) => setGcodeFeedRate(Number(e.target.value))}
+                      className="w-full px-2 py-1 border rounded text-sm mb-2"
+                    />
+                    <label className="block text-xs text-gray-600 mb-1">ペンを上げる命令</label>
+                    <input
+                      type="text"
+                      value={gcodePenUp}
+                      onChange={(e) => setGcodePenUp(e.target.value)}
+                      className="w-full px-2 py-1 border rounded text-sm font-mono mb-2"
+                    />
+                    <label className="block text-xs text-gray-600 mb-1">ペンを下ろす命令</label>
+                    <input
+                      type="text"
+                      value={gcodePenDown}
+                      onChange={(e) => setGcodePenDown(e.target.value)}
+                      className="w-full px-2 py-1 border rounded text-sm font-mono"
+                    />
+                  </>
+                )}
+                {(exportFormat === "stl" || exportFormat === "obj" || exportFormat === "glb") && (
+                  <>
+                    <label className="block text-xs text-gray-600 mt-2 mb-1">厚さ（mm）</label>
+                    <input
+                      type="number"
+                      min={0.1}
+                      step={0.1}
+                      value={meshDepth}
+                      onChange={(e) => setMeshDepth(Number(e.target.value))}
+                      className="w-full px-2 py-1 border rounded text-sm mb-2"
+                    />
+                    <label className="block text-xs text-gray-600 mb-1">面取り（mm、0で面取りなし）</label>
+                    <input
+                      type="number"
+                      min={0}
+                      step={0.05}
+                      value={meshBevel}
+                      onChange={(e) => setMeshBevel(Number(e.target.value))}
+                      className="w-full px-2 py-1 border rounded text-sm"
+                    />
+                  </>
+                )}
+                {(exportFormat === "gcode" || exportFormat === "hpgl") && (
+                  <label className="flex items-center cursor-pointer mt-2">
+                    <input
+                      type="checkbox"
+                      checked={optimizeTravel}
+                      onChange={(e) => setOptimizeTravel(e.target.checked)}
+                      className="mr-2"
+                    />
+                    <span className="text-xs text-gray-600">移動距離が短くなる順に描く</span>
+                  </label>
+                )}
+              </div>
+            )}
+
+            {["svg", "dxf", "gcode", "hpgl"].includes(exportFormat) && (
+              <label className="flex items-center cursor-pointer mb-3">
+                <input
+                  type="checkbox"
+                  checked={unionOutlines}
+                  onChange={(e) => setUnionOutlines(e.target.checked)}
+                  className="mr-2"
+                />
+                <span className="text-xs text-gray-600">文字の重なりを結合する（レイヤーごとに1つのパス）</span>
+              </label>
+            )}
+
+            <label className="flex items-center cursor-pointer mb-3">
+              <input
+                type="checkbox"
+                checked={allowRestrictedLicense}
+                onChange={(e) => setAllowRestrictedLicense(e.target.checked)}
+                className="mr-2"
+              />
+              <span className="text-xs text-gray-600">埋め込み制限のあるフォントも出力する</span>
+            </label>
+
+            <label className="flex items-center cursor-pointer mb-3">
+              <input
+                type="checkbox"
+                checked={failOnMissingGlyphs}
+                onChange={(e) => setFailOnMissingGlyphs(e.target.checked)}
+                className="mr-2"
+              />
+              <span className="text-xs text-gray-600">フォントに無い文字がある場合は出力しない</span>
+            </label>
+
             <button
-              onClick={exportToSvg}
+              onClick={() => (exportFormat === "svg" ? exportToSvg() : exportToFile(exportFormat))}
               disabled={isExporting || !selectedFont || !textInput}
               className={`w-full px-3 py-2 rounded text-white text-sm ${
                 isExporting || !selectedFont || !textInput
@@ -287,7 +866,7 @@ export default function PreviewArea({
                   : "bg-green-500 hover:bg-green-600"
               }`}
             >
-              {isExporting ? "エクスポート中..." : "SVGとして保存"}
+              {isExporting ? "エクスポート中..." : `${exportFormat.toUpperCase()}として保存`}
             </button>
 
             {(!selectedFont || !textInput) && (
@@ -301,6 +880,12 @@ export default function PreviewArea({
                 {exportError}
               </p>
             )}
+
+            {exportWarnings.map((warning, i) => (
+              <p key={i} className="text-xs text-amber-600 mt-2">
+                {warning}
+              </p>
+            ))}
           </div>
         )}
       </div>
@@ -21,3 +21,146 @@ export interface AppState {
   strokeLayers: StrokeLayer[];
   isVertical: boolean;
 }
+
+export interface FontFeature {
+  tag: string;
+  enabled: boolean;
+  value?: number;
+  start?: number;
+  end?: number;
+}
+
+export interface FontLocation {
+  path: string;
+  font_index: number;
+}
+
+export interface FontFaceInfo {
+  postscript_name: string | null;
+  full_name: string;
+  weight: number;
+  stretch: number;
+  style: "normal" | "italic" | "oblique";
+  location: FontLocation;
+  user_font: boolean;
+}
+
+export interface FontFamilyInfo {
+  family: string;
+  faces: FontFaceInfo[];
+}
+
+export interface FontFilter {
+  name?: string;
+  characters?: string;
+  scripts?: string[];
+  has_vertical_metrics?: boolean;
+  monospaced?: boolean;
+  variable?: boolean;
+}
+
+export interface AxisCoordinate {
+  tag: string;
+  value: number;
+}
+
+export interface ExportWarning {
+  code: string;
+  message: string;
+}
+
+export interface FallbackFont {
+  font_name: string;
+  postscript_name?: string;
+  font_location?: FontLocation;
+}
+
+export interface MissingGlyph {
+  char_index: number;
+  character: string;
+  codepoint: number;
+}
+
+export interface SvgExportResult {
+  svg: string;
+  warnings: ExportWarning[];
+  missing_glyphs: MissingGlyph[];
+}
+
+/** generate_png・generate_pdfなどバイナリで返す書き出しの結果 */
+export interface FileExportResult {
+  data: number[];
+  warnings: ExportWarning[];
+  missing_glyphs: MissingGlyph[];
+}
+
+export interface PngExportResult extends FileExportResult {
+  width: number;
+  height: number;
+}
+
+export interface MeshExportResult extends FileExportResult {
+  triangle_count: number;
+}
+
+export interface EpsExportResult {
+  eps: string;
+  warnings: ExportWarning[];
+  missing_glyphs: MissingGlyph[];
+}
+
+export interface PlotterExportResult {
+  /** G-codeまたはHPGLの命令列 */
+  commands: string;
+  warnings: ExportWarning[];
+  missing_glyphs: MissingGlyph[];
+}
+
+export interface DxfExportResult {
+  dxf: string;
+  /** アウトライン全体の大きさ（mm） */
+  width_mm: number;
+  height_mm: number;
+  warnings: ExportWarning[];
+  missing_glyphs: MissingGlyph[];
+}
+
+export interface SubsetExportResult {
+  data: number[];
+  extension: "ttf" | "otf" | "woff2";
+  glyph_count: number;
+  total_glyphs: number;
+  /** 書き出したフォントのバイト数 */
+  size: number;
+  warnings: ExportWarning[];
+  missing_glyphs: MissingGlyph[];
+}
+
+export interface CharsetCoverage {
+  id: string;
+  name: string;
+  total: number;
+  covered: number;
+  missing: string;
+}
+
+export interface CidInfo {
+  registry: string | null;
+  ordering: string | null;
+  supplement: number;
+  number_of_glyphs: number;
+  adobe_japan1_supplement: number | null;
+}
+
+export interface FontCoverage {
+  total_codepoints: number;
+  charsets: CharsetCoverage[];
+  cid: CidInfo | null;
+}
+
+export interface CatalogRefreshSummary {
+  files: number;
+  faces: number;
+  updated_files: number;
+  removed_files: number;
+}